@@ -4,6 +4,19 @@ use zip::ZipArchive;
 
 const QADIM_LOG: &str = "tests/logs/qadim-20200427.zevtc";
 
+/// Read the zipped log at the given path and return its uncompressed contents.
+fn unzipped(path: &str) -> Vec<u8> {
+    let mut log_data = Vec::new();
+    let zip_data = fs::read(path).unwrap();
+    let mut archive = ZipArchive::new(io::Cursor::new(zip_data)).unwrap();
+    archive
+        .by_index(0)
+        .unwrap()
+        .read_to_end(&mut log_data)
+        .unwrap();
+    log_data
+}
+
 /// This benchmark tests the overall performance of process_file.
 ///
 /// This is important because for many applications that just want to read a log, this is the
@@ -45,15 +58,7 @@ fn zipped_qadim_ram_benchmark(c: &mut Criterion) {
 /// measures our parsing performance most accurately, assuming that all data is readily available,
 /// the only slowdown that remains is our parsing and processing algorithm.
 fn unzipped_qadim_benchmark(c: &mut Criterion) {
-    let mut log_data = Vec::new();
-    let zip_data = fs::read(QADIM_LOG).unwrap();
-    let mut archive = ZipArchive::new(io::Cursor::new(zip_data)).unwrap();
-    archive
-        .by_index(0)
-        .unwrap()
-        .read_to_end(&mut log_data)
-        .unwrap();
-    let log_data = &log_data;
+    let log_data = &unzipped(QADIM_LOG);
 
     let mut group = c.benchmark_group("parsing/qadim/unzipped");
 
@@ -65,6 +70,50 @@ fn unzipped_qadim_benchmark(c: &mut Criterion) {
     });
 }
 
+/// This benchmark tests raw::parse_bytes on a pre-extracted log.
+///
+/// This is the counterpart to unzipped_qadim_benchmark, but instead of going through a reader, the
+/// events are decoded directly from the in-memory buffer. This is what you get when you have a
+/// memory mapped file.
+fn unzipped_qadim_bytes_benchmark(c: &mut Criterion) {
+    let log_data = &unzipped(QADIM_LOG);
+
+    let mut group = c.benchmark_group("parsing/qadim/unzipped");
+
+    group.throughput(Throughput::Bytes(log_data.len() as u64));
+    group.bench_function("bytes", |b| {
+        b.iter(|| {
+            let raw_evtc = evtclib::raw::parse_bytes(black_box(log_data)).unwrap();
+            evtclib::process(&raw_evtc).unwrap()
+        })
+    });
+}
+
+/// This benchmark tests raw::parse_bytes_parallel and the parallel processing on a pre-extracted
+/// log.
+///
+/// This is the parallel counterpart to unzipped_qadim_bytes_benchmark. It is only run if the
+/// rayon feature is enabled.
+fn unzipped_qadim_parallel_benchmark(c: &mut Criterion) {
+    #[cfg(feature = "rayon")]
+    {
+        let log_data = &unzipped(QADIM_LOG);
+        let options = evtclib::ProcessingOptions::new().parallel(true);
+
+        let mut group = c.benchmark_group("parsing/qadim/unzipped");
+
+        group.throughput(Throughput::Bytes(log_data.len() as u64));
+        group.bench_function("parallel", |b| {
+            b.iter(|| {
+                let raw_evtc = evtclib::raw::parse_bytes_parallel(black_box(log_data)).unwrap();
+                evtclib::process_with_options(&raw_evtc, &options).unwrap()
+            })
+        });
+    }
+    #[cfg(not(feature = "rayon"))]
+    let _ = c;
+}
+
 /// This benchmark tests the performance of process on a pre-parsed log.
 ///
 /// This is important because it is the point where we can change the most. Parsing the input file
@@ -81,9 +130,132 @@ fn process_qadim(c: &mut Criterion) {
     });
 }
 
+/// Create a log with many minions, similar to a large WvW log.
+///
+/// There are `masters` agents with `minions` minions each. The instance ids of the masters are
+/// reused: the second half of the masters gets the same instance ids as the first half, but only
+/// appears after the first half is gone.
+fn minion_log(masters: u16, minions: u16, events: u64) -> evtclib::raw::Evtc {
+    use evtclib::raw::{parser::Header, Agent, CbtEvent, Evtc};
+
+    let generation_size = masters / 2;
+    // The agents are sorted by their address, so the masters come last (which is the worst case
+    // for a linear search).
+    let master_addr = |m: u16| 1_000_000 + u64::from(m);
+    let master_instid = |m: u16| 1 + m % generation_size;
+    let minion_addr = |m: u16, k: u16| 100_000 + u64::from(m) * u64::from(minions) + u64::from(k);
+    let minion_instid = |m: u16, k: u16| masters + m * minions + k;
+
+    let mut name = [0; 64];
+    name[..6].copy_from_slice(b"Minion");
+    let agent = |addr| Agent {
+        addr,
+        prof: 1,
+        is_elite: u32::MAX,
+        toughness: 0,
+        concentration: 0,
+        healing: 0,
+        condition: 0,
+        name,
+    };
+    let mut agents = Vec::new();
+    for m in 0..masters {
+        agents.push(agent(master_addr(m)));
+        for k in 0..minions {
+            agents.push(agent(minion_addr(m, k)));
+        }
+    }
+
+    // Each generation gets half of the events. Within a generation, the masters take turns, and
+    // every other event is from a minion.
+    let events = (0..events)
+        .map(|i| {
+            let generation = (i * 2 / events) as u16;
+            let step = i / 2;
+            let m = generation * generation_size + (step % u64::from(generation_size)) as u16;
+            let mut event = CbtEvent {
+                time: 1 + i,
+                value: 1,
+                ..Default::default()
+            };
+            if i % 2 == 0 {
+                event.src_agent = master_addr(m);
+                event.src_instid = master_instid(m);
+            } else {
+                let k = (step / u64::from(generation_size) % u64::from(minions)) as u16;
+                event.src_agent = minion_addr(m, k);
+                event.src_instid = minion_instid(m, k);
+                event.src_master_instid = master_instid(m);
+            }
+            event
+        })
+        .collect();
+
+    Evtc {
+        header: Header {
+            arcdps_build: "20211112".into(),
+            combat_id: 1,
+            agent_count: agents.len() as u32,
+            revision: 1,
+        },
+        skill_count: 0,
+        agents,
+        skills: Vec::new(),
+        events,
+        unknown_statechanges: 0,
+    }
+}
+
+/// Resolve the masters of the minions by searching through all agents for every event.
+///
+/// This is how process used to find the masters before it built an index of the instance ids. It
+/// is kept here as a baseline for process_minions.
+fn linear_masters(events: &[evtclib::raw::CbtEvent], agents: &[evtclib::Agent]) -> usize {
+    events
+        .iter()
+        .filter(|event| event.src_master_instid != 0)
+        .filter_map(|event| {
+            agents.iter().find(|agent| {
+                agent.instance_id() == event.src_master_instid
+                    && agent.first_aware() < event.time
+                    && event.time < agent.last_aware()
+            })
+        })
+        .count()
+}
+
+/// This benchmark tests the performance of process on a log with many minions.
+///
+/// This is important for large WvW logs, where there are thousands of agents and most of the
+/// events come from minions, whose masters have to be resolved.
+///
+/// The "linear-baseline" function only resolves the masters the way process used to, by searching
+/// through all agents for every event. As the whole processing is faster than that search alone,
+/// it shows the gain of the instance id index.
+fn process_minions(c: &mut Criterion) {
+    let raw_evtc = minion_log(1000, 3, 400_000);
+    let log = evtclib::process(&raw_evtc).unwrap();
+
+    let mut group = c.benchmark_group("process/minions");
+    group.throughput(Throughput::Elements(raw_evtc.events.len() as u64));
+    group.bench_function("linear-baseline", |b| {
+        b.iter(|| linear_masters(black_box(&raw_evtc.events), log.agents()))
+    });
+    group.bench_function("sequential", |b| {
+        b.iter(|| evtclib::process(black_box(&raw_evtc)).unwrap())
+    });
+    #[cfg(feature = "rayon")]
+    {
+        let options = evtclib::ProcessingOptions::new().parallel(true);
+        group.bench_function("parallel", |b| {
+            b.iter(|| evtclib::process_with_options(black_box(&raw_evtc), &options).unwrap())
+        });
+    }
+}
+
 criterion_group! {
     name = benches;
     config = Criterion::default().sample_size(30);
-    targets = zipped_qadim_benchmark, zipped_qadim_ram_benchmark, unzipped_qadim_benchmark, process_qadim
+    targets = zipped_qadim_benchmark, zipped_qadim_ram_benchmark, unzipped_qadim_benchmark, unzipped_qadim_bytes_benchmark, unzipped_qadim_parallel_benchmark, process_qadim, process_minions
 }
 criterion_main!(benches);
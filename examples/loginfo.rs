@@ -10,13 +10,7 @@ fn main() {
         process::exit(1);
     };
 
-    let compression = if name.ends_with(".zip") || name.ends_with(".zevtc") {
-        Compression::Zip
-    } else {
-        Compression::None
-    };
-
-    let log = evtclib::process_file(&name, compression).unwrap();
+    let log = evtclib::process_file(&name, Compression::Auto).unwrap();
 
     println!("Encounter: {:?}", log.encounter());
     println!("Was CM? {}", log.is_cm());
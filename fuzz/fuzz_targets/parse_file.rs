@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = evtclib::raw::parse_file(data);
+    let _ = evtclib::raw::parse_bytes(data);
+});
@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use evtclib::raw::{self, ParseLimits};
+
+fuzz_target!(|data: &[u8]| {
+    // Without a limit, a zip bomb would just make the fuzzer run out of memory.
+    let limits = ParseLimits {
+        max_decompressed_size: Some(64 * 1024 * 1024),
+        ..Default::default()
+    };
+    let _ = raw::parse_zip_with_limits(std::io::Cursor::new(data), &limits);
+});
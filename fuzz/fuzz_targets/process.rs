@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use evtclib::{raw, ProcessingOptions};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(evtc) = raw::parse_bytes(data) {
+        let _ = evtclib::process(&evtc);
+        let options = ProcessingOptions::new()
+            .unknown_professions_fatal(false)
+            .retain_raw_events(true);
+        let _ = evtclib::process_with_options(&evtc, &options);
+    }
+});
@@ -2,7 +2,6 @@ use std::convert::TryFrom;
 use std::marker::PhantomData;
 
 use getset::{CopyGetters, Getters, Setters};
-use num_traits::FromPrimitive;
 
 use super::{
     gamedata::{EliteSpec, Profession},
@@ -139,6 +138,34 @@ pub enum AgentKind {
 }
 
 impl AgentKind {
+    /// Extract the correct `AgentKind` from the given [raw agent][raw::Agent], allowing unknown
+    /// professions and elite specializations.
+    ///
+    /// This works like the `TryFrom<&raw::Agent>` implementation, but instead of returning
+    /// [`EvtcError::InvalidProfession`][EvtcError::InvalidProfession] or
+    /// [`EvtcError::InvalidEliteSpec`][EvtcError::InvalidEliteSpec], the player will have
+    /// [`Profession::Unknown`][Profession::Unknown] or [`EliteSpec::Unknown`][EliteSpec::Unknown]
+    /// set.
+    pub fn from_raw_lenient(raw_agent: &raw::Agent) -> Result<AgentKind, EvtcError> {
+        AgentKind::from_raw(raw_agent, true)
+    }
+
+    fn from_raw(raw_agent: &raw::Agent, lenient: bool) -> Result<AgentKind, EvtcError> {
+        if raw_agent.is_character() {
+            Ok(AgentKind::Character(AgentKind::from_raw_character(
+                raw_agent,
+            )?))
+        } else if raw_agent.is_gadget() {
+            Ok(AgentKind::Gadget(AgentKind::from_raw_gadget(raw_agent)?))
+        } else if raw_agent.is_player() {
+            Ok(AgentKind::Player(AgentKind::from_raw_player(
+                raw_agent, lenient,
+            )?))
+        } else {
+            Err(EvtcError::InvalidData)
+        }
+    }
+
     fn from_raw_character(raw_agent: &raw::Agent) -> Result<Character, EvtcError> {
         assert!(raw_agent.is_character());
         let name = raw::cstr_up_to_nul(&raw_agent.name).ok_or(EvtcError::InvalidData)?;
@@ -157,7 +184,7 @@ impl AgentKind {
         })
     }
 
-    fn from_raw_player(raw_agent: &raw::Agent) -> Result<Player, EvtcError> {
+    fn from_raw_player(raw_agent: &raw::Agent, lenient: bool) -> Result<Player, EvtcError> {
         assert!(raw_agent.is_player());
         let character_name = raw::cstr_up_to_nul(&raw_agent.name)
             .ok_or(EvtcError::InvalidData)?
@@ -178,14 +205,18 @@ impl AgentKind {
         let elite = if raw_agent.is_elite == 0 {
             None
         } else {
-            Some(
-                EliteSpec::from_u32(raw_agent.is_elite)
-                    .ok_or(EvtcError::InvalidEliteSpec(raw_agent.is_elite))?,
-            )
+            let elite = EliteSpec::from_id(raw_agent.is_elite);
+            if elite.is_unknown() && !lenient {
+                return Err(EvtcError::InvalidEliteSpec(raw_agent.is_elite));
+            }
+            Some(elite)
         };
+        let profession = Profession::from_id(raw_agent.prof);
+        if profession.is_unknown() && !lenient {
+            return Err(EvtcError::InvalidProfession(raw_agent.prof));
+        }
         Ok(Player {
-            profession: Profession::from_u32(raw_agent.prof)
-                .ok_or(EvtcError::InvalidProfession(raw_agent.prof))?,
+            profession,
             elite,
             character_name: character_name.to_owned(),
             account_name: account_name.to_owned(),
@@ -257,17 +288,7 @@ impl TryFrom<&raw::Agent> for AgentKind {
     /// Note that in most cases, you probably want to use `Agent::try_from` or even
     /// [`process`][super::process] instead of this function.
     fn try_from(raw_agent: &raw::Agent) -> Result<Self, Self::Error> {
-        if raw_agent.is_character() {
-            Ok(AgentKind::Character(AgentKind::from_raw_character(
-                raw_agent,
-            )?))
-        } else if raw_agent.is_gadget() {
-            Ok(AgentKind::Gadget(AgentKind::from_raw_gadget(raw_agent)?))
-        } else if raw_agent.is_player() {
-            Ok(AgentKind::Player(AgentKind::from_raw_player(raw_agent)?))
-        } else {
-            Err(EvtcError::InvalidData)
-        }
+        AgentKind::from_raw(raw_agent, false)
     }
 }
 
@@ -608,7 +629,19 @@ impl TryFrom<&raw::Agent> for Agent {
 
     /// Parse a raw agent.
     fn try_from(raw_agent: &raw::Agent) -> Result<Self, Self::Error> {
-        let kind = AgentKind::try_from(raw_agent)?;
+        Agent::from_raw(raw_agent, AgentKind::try_from(raw_agent)?)
+    }
+}
+
+impl Agent {
+    /// Parse a raw agent, allowing unknown professions and elite specializations.
+    ///
+    /// See [`AgentKind::from_raw_lenient`][AgentKind::from_raw_lenient] for more information.
+    pub fn from_raw_lenient(raw_agent: &raw::Agent) -> Result<Agent, EvtcError> {
+        Agent::from_raw(raw_agent, AgentKind::from_raw_lenient(raw_agent)?)
+    }
+
+    fn from_raw(raw_agent: &raw::Agent, kind: AgentKind) -> Result<Agent, EvtcError> {
         Ok(Agent {
             addr: raw_agent.addr,
             kind,
@@ -618,7 +651,7 @@ impl TryFrom<&raw::Agent> for Agent {
             condition: raw_agent.condition,
             instance_id: 0,
             first_aware: 0,
-            last_aware: u64::max_value(),
+            last_aware: u64::MAX,
             master_agent: None,
             phantom_data: PhantomData,
         })
@@ -791,6 +824,61 @@ impl Agent<Character> {
     }
 }
 
+/// A period of time in which an agent had a specific instance id.
+///
+/// The game reuses instance ids, so the same instance id can belong to different agents over the
+/// course of a log. See [`Log::agent_by_instance_id_at`][super::Log::agent_by_instance_id_at].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, CopyGetters, Setters)]
+pub struct InstancePeriod {
+    /// The instance id.
+    #[get_copy = "pub"]
+    instance_id: u16,
+
+    /// The address of the agent that had the instance id.
+    #[get_copy = "pub"]
+    addr: u64,
+
+    /// The timestamp of the first event of the agent with this instance id.
+    #[get_copy = "pub"]
+    first_aware: u64,
+
+    /// The timestamp of the last event of the agent with this instance id.
+    #[getset(get_copy = "pub", set = "pub(crate)")]
+    last_aware: u64,
+}
+
+impl InstancePeriod {
+    pub(crate) fn new(instance_id: u16, addr: u64, time: u64) -> Self {
+        InstancePeriod {
+            instance_id,
+            addr,
+            first_aware: time,
+            last_aware: time,
+        }
+    }
+
+    /// Check whether the given time lies within this period (inclusive).
+    pub fn contains(&self, time: u64) -> bool {
+        self.first_aware <= time && time <= self.last_aware
+    }
+}
+
+/// Find the period in which an agent had the given instance id at the given time.
+///
+/// The periods have to be sorted by instance id and then by the start of the period. If multiple
+/// periods [contain][InstancePeriod::contains] the time, the one that started last is returned.
+pub(crate) fn find_instance_period(
+    periods: &[InstancePeriod],
+    instance_id: u16,
+    time: u64,
+) -> Option<&InstancePeriod> {
+    let start = periods.partition_point(|p| p.instance_id() < instance_id);
+    let end =
+        periods.partition_point(|p| (p.instance_id(), p.first_aware()) <= (instance_id, time));
+    periods[start..end].iter().rev().find(|p| p.contains(time))
+}
+
 #[cfg(all(feature = "serde", test))]
 mod tests {
     use super::*;
@@ -0,0 +1,461 @@
+//! Buff and skill metadata.
+//!
+//! arcdps writes some information about the buffs and skills that appear in a log as special
+//! statechange events (see [`EventKind::BuffInfo`], [`EventKind::BuffFormula`],
+//! [`EventKind::SkillInfo`] and [`EventKind::SkillTiming`]). This module contains the types for
+//! that information, as well as the [`BuffDatabase`] and [`SkillDatabase`], which collect them
+//! into lookup tables.
+//!
+//! The main way to obtain the databases is through [`Log::buff_database`][super::Log::buff_database]
+//! and [`Log::skill_database`][super::Log::skill_database]:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let log = evtclib::process_file("logfile.zevtc", evtclib::Compression::Zip)?;
+//! let buffs = log.buff_database();
+//! for event in log.events() {
+//!     if let evtclib::EventKind::BuffApplication { buff_id, .. } = event.kind() {
+//!         if buffs.is_condition(*buff_id) {
+//!             println!("Condition {} applied", buff_id);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::HashMap;
+
+use getset::{CopyGetters, Getters};
+use num_traits::FromPrimitive;
+
+use super::{raw, Event, EventKind};
+
+/// The way that multiple stacks of a buff interact.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StackingType {
+    /// Stacks in intensity, stacks are lost conditionally.
+    StackingConditionalLoss,
+    /// Stacks in duration, stacks are queued.
+    Queue,
+    /// Stacks in duration, but the duration is capped.
+    CappedDuration,
+    /// Stacks in duration, like regeneration.
+    Regeneration,
+    /// Stacks in intensity.
+    Stacking,
+    /// Only a single stack is present.
+    Force,
+    /// An unknown stacking type.
+    Unknown(u8),
+}
+
+impl StackingType {
+    /// Parse a given integer into the correct enum value.
+    fn from_u8(value: u8) -> StackingType {
+        match value {
+            0 => StackingType::StackingConditionalLoss,
+            1 => StackingType::Queue,
+            2 => StackingType::CappedDuration,
+            3 => StackingType::Regeneration,
+            4 => StackingType::Stacking,
+            5 => StackingType::Force,
+            _ => StackingType::Unknown(value),
+        }
+    }
+}
+
+/// General information about a buff.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CopyGetters)]
+pub struct BuffInfo {
+    /// The category of the buff.
+    ///
+    /// This is `None` if the category is not known to evtclib.
+    #[get_copy = "pub"]
+    category: Option<raw::BuffCategory>,
+    /// The stacking type of the buff.
+    #[get_copy = "pub"]
+    stacking_type: StackingType,
+    /// The maximum number of stacks.
+    #[get_copy = "pub"]
+    max_stacks: u16,
+    /// The maximum duration of the buff.
+    #[get_copy = "pub"]
+    duration_cap: u32,
+    /// Whether the buff is (probably) an invulnerability.
+    #[get_copy = "pub"]
+    is_invulnerable: bool,
+    /// Whether the buff is (probably) inverted.
+    #[get_copy = "pub"]
+    is_inverted: bool,
+    /// Whether the buff is (probably) a resistance.
+    #[get_copy = "pub"]
+    is_resistance: bool,
+}
+
+impl BuffInfo {
+    pub(crate) fn from_raw(raw_event: &raw::CbtEvent) -> BuffInfo {
+        let padding = raw_event.padding_end.to_le_bytes();
+        let category = match raw_event.statechange_data {
+            raw::StateChangeData::BuffInfo { category } => raw::BuffCategory::from_u8(category),
+            _ => None,
+        };
+        BuffInfo {
+            category,
+            stacking_type: StackingType::from_u8(padding[0]),
+            max_stacks: raw_event.src_master_instid,
+            duration_cap: raw_event.overstack_value,
+            is_invulnerable: raw_event.is_flanking,
+            is_inverted: raw_event.is_shields,
+            is_resistance: padding[1] != 0,
+        }
+    }
+}
+
+/// A formula that describes the effect of a buff.
+///
+/// The formulas are applied as `constant_offset + level_offset * level + variable * attr`, where
+/// `attr` depends on the formula type.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, CopyGetters)]
+pub struct BuffFormula {
+    /// The type of the formula.
+    #[get_copy = "pub"]
+    formula_type: u32,
+    /// The attribute that is modified by this formula.
+    ///
+    /// This is `None` if the attribute is not known to evtclib.
+    #[get_copy = "pub"]
+    attribute1: Option<raw::Attribute>,
+    /// The second attribute that is modified by this formula.
+    ///
+    /// This is `None` if the attribute is not known to evtclib.
+    #[get_copy = "pub"]
+    attribute2: Option<raw::Attribute>,
+    /// The constant part of the formula.
+    #[get_copy = "pub"]
+    constant_offset: f32,
+    /// The part of the formula that scales with the level.
+    #[get_copy = "pub"]
+    level_offset: f32,
+    /// The part of the formula that scales with the attribute.
+    #[get_copy = "pub"]
+    variable: f32,
+    /// The trait that the source needs to have for this formula to be active (0 if none).
+    #[get_copy = "pub"]
+    trait_source: u32,
+    /// The trait that the target needs to have for this formula to be active (0 if none).
+    #[get_copy = "pub"]
+    trait_self: u32,
+    /// The buff that the source needs to have for this formula to be active (0 if none).
+    #[get_copy = "pub"]
+    buff_source: u32,
+    /// The buff that the target needs to have for this formula to be active (0 if none).
+    #[get_copy = "pub"]
+    buff_self: u32,
+    /// Whether the formula applies to NPCs.
+    #[get_copy = "pub"]
+    applies_to_npc: bool,
+    /// Whether the formula applies to players.
+    #[get_copy = "pub"]
+    applies_to_player: bool,
+    /// Whether the formula is active during a break.
+    #[get_copy = "pub"]
+    is_break: bool,
+}
+
+impl BuffFormula {
+    pub(crate) fn from_raw(raw_event: &raw::CbtEvent) -> BuffFormula {
+        // (float*)&time[8]: type attr1 attr2 param1 param2 param3 trait_src trait_self
+        let floats = [
+            f32::from_bits(raw_event.time as u32),
+            f32::from_bits((raw_event.time >> 32) as u32),
+            f32::from_bits(raw_event.src_agent as u32),
+            f32::from_bits((raw_event.src_agent >> 32) as u32),
+            f32::from_bits(raw_event.dst_agent as u32),
+            f32::from_bits((raw_event.dst_agent >> 32) as u32),
+            f32::from_bits(raw_event.value as u32),
+            f32::from_bits(raw_event.buff_dmg as u32),
+        ];
+        // (float*)&src_instid[2]: buff_src buff_self
+        let buff_source =
+            f32::from_bits(raw_event.src_instid as u32 | (raw_event.dst_instid as u32) << 16);
+        let buff_self = f32::from_bits(
+            raw_event.src_master_instid as u32 | (raw_event.dst_master_instid as u32) << 16,
+        );
+        BuffFormula {
+            formula_type: floats[0] as u32,
+            attribute1: raw::Attribute::from_u32(floats[1] as u32),
+            attribute2: raw::Attribute::from_u32(floats[2] as u32),
+            constant_offset: floats[3],
+            level_offset: floats[4],
+            variable: floats[5],
+            trait_source: floats[6] as u32,
+            trait_self: floats[7] as u32,
+            buff_source: buff_source as u32,
+            buff_self: buff_self as u32,
+            applies_to_npc: !raw_event.is_flanking,
+            applies_to_player: !raw_event.is_shields,
+            is_break: raw_event.is_offcycle,
+        }
+    }
+}
+
+/// General information about a skill.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, CopyGetters)]
+pub struct SkillInfo {
+    /// The recharge time of the skill.
+    #[get_copy = "pub"]
+    recharge: f32,
+    /// The first range value of the skill.
+    #[get_copy = "pub"]
+    range0: f32,
+    /// The second range value of the skill.
+    #[get_copy = "pub"]
+    range1: f32,
+    /// The tooltip time of the skill.
+    #[get_copy = "pub"]
+    tooltip_time: f32,
+}
+
+impl SkillInfo {
+    pub(crate) fn from_raw(raw_event: &raw::CbtEvent) -> SkillInfo {
+        // (float*)&time[4]: recharge range0 range1 tooltiptime
+        SkillInfo {
+            recharge: f32::from_bits(raw_event.time as u32),
+            range0: f32::from_bits((raw_event.time >> 32) as u32),
+            range1: f32::from_bits(raw_event.src_agent as u32),
+            tooltip_time: f32::from_bits((raw_event.src_agent >> 32) as u32),
+        }
+    }
+}
+
+/// A timing of a skill.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CopyGetters)]
+pub struct SkillTiming {
+    /// The action that happens at this timing.
+    #[get_copy = "pub"]
+    action: u64,
+    /// The time (in milliseconds since the start of the skill) at which the action happens.
+    #[get_copy = "pub"]
+    at_millisecond: u64,
+}
+
+impl SkillTiming {
+    pub(crate) fn from_raw(raw_event: &raw::CbtEvent) -> SkillTiming {
+        SkillTiming {
+            action: raw_event.src_agent,
+            at_millisecond: raw_event.dst_agent,
+        }
+    }
+}
+
+/// All information that is known about a single buff.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq, Getters)]
+pub struct Buff {
+    /// The general buff information, if present.
+    #[get = "pub"]
+    info: Option<BuffInfo>,
+    /// The formulas of this buff.
+    #[get = "pub"]
+    formulas: Vec<BuffFormula>,
+}
+
+/// All information that is known about a single skill.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq, Getters)]
+pub struct SkillData {
+    /// The general skill information, if present.
+    #[get = "pub"]
+    info: Option<SkillInfo>,
+    /// The timings of this skill.
+    #[get = "pub"]
+    timings: Vec<SkillTiming>,
+}
+
+/// A lookup table for buff information.
+///
+/// The information is taken from the [`BuffInfo`][EventKind::BuffInfo] and
+/// [`BuffFormula`][EventKind::BuffFormula] events of a log. Note that arcdps only writes those
+/// events for buffs that actually appear in the log.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BuffDatabase {
+    buffs: HashMap<u32, Buff>,
+}
+
+impl BuffDatabase {
+    /// Build the database from the given events.
+    ///
+    /// Events that do not contain buff information are ignored.
+    pub fn from_events<'a, I: IntoIterator<Item = &'a Event>>(events: I) -> BuffDatabase {
+        let mut database = BuffDatabase::default();
+        for event in events {
+            database.add_event(event);
+        }
+        database
+    }
+
+    /// Add the information of the given event to the database.
+    ///
+    /// Events that do not contain buff information are ignored.
+    pub fn add_event(&mut self, event: &Event) {
+        match event.kind() {
+            EventKind::BuffInfo { buff_id, info } => {
+                self.buffs.entry(*buff_id).or_default().info = Some(info.clone());
+            }
+            EventKind::BuffFormula { buff_id, formula } => {
+                self.buffs
+                    .entry(*buff_id)
+                    .or_default()
+                    .formulas
+                    .push(formula.clone());
+            }
+            _ => (),
+        }
+    }
+
+    /// Add the buffs of the other database that are not in this one yet.
+    pub(crate) fn merge(&mut self, other: &BuffDatabase) {
+        for (id, buff) in &other.buffs {
+            self.buffs.entry(*id).or_insert_with(|| buff.clone());
+        }
+    }
+
+    /// Return all information about the given buff.
+    pub fn buff(&self, buff_id: u32) -> Option<&Buff> {
+        self.buffs.get(&buff_id)
+    }
+
+    /// Return the general information about the given buff.
+    pub fn buff_info(&self, buff_id: u32) -> Option<&BuffInfo> {
+        self.buff(buff_id).and_then(|b| b.info.as_ref())
+    }
+
+    /// Return the formulas of the given buff.
+    ///
+    /// If the buff is not known, an empty slice is returned.
+    pub fn formulas(&self, buff_id: u32) -> &[BuffFormula] {
+        self.buff(buff_id)
+            .map(|b| &b.formulas as &[_])
+            .unwrap_or(&[])
+    }
+
+    /// Return the category of the given buff.
+    pub fn category(&self, buff_id: u32) -> Option<raw::BuffCategory> {
+        self.buff_info(buff_id).and_then(BuffInfo::category)
+    }
+
+    /// Check whether the given buff is a boon.
+    pub fn is_boon(&self, buff_id: u32) -> bool {
+        self.category(buff_id) == Some(raw::BuffCategory::Boon)
+    }
+
+    /// Check whether the given buff is a condition.
+    pub fn is_condition(&self, buff_id: u32) -> bool {
+        self.category(buff_id) == Some(raw::BuffCategory::Condition)
+    }
+
+    /// Return an iterator over all buffs in the database, together with their ids.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &Buff)> {
+        self.buffs.iter().map(|(id, buff)| (*id, buff))
+    }
+
+    /// Return the number of buffs in the database.
+    pub fn len(&self) -> usize {
+        self.buffs.len()
+    }
+
+    /// Check whether the database is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buffs.is_empty()
+    }
+}
+
+/// A lookup table for skill information.
+///
+/// The information is taken from the [`SkillInfo`][EventKind::SkillInfo] and
+/// [`SkillTiming`][EventKind::SkillTiming] events of a log. Note that arcdps only writes those
+/// events for skills that actually appear in the log.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SkillDatabase {
+    skills: HashMap<u32, SkillData>,
+}
+
+impl SkillDatabase {
+    /// Build the database from the given events.
+    ///
+    /// Events that do not contain skill information are ignored.
+    pub fn from_events<'a, I: IntoIterator<Item = &'a Event>>(events: I) -> SkillDatabase {
+        let mut database = SkillDatabase::default();
+        for event in events {
+            database.add_event(event);
+        }
+        database
+    }
+
+    /// Add the information of the given event to the database.
+    ///
+    /// Events that do not contain skill information are ignored.
+    pub fn add_event(&mut self, event: &Event) {
+        match event.kind() {
+            EventKind::SkillInfo { skill_id, info } => {
+                self.skills.entry(*skill_id).or_default().info = Some(info.clone());
+            }
+            EventKind::SkillTiming { skill_id, timing } => {
+                self.skills
+                    .entry(*skill_id)
+                    .or_default()
+                    .timings
+                    .push(timing.clone());
+            }
+            _ => (),
+        }
+    }
+
+    /// Add the skills of the other database that are not in this one yet.
+    pub(crate) fn merge(&mut self, other: &SkillDatabase) {
+        for (id, skill) in &other.skills {
+            self.skills.entry(*id).or_insert_with(|| skill.clone());
+        }
+    }
+
+    /// Return all information about the given skill.
+    pub fn skill(&self, skill_id: u32) -> Option<&SkillData> {
+        self.skills.get(&skill_id)
+    }
+
+    /// Return the general information about the given skill.
+    pub fn skill_info(&self, skill_id: u32) -> Option<&SkillInfo> {
+        self.skill(skill_id).and_then(|s| s.info.as_ref())
+    }
+
+    /// Return the timings of the given skill.
+    ///
+    /// If the skill is not known, an empty slice is returned.
+    pub fn timings(&self, skill_id: u32) -> &[SkillTiming] {
+        self.skill(skill_id)
+            .map(|s| &s.timings as &[_])
+            .unwrap_or(&[])
+    }
+
+    /// Return an iterator over all skills in the database, together with their ids.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &SkillData)> {
+        self.skills.iter().map(|(id, skill)| (*id, skill))
+    }
+
+    /// Return the number of skills in the database.
+    pub fn len(&self) -> usize {
+        self.skills.len()
+    }
+
+    /// Check whether the database is empty.
+    pub fn is_empty(&self) -> bool {
+        self.skills.is_empty()
+    }
+}
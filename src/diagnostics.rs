@@ -0,0 +1,181 @@
+//! Private module to contain the diagnostics about dropped events.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use getset::Getters;
+
+use super::{event::FromRawEventError, raw};
+
+/// A raw event that could not be converted to an [`Event`][super::Event].
+///
+/// Instead of silently dropping those events, [`process`][super::process] keeps them together
+/// with the reason why the conversion failed. They can be accessed through
+/// [`Log::dropped_events`][super::Log::dropped_events].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Getters)]
+pub struct DroppedEvent {
+    /// The original event, as it was found in the log.
+    #[get = "pub"]
+    raw: raw::CbtEvent,
+
+    /// The reason why the event could not be converted.
+    #[get = "pub"]
+    error: FromRawEventError,
+}
+
+impl DroppedEvent {
+    pub(crate) fn new(raw: raw::CbtEvent, error: FromRawEventError) -> Self {
+        DroppedEvent { raw, error }
+    }
+}
+
+/// A report about the event conversion of a log.
+///
+/// This can be obtained through [`Log::diagnostics`][super::Log::diagnostics] and is useful to
+/// find out whether (and why) data was lost during the processing. The [`Display`][fmt::Display]
+/// implementation produces a human readable summary.
+///
+/// Events with a statechange that is completely unknown to evtclib are already skipped by the
+/// parser. They are not part of the [total events][Diagnostics::total_events], but are counted in
+/// [`unknown_statechanges`][Diagnostics::unknown_statechanges].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostics {
+    total_events: usize,
+    unknown_statechanges: usize,
+    dropped_by_reason: HashMap<FromRawEventError, usize>,
+}
+
+impl Diagnostics {
+    pub(crate) fn new(
+        total_events: usize,
+        unknown_statechanges: usize,
+        dropped: &[DroppedEvent],
+    ) -> Self {
+        let mut dropped_by_reason = HashMap::new();
+        for event in dropped {
+            *dropped_by_reason.entry(event.error.clone()).or_insert(0) += 1;
+        }
+        Diagnostics {
+            total_events,
+            unknown_statechanges,
+            dropped_by_reason,
+        }
+    }
+
+    /// The number of raw events that were processed.
+    pub fn total_events(&self) -> usize {
+        self.total_events
+    }
+
+    /// The number of events that could be converted.
+    ///
+    /// This includes the events that were converted but not kept because of the
+    /// [`ProcessingOptions`][super::ProcessingOptions], as well as the buff and skill information
+    /// that is only collected into the [`BuffDatabase`][super::BuffDatabase] and
+    /// [`SkillDatabase`][super::SkillDatabase].
+    pub fn converted_events(&self) -> usize {
+        self.total_events - self.dropped_events()
+    }
+
+    /// The number of events that could not be converted.
+    pub fn dropped_events(&self) -> usize {
+        self.dropped_by_reason.values().sum()
+    }
+
+    /// The number of events that were skipped by the parser because their statechange is unknown.
+    ///
+    /// See also [`Evtc::unknown_statechanges`][raw::Evtc::unknown_statechanges].
+    pub fn unknown_statechanges(&self) -> usize {
+        self.unknown_statechanges
+    }
+
+    /// The number of dropped events for each reason.
+    pub fn dropped_by_reason(&self) -> &HashMap<FromRawEventError, usize> {
+        &self.dropped_by_reason
+    }
+
+    /// Check whether any events were dropped or skipped.
+    pub fn is_lossless(&self) -> bool {
+        self.dropped_by_reason.is_empty() && self.unknown_statechanges == 0
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} events dropped",
+            self.dropped_events(),
+            self.total_events
+        )?;
+        if self.unknown_statechanges > 0 {
+            write!(
+                f,
+                ", {} events with unknown statechanges skipped",
+                self.unknown_statechanges
+            )?;
+        }
+        // Sort the reasons to get a deterministic output, most common reason first.
+        let mut reasons = self.dropped_by_reason.iter().collect::<Vec<_>>();
+        reasons.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.to_string().cmp(&b.to_string())));
+        for (reason, count) in reasons {
+            write!(f, "\n  {}: {}", reason, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        let dropped = [
+            DroppedEvent::new(Default::default(), FromRawEventError::UnknownDamageEvent),
+            DroppedEvent::new(Default::default(), FromRawEventError::UnexpectedReplInfo),
+            DroppedEvent::new(Default::default(), FromRawEventError::UnknownDamageEvent),
+        ];
+        let diagnostics = Diagnostics::new(10, 0, &dropped);
+        assert_eq!(diagnostics.total_events(), 10);
+        assert_eq!(diagnostics.converted_events(), 7);
+        assert_eq!(diagnostics.dropped_events(), 3);
+        assert_eq!(
+            diagnostics.dropped_by_reason()[&FromRawEventError::UnknownDamageEvent],
+            2
+        );
+        assert!(!diagnostics.is_lossless());
+    }
+
+    #[test]
+    fn test_display() {
+        let dropped = [
+            DroppedEvent::new(Default::default(), FromRawEventError::UnexpectedReplInfo),
+            DroppedEvent::new(Default::default(), FromRawEventError::UnknownDamageEvent),
+            DroppedEvent::new(Default::default(), FromRawEventError::UnknownDamageEvent),
+        ];
+        let diagnostics = Diagnostics::new(4, 2, &dropped);
+        assert_eq!(
+            diagnostics.to_string(),
+            "3 of 4 events dropped, 2 events with unknown statechanges skipped\n  \
+             event contains an unknown damage event: 2\n  \
+             an unexpected REPLINFO was found: 1"
+        );
+    }
+
+    #[test]
+    fn test_lossless() {
+        let diagnostics = Diagnostics::new(5, 0, &[]);
+        assert!(diagnostics.is_lossless());
+        assert_eq!(diagnostics.to_string(), "0 of 5 events dropped");
+    }
+
+    #[test]
+    fn test_unknown_statechanges() {
+        let diagnostics = Diagnostics::new(5, 1, &[]);
+        assert_eq!(diagnostics.unknown_statechanges(), 1);
+        assert_eq!(diagnostics.dropped_events(), 0);
+        assert!(!diagnostics.is_lossless());
+    }
+}
@@ -1,19 +1,21 @@
 //! Event definitions.
 //!
 //! This module contains the different types of events in their high-level form.
+use super::database::{BuffFormula, BuffInfo, SkillInfo, SkillTiming};
 use super::raw;
 
 use std::convert::TryFrom;
 use std::io;
 
 use byteorder::{BigEndian, WriteBytesExt, LE};
-use getset::{CopyGetters, Getters};
+use getset::{CopyGetters, Getters, Setters};
 use num_traits::FromPrimitive;
 use thiserror::Error;
 
 /// Any error that can occur when trying to convert a raw [`CbtEvent`][raw::CbtEvent] to a
 /// [`Event`][Event].
-#[derive(Clone, Debug, Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Error)]
 pub enum FromRawEventError {
     #[error("event contains an unknown state change: {0:?}")]
     UnknownStateChange(raw::CbtStateChange),
@@ -213,6 +215,272 @@ pub enum EventKind {
     /// Note that the tag id is volatile and depends on the game build. Do not rely on the actual
     /// value of this!
     Tag { agent_addr: u64, tag_id: i32 },
+
+    /// The breakbar state of the given agent has changed.
+    BreakbarState {
+        agent_addr: u64,
+        state: BreakbarState,
+    },
+
+    /// The breakbar of the given agent has changed.
+    BreakbarPercent {
+        agent_addr: u64,
+        /// The new breakbar value, as a fraction between 0 and 1.
+        percent: f32,
+    },
+
+    /// The agent has reached a barrier treshold.
+    BarrierUpdate {
+        agent_addr: u64,
+        /// The new barrier, as percentage multiplied by 10000.
+        barrier: u16,
+    },
+
+    /// The stats of the agents have been reset.
+    StatReset {
+        /// The species id of the agent that triggered the reset, e.g. the boss.
+        species_id: u64,
+    },
+
+    /// The time at which the instance was likely started.
+    InstanceStart {
+        /// The time in milliseconds, in the same format as [`Event::time`][Event::time].
+        start_time: u64,
+    },
+
+    /// The server tick rate has dropped.
+    ///
+    /// This is only reported if the tick rate is 20 or lower.
+    TickRate { tick_rate: u64 },
+
+    /// The agent went down.
+    ///
+    /// This is used to attribute the down contribution.
+    Last90BeforeDown {
+        agent_addr: u64,
+        /// The time in milliseconds since the agent was last above 90% health.
+        time_since_last_90: u64,
+    },
+
+    /// An effect was created.
+    ///
+    /// Effect ids are volatile and depend on the game build, see
+    /// [`IdToGuid`][EventKind::IdToGuid] for the stable identifiers.
+    Effect {
+        owner_agent_addr: u64,
+        location: EffectLocation,
+        effect_id: u32,
+        /// The orientation of the effect (x, y, z).
+        orientation: [f32; 3],
+        /// The duration of the effect in milliseconds.
+        duration: u32,
+        /// The id with which the end of the effect is signalled, see
+        /// [`EffectEnd`][EventKind::EffectEnd].
+        ///
+        /// This is zero for untracked effects.
+        tracking_id: u32,
+        /// Whether the effect is on a moving platform.
+        on_non_static_platform: bool,
+    },
+
+    /// A tracked effect has ended.
+    EffectEnd {
+        owner_agent_addr: u64,
+        tracking_id: u32,
+    },
+
+    /// Maps a volatile content id to its persistent GUID.
+    IdToGuid {
+        /// The persistent GUID of the content.
+        guid: [u8; 16],
+        content_type: ContentType,
+        /// The volatile id, as used in [`Effect`][EventKind::Effect] or
+        /// [`SquadMarker`][EventKind::SquadMarker].
+        content_id: u32,
+    },
+
+    /// The species id of the log has been changed.
+    LogNpcUpdate {
+        /// The new species id, or 0 if the log was changed to a generic log.
+        species_id: u64,
+        agent_addr: u64,
+        server_timestamp: u32,
+    },
+
+    /// The fractal scale of the instance.
+    FractalScale { scale: u64 },
+
+    /// The ruleset that is active in the instance.
+    Ruleset { pve: bool, wvw: bool, pvp: bool },
+
+    /// A squad marker has been placed or removed.
+    ///
+    /// If all coordinates are zero or infinite, the marker was removed.
+    SquadMarker {
+        /// The index of the marker (e.g. 0 is arrow).
+        marker_index: u32,
+        x: f32,
+        y: f32,
+        z: f32,
+    },
+
+    /// The log was made with the given arcdps build.
+    ArcBuild { build: String },
+
+    /// The agent has deployed or stowed their glider.
+    Glider { agent_addr: u64, deployed: bool },
+
+    /// The agent has used a stun break.
+    StunBreak {
+        agent_addr: u64,
+        /// The remaining duration of the disabling effect.
+        duration_remaining: i32,
+    },
+
+    /// General information about a buff.
+    ///
+    /// The time of this event is not meaningful, so it is only collected into the
+    /// [`BuffDatabase`][crate::BuffDatabase] and not included in [`Log::events`][crate::Log::events].
+    BuffInfo { buff_id: u32, info: BuffInfo },
+
+    /// A formula that describes the effect of a buff.
+    ///
+    /// A buff can have multiple formulas. Like [`BuffInfo`][EventKind::BuffInfo], this event is
+    /// only collected into the [`BuffDatabase`][crate::BuffDatabase].
+    BuffFormula { buff_id: u32, formula: BuffFormula },
+
+    /// General information about a skill.
+    ///
+    /// The time of this event is not meaningful, so it is only collected into the
+    /// [`SkillDatabase`][crate::SkillDatabase] and not included in
+    /// [`Log::events`][crate::Log::events].
+    SkillInfo { skill_id: u32, info: SkillInfo },
+
+    /// A timing of a skill.
+    ///
+    /// A skill can have multiple timings. Like [`SkillInfo`][EventKind::SkillInfo], this event is
+    /// only collected into the [`SkillDatabase`][crate::SkillDatabase].
+    SkillTiming { skill_id: u32, timing: SkillTiming },
+}
+
+impl EventKind {
+    /// Returns whether the time of this event is a point in time.
+    ///
+    /// The buff and skill information events use the time field for other data.
+    pub(crate) fn is_timed(&self) -> bool {
+        !matches!(
+            self,
+            EventKind::BuffInfo { .. }
+                | EventKind::BuffFormula { .. }
+                | EventKind::SkillInfo { .. }
+                | EventKind::SkillTiming { .. }
+        )
+    }
+
+    /// Returns the category that this event belongs to.
+    pub fn category(&self) -> EventCategory {
+        use EventKind::*;
+        match self {
+            EnterCombat { .. }
+            | ExitCombat { .. }
+            | ChangeUp { .. }
+            | ChangeDown { .. }
+            | ChangeDead { .. }
+            | Spawn { .. }
+            | Despawn { .. }
+            | HealthUpdate { .. }
+            | MaxHealthUpdate { .. }
+            | WeaponSwap { .. }
+            | TeamChange { .. }
+            | AttackTarget { .. }
+            | Targetable { .. }
+            | Guild { .. }
+            | Tag { .. }
+            | BreakbarState { .. }
+            | BreakbarPercent { .. }
+            | BarrierUpdate { .. }
+            | Last90BeforeDown { .. }
+            | Glider { .. }
+            | StunBreak { .. } => EventCategory::State,
+
+            LogStart { .. }
+            | LogEnd { .. }
+            | PointOfView { .. }
+            | Language { .. }
+            | Build { .. }
+            | ShardId { .. }
+            | MapId { .. }
+            | Error { .. }
+            | StatReset { .. }
+            | InstanceStart { .. }
+            | TickRate { .. }
+            | IdToGuid { .. }
+            | LogNpcUpdate { .. }
+            | FractalScale { .. }
+            | Ruleset { .. }
+            | ArcBuild { .. }
+            | BuffInfo { .. }
+            | BuffFormula { .. }
+            | SkillInfo { .. }
+            | SkillTiming { .. } => EventCategory::Metadata,
+
+            Reward { .. } => EventCategory::Reward,
+
+            SkillUse { .. } => EventCategory::Skill,
+
+            ConditionTick { .. } | InvulnTick { .. } | Physical { .. } => EventCategory::Damage,
+
+            BuffApplication { .. }
+            | BuffRemove { .. }
+            | BuffInitial { .. }
+            | StackActive { .. }
+            | StackReset { .. } => EventCategory::Buff,
+
+            Position { .. } | Velocity { .. } | Facing { .. } => EventCategory::Movement,
+
+            Effect { .. } | EffectEnd { .. } | SquadMarker { .. } => EventCategory::Effect,
+        }
+    }
+}
+
+/// A coarse category of [`EventKind`][EventKind]s.
+///
+/// This can be used to quickly decide whether an event is relevant, without having to match on
+/// every single [`EventKind`][EventKind] (see for example
+/// [`ProcessingOptions`][super::ProcessingOptions]).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventCategory {
+    /// Changes to the state of an agent, such as entering combat, health updates or going down.
+    State,
+    /// Information about the log itself, such as the game build or the buff information.
+    Metadata,
+    /// Rewards that have been given out.
+    Reward,
+    /// Skill activations.
+    Skill,
+    /// Damage events, both physical and condition damage.
+    Damage,
+    /// Buff applications and removals.
+    Buff,
+    /// Position, velocity and facing updates.
+    Movement,
+    /// Effects and squad markers.
+    Effect,
+}
+
+impl EventCategory {
+    /// All event categories.
+    pub const ALL: &'static [EventCategory] = &[
+        EventCategory::State,
+        EventCategory::Metadata,
+        EventCategory::Reward,
+        EventCategory::Skill,
+        EventCategory::Damage,
+        EventCategory::Buff,
+        EventCategory::Movement,
+        EventCategory::Effect,
+    ];
 }
 
 /// A higher-level representation of a combat event.
@@ -239,15 +507,15 @@ pub enum EventKind {
 /// Note that if you plan on re-using the raw event afterwards, you should use the implementation
 /// that works on a reference instead: `Event::try_from(&raw_event)`.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Clone, Debug, PartialEq, CopyGetters, Getters)]
+#[derive(Clone, Debug, PartialEq, CopyGetters, Getters, Setters)]
 pub struct Event {
     /// The time when the event happened.
     ///
     /// This are the milliseconds since Windows has been started (`timeGetTime()`).
-    #[get_copy = "pub"]
+    #[getset(get_copy = "pub", set = "pub(crate)")]
     time: u64,
     /// The kind of the event.
-    #[get = "pub"]
+    #[getset(get = "pub", set = "pub(crate)")]
     kind: EventKind,
     /// Whether the agent had more than 90% of its health.
     ///
@@ -423,14 +691,102 @@ impl TryFrom<&raw::CbtEvent> for Event {
                 stack_id: raw_event.padding_end,
                 duration: raw_event.value,
             },
-            // XXX: implement proper handling of those events!
-            CbtStateChange::BuffInfo
-            | CbtStateChange::BuffFormula
-            | CbtStateChange::SkillInfo
-            | CbtStateChange::SkillTiming
-            | CbtStateChange::BreakbarState
-            | CbtStateChange::BreakbarPercent
-            | CbtStateChange::BarrierUpdate => {
+            CbtStateChange::BreakbarState => EventKind::BreakbarState {
+                agent_addr: raw_event.src_agent,
+                state: BreakbarState::from_u16(raw_event.value as u16),
+            },
+            CbtStateChange::BreakbarPercent => EventKind::BreakbarPercent {
+                agent_addr: raw_event.src_agent,
+                percent: f32::from_bits(raw_event.value as u32),
+            },
+            CbtStateChange::BarrierUpdate => EventKind::BarrierUpdate {
+                agent_addr: raw_event.src_agent,
+                barrier: raw_event.dst_agent as u16,
+            },
+            CbtStateChange::StatReset => EventKind::StatReset {
+                species_id: raw_event.src_agent,
+            },
+            CbtStateChange::InstanceStart => EventKind::InstanceStart {
+                start_time: raw_event.src_agent,
+            },
+            CbtStateChange::TickRate => EventKind::TickRate {
+                tick_rate: 25u64.saturating_sub(raw_event.src_agent),
+            },
+            CbtStateChange::Last90BeforeDown => EventKind::Last90BeforeDown {
+                agent_addr: raw_event.src_agent,
+                time_since_last_90: raw_event.dst_agent,
+            },
+            CbtStateChange::Effect => get_effect_old(raw_event),
+            CbtStateChange::Effect2 => get_effect(raw_event),
+            CbtStateChange::IdToGuid => {
+                let mut guid = [0; 16];
+                guid[..8].copy_from_slice(&raw_event.src_agent.to_le_bytes());
+                guid[8..].copy_from_slice(&raw_event.dst_agent.to_le_bytes());
+                EventKind::IdToGuid {
+                    guid,
+                    content_type: ContentType::from_u32(raw_event.overstack_value),
+                    content_id: raw_event.skillid,
+                }
+            }
+            CbtStateChange::LogNpcUpdate => EventKind::LogNpcUpdate {
+                species_id: raw_event.src_agent,
+                agent_addr: raw_event.dst_agent,
+                server_timestamp: raw_event.value as u32,
+            },
+            CbtStateChange::FractalScale => EventKind::FractalScale {
+                scale: raw_event.src_agent,
+            },
+            CbtStateChange::Ruleset => EventKind::Ruleset {
+                pve: raw_event.src_agent & 1 != 0,
+                wvw: raw_event.src_agent & 2 != 0,
+                pvp: raw_event.src_agent & 4 != 0,
+            },
+            CbtStateChange::SquadMarker => EventKind::SquadMarker {
+                marker_index: raw_event.skillid,
+                x: f32::from_bits(raw_event.src_agent as u32),
+                y: f32::from_bits((raw_event.src_agent >> 32) as u32),
+                z: f32::from_bits(raw_event.dst_agent as u32),
+            },
+            CbtStateChange::ArcBuild => {
+                let data = get_error_bytes(raw_event);
+                EventKind::ArcBuild {
+                    build: raw::cstr_up_to_nul(&data)
+                        .ok_or(FromRawEventError::InvalidText)?
+                        .to_string_lossy()
+                        .into_owned(),
+                }
+            }
+            CbtStateChange::Glider => EventKind::Glider {
+                agent_addr: raw_event.src_agent,
+                deployed: raw_event.value != 0,
+            },
+            CbtStateChange::StunBreak => EventKind::StunBreak {
+                agent_addr: raw_event.src_agent,
+                duration_remaining: raw_event.value,
+            },
+            CbtStateChange::BuffInfo => EventKind::BuffInfo {
+                buff_id: raw_event.skillid,
+                info: BuffInfo::from_raw(raw_event),
+            },
+            CbtStateChange::BuffFormula => EventKind::BuffFormula {
+                buff_id: raw_event.skillid,
+                formula: BuffFormula::from_raw(raw_event),
+            },
+            CbtStateChange::SkillInfo => EventKind::SkillInfo {
+                skill_id: raw_event.skillid,
+                info: SkillInfo::from_raw(raw_event),
+            },
+            CbtStateChange::SkillTiming => EventKind::SkillTiming {
+                skill_id: raw_event.skillid,
+                timing: SkillTiming::from_raw(raw_event),
+            },
+            // The layout of extension events depends on the extension, so there is nothing we can
+            // decode.
+            CbtStateChange::Extension
+            | CbtStateChange::ExtensionCombat
+            // Those are documented as internal, similar to ReplInfo.
+            | CbtStateChange::ApiDelayed
+            | CbtStateChange::IdleEvent => {
                 return Err(FromRawEventError::UnknownStateChange(
                     raw_event.is_statechange,
                 ))
@@ -453,7 +809,8 @@ impl TryFrom<&raw::CbtEvent> for Event {
 fn check_activation(raw_event: &raw::CbtEvent) -> Result<EventKind, FromRawEventError> {
     use raw::CbtActivation;
     match raw_event.is_activation {
-        CbtActivation::None => check_buffremove(raw_event),
+        // Unknown activations are handled like events without an activation.
+        CbtActivation::None | CbtActivation::Other(_) => check_buffremove(raw_event),
 
         activation => Ok(EventKind::SkillUse {
             source_agent_addr: raw_event.src_agent,
@@ -465,7 +822,7 @@ fn check_activation(raw_event: &raw::CbtEvent) -> Result<EventKind, FromRawEvent
                 CbtActivation::CancelCancel => Activation::CancelCancel(raw_event.value),
                 CbtActivation::Reset => Activation::Reset,
                 // Already checked and handled above
-                CbtActivation::None => unreachable!(),
+                CbtActivation::None | CbtActivation::Other(_) => unreachable!(),
             },
         }),
     }
@@ -474,7 +831,8 @@ fn check_activation(raw_event: &raw::CbtEvent) -> Result<EventKind, FromRawEvent
 fn check_buffremove(raw_event: &raw::CbtEvent) -> Result<EventKind, FromRawEventError> {
     use raw::CbtBuffRemove;
     match raw_event.is_buffremove {
-        CbtBuffRemove::None => check_damage(raw_event),
+        // Unknown removals are handled like events without a removal.
+        CbtBuffRemove::None | CbtBuffRemove::Other(_) => check_damage(raw_event),
 
         removal => Ok(EventKind::BuffRemove {
             source_agent_addr: raw_event.src_agent,
@@ -555,6 +913,87 @@ fn get_api_guild_string(bytes: &[u8; 16]) -> Option<String> {
     Some(result)
 }
 
+fn get_effect_location(raw_event: &raw::CbtEvent) -> EffectLocation {
+    if raw_event.dst_agent != 0 {
+        EffectLocation::Agent(raw_event.dst_agent)
+    } else {
+        EffectLocation::Position {
+            x: f32::from_bits(raw_event.value as u32),
+            y: f32::from_bits(raw_event.buff_dmg as u32),
+            z: f32::from_bits(raw_event.overstack_value),
+        }
+    }
+}
+
+/// Decode the retired [`Effect`][raw::CbtStateChange::Effect] statechange.
+fn get_effect_old(raw_event: &raw::CbtEvent) -> EventKind {
+    // The duration is the tracking id if the effect has ended.
+    let (orientation, duration) = match raw_event.statechange_data {
+        raw::StateChangeData::Effect {
+            orientation,
+            duration,
+        } => (orientation, u32::from(duration)),
+        _ => ([0; 2], 0),
+    };
+    if raw_event.skillid == 0 {
+        return EventKind::EffectEnd {
+            owner_agent_addr: raw_event.src_agent,
+            tracking_id: duration,
+        };
+    }
+    EventKind::Effect {
+        owner_agent_addr: raw_event.src_agent,
+        location: get_effect_location(raw_event),
+        effect_id: raw_event.skillid,
+        orientation: [
+            f32::from_bits(orientation[0]),
+            f32::from_bits(orientation[1]),
+            f32::from_bits(raw_event.padding_end),
+        ],
+        duration,
+        tracking_id: 0,
+        on_non_static_platform: false,
+    }
+}
+
+/// Decode the [`Effect2`][raw::CbtStateChange::Effect2] statechange.
+fn get_effect(raw_event: &raw::CbtEvent) -> EventKind {
+    let (duration, tracking_id, orientation_x) = match raw_event.statechange_data {
+        raw::StateChangeData::Effect2 {
+            duration,
+            tracking_id,
+            orientation_x,
+        } => (duration, tracking_id, orientation_x),
+        _ => (0, 0, 0),
+    };
+    if raw_event.skillid == 0 {
+        return EventKind::EffectEnd {
+            owner_agent_addr: raw_event.src_agent,
+            tracking_id,
+        };
+    }
+    // The orientation is stored as int16_t[3] in is_shields through pad64, multiplied by 1000.
+    let padding = raw_event.padding_end.to_le_bytes();
+    let orientation = [
+        orientation_x,
+        i16::from_le_bytes([padding[0], padding[1]]),
+        i16::from_le_bytes([padding[2], padding[3]]),
+    ];
+    EventKind::Effect {
+        owner_agent_addr: raw_event.src_agent,
+        location: get_effect_location(raw_event),
+        effect_id: raw_event.skillid,
+        orientation: [
+            orientation[0] as f32 / 1000.0,
+            orientation[1] as f32 / 1000.0,
+            orientation[2] as f32 / 1000.0,
+        ],
+        duration,
+        tracking_id,
+        on_non_static_platform: raw_event.is_flanking,
+    }
+}
+
 fn get_error_bytes(raw_event: &raw::CbtEvent) -> [u8; 32] {
     let mut result = [0; 32];
     let mut cursor = io::Cursor::new(&mut result as &mut [u8]);
@@ -616,3 +1055,165 @@ pub enum Activation {
     /// The channel was completed successfully.
     Reset,
 }
+
+/// The state of a breakbar.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BreakbarState {
+    /// The breakbar is active and can be damaged.
+    Active,
+    /// The breakbar is recovering.
+    Recover,
+    /// The breakbar is immune to damage.
+    Immune,
+    /// There is no breakbar.
+    None,
+    /// An unknown breakbar state.
+    Unknown(u16),
+}
+
+impl BreakbarState {
+    /// Parse a given integer into the correct enum value.
+    fn from_u16(value: u16) -> BreakbarState {
+        match value {
+            // magic constants from arcdps README
+            0 => BreakbarState::Active,
+            1 => BreakbarState::Recover,
+            2 => BreakbarState::Immune,
+            3 => BreakbarState::None,
+            _ => BreakbarState::Unknown(value),
+        }
+    }
+}
+
+/// The location of an effect.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EffectLocation {
+    /// The effect is attached to the agent with the given address.
+    Agent(u64),
+    /// The effect is at the given position.
+    Position { x: f32, y: f32, z: f32 },
+}
+
+/// The type of content that an [`IdToGuid`][EventKind::IdToGuid] event refers to.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContentType {
+    /// The id is an effect id.
+    Effect,
+    /// The id is a marker id.
+    Marker,
+    /// An unknown content type.
+    Unknown(u32),
+}
+
+impl ContentType {
+    /// Parse a given integer into the correct enum value.
+    fn from_u32(value: u32) -> ContentType {
+        match value {
+            0 => ContentType::Effect,
+            1 => ContentType::Marker,
+            _ => ContentType::Unknown(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rev1_event(fill: impl FnOnce(&mut [u8; 64])) -> raw::CbtEvent {
+        let mut bytes = [0; 64];
+        fill(&mut bytes);
+        raw::parser::parse_event_rev1_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn effect2() {
+        let raw_event = rev1_event(|b| {
+            b[8..16].copy_from_slice(&0xabcdu64.to_le_bytes());
+            b[24..28].copy_from_slice(&1.5f32.to_le_bytes());
+            b[28..32].copy_from_slice(&(-2.0f32).to_le_bytes());
+            b[32..36].copy_from_slice(&3.0f32.to_le_bytes());
+            b[36..40].copy_from_slice(&1234u32.to_le_bytes());
+            b[48..52].copy_from_slice(&5000u32.to_le_bytes());
+            b[52..56].copy_from_slice(&0x1234_5678u32.to_le_bytes());
+            b[56] = raw::CbtStateChange::Effect2 as u8;
+            b[57] = 1;
+            b[58..60].copy_from_slice(&1000i16.to_le_bytes());
+            b[60..62].copy_from_slice(&(-500i16).to_le_bytes());
+            b[62..64].copy_from_slice(&0i16.to_le_bytes());
+        });
+        let event = Event::try_from(&raw_event).unwrap();
+        assert_eq!(
+            event.kind(),
+            &EventKind::Effect {
+                owner_agent_addr: 0xabcd,
+                location: EffectLocation::Position {
+                    x: 1.5,
+                    y: -2.0,
+                    z: 3.0
+                },
+                effect_id: 1234,
+                orientation: [1.0, -0.5, 0.0],
+                duration: 5000,
+                tracking_id: 0x1234_5678,
+                on_non_static_platform: true,
+            }
+        );
+    }
+
+    #[test]
+    fn effect2_end() {
+        let raw_event = rev1_event(|b| {
+            b[8..16].copy_from_slice(&0xabcdu64.to_le_bytes());
+            b[52..56].copy_from_slice(&42u32.to_le_bytes());
+            b[56] = raw::CbtStateChange::Effect2 as u8;
+        });
+        let event = Event::try_from(&raw_event).unwrap();
+        assert_eq!(
+            event.kind(),
+            &EventKind::EffectEnd {
+                owner_agent_addr: 0xabcd,
+                tracking_id: 42,
+            }
+        );
+    }
+
+    #[test]
+    fn squad_marker() {
+        let raw_event = rev1_event(|b| {
+            b[8..12].copy_from_slice(&1.0f32.to_le_bytes());
+            b[12..16].copy_from_slice(&2.0f32.to_le_bytes());
+            b[16..20].copy_from_slice(&3.0f32.to_le_bytes());
+            b[36..40].copy_from_slice(&2u32.to_le_bytes());
+            b[56] = raw::CbtStateChange::SquadMarker as u8;
+        });
+        let event = Event::try_from(&raw_event).unwrap();
+        assert_eq!(
+            event.kind(),
+            &EventKind::SquadMarker {
+                marker_index: 2,
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+            }
+        );
+    }
+
+    #[test]
+    fn arc_build() {
+        let raw_event = rev1_event(|b| {
+            b[..11].copy_from_slice(b"2022-05-11\0");
+            b[56] = raw::CbtStateChange::ArcBuild as u8;
+        });
+        let event = Event::try_from(&raw_event).unwrap();
+        assert_eq!(
+            event.kind(),
+            &EventKind::ArcBuild {
+                build: "2022-05-11".into()
+            }
+        );
+    }
+}
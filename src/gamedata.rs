@@ -2,6 +2,7 @@
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 use std::{
+    convert::TryFrom,
     fmt::{self, Display, Formatter},
     str::FromStr,
 };
@@ -769,18 +770,83 @@ pub struct ParseProfessionError(String);
 ///
 /// This only contains the 9 base professions. For elite specializations, see
 /// [`EliteSpec`][EliteSpec].
+///
+/// Professions that are not (yet) known to evtclib are represented by
+/// [`Profession::Unknown`][Profession::Unknown]. Note that [`FromPrimitive`][FromPrimitive] only
+/// returns known professions, use [`Profession::from_id`][Profession::from_id] if you want the
+/// fallback.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, FromPrimitive)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub enum Profession {
-    Guardian = 1,
-    Warrior = 2,
-    Engineer = 3,
-    Ranger = 4,
-    Thief = 5,
-    Elementalist = 6,
-    Mesmer = 7,
-    Necromancer = 8,
-    Revenant = 9,
+    Guardian,
+    Warrior,
+    Engineer,
+    Ranger,
+    Thief,
+    Elementalist,
+    Mesmer,
+    Necromancer,
+    Revenant,
+    /// A profession that is not known to evtclib.
+    ///
+    /// The field contains the profession id.
+    Unknown(u32),
+}
+
+impl Profession {
+    /// Return the profession with the given id.
+    ///
+    /// If the id is not known, [`Profession::Unknown`][Profession::Unknown] is returned.
+    pub fn from_id(id: u32) -> Profession {
+        match id {
+            1 => Profession::Guardian,
+            2 => Profession::Warrior,
+            3 => Profession::Engineer,
+            4 => Profession::Ranger,
+            5 => Profession::Thief,
+            6 => Profession::Elementalist,
+            7 => Profession::Mesmer,
+            8 => Profession::Necromancer,
+            9 => Profession::Revenant,
+            _ => Profession::Unknown(id),
+        }
+    }
+
+    /// Return the id of this profession.
+    pub fn id(self) -> u32 {
+        match self {
+            Profession::Guardian => 1,
+            Profession::Warrior => 2,
+            Profession::Engineer => 3,
+            Profession::Ranger => 4,
+            Profession::Thief => 5,
+            Profession::Elementalist => 6,
+            Profession::Mesmer => 7,
+            Profession::Necromancer => 8,
+            Profession::Revenant => 9,
+            Profession::Unknown(id) => id,
+        }
+    }
+
+    /// Check whether this profession is unknown to evtclib.
+    pub fn is_unknown(self) -> bool {
+        matches!(self, Profession::Unknown(_))
+    }
+}
+
+impl FromPrimitive for Profession {
+    fn from_i64(n: i64) -> Option<Self> {
+        u64::try_from(n).ok().and_then(Self::from_u64)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        let profession = Profession::from_id(u32::try_from(n).ok()?);
+        if profession.is_unknown() {
+            None
+        } else {
+            Some(profession)
+        }
+    }
 }
 
 impl FromStr for Profession {
@@ -815,6 +881,7 @@ impl Display for Profession {
             Profession::Mesmer => "Mesmer",
             Profession::Necromancer => "Necromancer",
             Profession::Revenant => "Revenant",
+            Profession::Unknown(id) => return write!(f, "Unknown profession ({})", id),
         };
         write!(f, "{}", name)
     }
@@ -827,44 +894,54 @@ pub struct ParseEliteSpecError(String);
 
 /// All possible elite specializations.
 ///
-/// Note that the numeric value of the enum variants correspond to the specialization ID in the API
-/// as well. See [the official wiki](https://wiki.guildwars2.com/wiki/API:2/specializations) for
+/// Note that the [ids][EliteSpec::id] of the elite specializations correspond to the
+/// specialization ID in the API as well. See [the official wiki](https://wiki.guildwars2.com/wiki/API:2/specializations) for
 /// more information regarding the API usage.
+///
+/// Elite specializations that are not (yet) known to evtclib are represented by
+/// [`EliteSpec::Unknown`][EliteSpec::Unknown]. Note that [`FromPrimitive`][FromPrimitive] only
+/// returns known specializations, use [`EliteSpec::from_id`][EliteSpec::from_id] if you want the
+/// fallback.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, FromPrimitive)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub enum EliteSpec {
     // Heart of Thorns elites:
-    Dragonhunter = 27,
-    Berserker = 18,
-    Scrapper = 43,
-    Druid = 5,
-    Daredevil = 7,
-    Tempest = 48,
-    Chronomancer = 40,
-    Reaper = 34,
-    Herald = 52,
+    Dragonhunter,
+    Berserker,
+    Scrapper,
+    Druid,
+    Daredevil,
+    Tempest,
+    Chronomancer,
+    Reaper,
+    Herald,
 
     // Path of Fire elites:
-    Firebrand = 62,
-    Spellbreaker = 61,
-    Holosmith = 57,
-    Soulbeast = 55,
-    Deadeye = 58,
-    Weaver = 56,
-    Mirage = 59,
-    Scourge = 60,
-    Renegade = 63,
+    Firebrand,
+    Spellbreaker,
+    Holosmith,
+    Soulbeast,
+    Deadeye,
+    Weaver,
+    Mirage,
+    Scourge,
+    Renegade,
 
     // End of Dragons elites:
-    Willbender = 65,
-    Bladesworn = 68,
-    Mechanist = 70,
-    Untamed = 72,
-    Specter = 71,
-    Catalyst = 67,
-    Virtuoso = 66,
-    Harbinger = 64,
-    Vindicator = 69,
+    Willbender,
+    Bladesworn,
+    Mechanist,
+    Untamed,
+    Specter,
+    Catalyst,
+    Virtuoso,
+    Harbinger,
+    Vindicator,
+
+    /// An elite specialization that is not known to evtclib.
+    ///
+    /// The field contains the specialization id.
+    Unknown(u32),
 }
 
 impl FromStr for EliteSpec {
@@ -937,19 +1014,99 @@ impl Display for EliteSpec {
             EliteSpec::Virtuoso => "Virtuoso",
             EliteSpec::Harbinger => "Harbinger",
             EliteSpec::Vindicator => "Vindicator",
+            EliteSpec::Unknown(id) => return write!(f, "Unknown elite specialization ({})", id),
         };
         write!(f, "{}", name)
     }
 }
 
 impl EliteSpec {
+    /// Return the elite specialization with the given id.
+    ///
+    /// If the id is not known, [`EliteSpec::Unknown`][EliteSpec::Unknown] is returned.
+    pub fn from_id(id: u32) -> EliteSpec {
+        match id {
+            27 => EliteSpec::Dragonhunter,
+            18 => EliteSpec::Berserker,
+            43 => EliteSpec::Scrapper,
+            5 => EliteSpec::Druid,
+            7 => EliteSpec::Daredevil,
+            48 => EliteSpec::Tempest,
+            40 => EliteSpec::Chronomancer,
+            34 => EliteSpec::Reaper,
+            52 => EliteSpec::Herald,
+            62 => EliteSpec::Firebrand,
+            61 => EliteSpec::Spellbreaker,
+            57 => EliteSpec::Holosmith,
+            55 => EliteSpec::Soulbeast,
+            58 => EliteSpec::Deadeye,
+            56 => EliteSpec::Weaver,
+            59 => EliteSpec::Mirage,
+            60 => EliteSpec::Scourge,
+            63 => EliteSpec::Renegade,
+            65 => EliteSpec::Willbender,
+            68 => EliteSpec::Bladesworn,
+            70 => EliteSpec::Mechanist,
+            72 => EliteSpec::Untamed,
+            71 => EliteSpec::Specter,
+            67 => EliteSpec::Catalyst,
+            66 => EliteSpec::Virtuoso,
+            64 => EliteSpec::Harbinger,
+            69 => EliteSpec::Vindicator,
+            _ => EliteSpec::Unknown(id),
+        }
+    }
+
+    /// Return the id of this elite specialization.
+    pub fn id(self) -> u32 {
+        match self {
+            EliteSpec::Dragonhunter => 27,
+            EliteSpec::Berserker => 18,
+            EliteSpec::Scrapper => 43,
+            EliteSpec::Druid => 5,
+            EliteSpec::Daredevil => 7,
+            EliteSpec::Tempest => 48,
+            EliteSpec::Chronomancer => 40,
+            EliteSpec::Reaper => 34,
+            EliteSpec::Herald => 52,
+            EliteSpec::Firebrand => 62,
+            EliteSpec::Spellbreaker => 61,
+            EliteSpec::Holosmith => 57,
+            EliteSpec::Soulbeast => 55,
+            EliteSpec::Deadeye => 58,
+            EliteSpec::Weaver => 56,
+            EliteSpec::Mirage => 59,
+            EliteSpec::Scourge => 60,
+            EliteSpec::Renegade => 63,
+            EliteSpec::Willbender => 65,
+            EliteSpec::Bladesworn => 68,
+            EliteSpec::Mechanist => 70,
+            EliteSpec::Untamed => 72,
+            EliteSpec::Specter => 71,
+            EliteSpec::Catalyst => 67,
+            EliteSpec::Virtuoso => 66,
+            EliteSpec::Harbinger => 64,
+            EliteSpec::Vindicator => 69,
+            EliteSpec::Unknown(id) => id,
+        }
+    }
+
+    /// Check whether this elite specialization is unknown to evtclib.
+    pub fn is_unknown(self) -> bool {
+        matches!(self, EliteSpec::Unknown(_))
+    }
+
     /// Return the profession that this elite specialization belongs to.
     ///
     /// This value is hardcoded (and not expected to change), and does not require a network
     /// connection or API access.
-    pub fn profession(self) -> Profession {
+    ///
+    /// For [unknown][EliteSpec::Unknown] elite specializations, the profession cannot be
+    /// determined and `None` is returned. In that case, the profession of the
+    /// [`Player`][super::Player] can be used instead.
+    pub fn profession(self) -> Option<Profession> {
         use EliteSpec::*;
-        match self {
+        let profession = match self {
             Dragonhunter | Firebrand | Willbender => Profession::Guardian,
             Berserker | Spellbreaker | Bladesworn => Profession::Warrior,
             Scrapper | Holosmith | Mechanist => Profession::Engineer,
@@ -959,6 +1116,23 @@ impl EliteSpec {
             Chronomancer | Mirage | Virtuoso => Profession::Mesmer,
             Reaper | Scourge | Harbinger => Profession::Necromancer,
             Herald | Renegade | Vindicator => Profession::Revenant,
+            Unknown(_) => return None,
+        };
+        Some(profession)
+    }
+}
+
+impl FromPrimitive for EliteSpec {
+    fn from_i64(n: i64) -> Option<Self> {
+        u64::try_from(n).ok().and_then(Self::from_u64)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        let elite = EliteSpec::from_id(u32::try_from(n).ok()?);
+        if elite.is_unknown() {
+            None
+        } else {
+            Some(elite)
         }
     }
 }
@@ -1414,4 +1588,32 @@ mod tests {
             assert!(test.parse::<EliteSpec>().is_err());
         }
     }
+
+    #[test]
+    fn test_profession_ids() {
+        assert_eq!(Profession::from_id(1), Profession::Guardian);
+        assert_eq!(Profession::from_id(42), Profession::Unknown(42));
+        assert_eq!(Profession::Revenant.id(), 9);
+        assert_eq!(Profession::Unknown(42).id(), 42);
+
+        assert_eq!(Profession::from_u32(8), Some(Profession::Necromancer));
+        assert_eq!(Profession::from_u32(42), None);
+        assert_eq!(Profession::from_i64(-1), None);
+    }
+
+    #[test]
+    fn test_elite_spec_ids() {
+        assert_eq!(EliteSpec::from_id(27), EliteSpec::Dragonhunter);
+        assert_eq!(EliteSpec::from_id(1000), EliteSpec::Unknown(1000));
+        assert_eq!(EliteSpec::Vindicator.id(), 69);
+        assert_eq!(EliteSpec::Unknown(1000).id(), 1000);
+        assert_eq!(
+            EliteSpec::Vindicator.profession(),
+            Some(Profession::Revenant)
+        );
+        assert_eq!(EliteSpec::Unknown(1000).profession(), None);
+
+        assert_eq!(EliteSpec::from_u32(5), Some(EliteSpec::Druid));
+        assert_eq!(EliteSpec::from_u32(1000), None);
+    }
 }
@@ -40,11 +40,16 @@
 //! from it. This will also ensure that the buffering is set up correctly, to avoid unnecessary
 //! system calls.
 //!
+//! Both of those functions take a [`Compression`][Compression] that tells evtclib how the log is
+//! stored. If you do not know it in advance, you can use [`Compression::Auto`][Compression::Auto]
+//! to detect it from the content of the file.
+//!
 //! Both of those functions require the reader to be seekable, as that is what we need for zip
-//! archive support. If you cannot provide that, or if you need finer grained control for other
-//! reasons, you can use either [`raw::parse_file`][raw::parse_file] or
-//! [`raw::parse_zip`][raw::parse_zip] to obtain the low-level [`Evtc`][raw::Evtc] structure, and
-//! then turn it into a [`Log`][Log] by using [`process`][process]:
+//! archive support (and for the compression detection). If you cannot provide that, or if you
+//! need finer grained control for other reasons, you can use either
+//! [`raw::parse_file`][raw::parse_file] or [`raw::parse_zip`][raw::parse_zip] to obtain the
+//! low-level [`Evtc`][raw::Evtc] structure, and then turn it into a [`Log`][Log] by using
+//! [`process`][process]:
 //!
 //! ```no_run
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -53,6 +58,9 @@
 //! // Preferred:
 //! let log: Log = evtclib::process_file("my_log.evtc", Compression::None)?;
 //!
+//! // If you do not know whether the log is compressed:
+//! let log: Log = evtclib::process_file("my_log.zevtc", Compression::Auto)?;
+//!
 //! // If you have a stream:
 //! let file = File::open("my_log.evtc")?;
 //! let log: Log = evtclib::process_stream(file, Compression::None)?;
@@ -73,20 +81,35 @@
 //! # }
 //! ```
 //!
+//! If you only need to go through the events once and don't want to keep the whole log in memory
+//! (e.g. for very long WvW logs), you can use [`stream_events`][stream_events] to lazily read and
+//! convert one event at a time.
+//!
 //! Make sure to take a look at the note on "Buffering" in the [parser
 //! module](raw/parser/index.html#buffering) in order to increase the speed of your application.
 //!
 //! # Writing evtc Files
 //!
-//! Currently, `evtclib` does not provide a way to output or modify evtc files. This is for two
-//! reasons:
+//! `evtclib` can write low-level [`Evtc`][raw::Evtc] structures back to evtc files, using
+//! [`raw::write_file`][raw::write_file] or [`raw::write_zip`][raw::write_zip]. This can be used to
+//! modify logs, for example to anonymize or trim them:
 //!
-//! * The only sensible source for logs is the arcdps addon itself, most applications only consume
-//! them.
-//! * The library was needed for reading support, and writing support has never been a priority.
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::fs::File;
+//! use std::io::{BufReader, BufWriter};
+//! let mut raw_log = evtclib::raw::parse_zip(BufReader::new(File::open("my_log.zevtc")?))?;
+//! // Only keep the first 1000 events
+//! raw_log.events.truncate(1000);
+//! evtclib::raw::write_zip(&raw_log, BufWriter::new(File::create("trimmed.zevtc")?))?;
+//! # Ok(())
+//! # }
+//! ```
 //!
-//! While there are legitimate use cases for writing/modification support, they are currently not
-//! implemented (but might be in a future version).
+//! There is no way to turn a high-level [`Log`][Log] back into an evtc file, as the processing
+//! step is lossy.
+
+use std::convert::TryFrom;
 
 use num_traits::FromPrimitive;
 use thiserror::Error;
@@ -94,13 +117,37 @@ use thiserror::Error;
 pub mod raw;
 
 mod agent;
-pub use agent::{Agent, AgentKind, Character, Gadget, Player};
+pub use agent::{Agent, AgentKind, Character, Gadget, InstancePeriod, Player};
 
 pub mod event;
-pub use event::{Event, EventKind};
+pub use event::{Event, EventCategory, EventKind};
+
+mod skill;
+pub use skill::Skill;
+
+mod metadata;
+pub use metadata::LogMetadata;
+
+mod diagnostics;
+pub use diagnostics::{Diagnostics, DroppedEvent};
 
 mod processing;
-pub use processing::{process, process_file, process_stream, Compression};
+pub use processing::{
+    process, process_archive_entry, process_file, process_file_with_options, process_stream,
+    process_stream_with_options, process_with_options, stream_events, Compression, EventStream,
+    LogBuilder, ProcessingOptions,
+};
+#[cfg(feature = "async")]
+pub use processing::{process_stream_async, process_stream_async_with_options};
+
+mod merge;
+pub use merge::{merge_logs, MergeError};
+
+mod scan;
+pub use scan::{find_logs, scan_file, LogFile, LogFiles, LogHeader};
+
+pub mod database;
+pub use database::{BuffDatabase, SkillDatabase};
 
 pub mod gamedata;
 use gamedata::Boss;
@@ -134,6 +181,40 @@ pub enum EvtcError {
     /// The file contains invalid utf-8.
     #[error("utf8 decoding error: {0}")]
     Utf8Error(#[from] std::str::Utf8Error),
+    /// The format of the file could not be detected.
+    ///
+    /// This is returned by [`Compression::detect`][Compression::detect] (and therefore when using
+    /// [`Compression::Auto`][Compression::Auto]) if the file is neither an evtc file nor a
+    /// supported archive.
+    #[error("the file format could not be detected")]
+    UnknownFormat,
+    /// The compression is known, but support for it has not been enabled.
+    ///
+    /// Enable the corresponding cargo feature (`gzip`, `zstd` or `xz`) to parse those files.
+    #[error("support for {0:?} compression is not enabled")]
+    UnsupportedCompression(Compression),
+    /// An entry of a multi-log archive could not be processed.
+    ///
+    /// See [`process_archive_entry`][process_archive_entry].
+    #[error("archive entry {name}: {source}")]
+    ArchiveEntry {
+        /// The name of the entry that failed.
+        name: String,
+        /// The error that occured while processing the entry.
+        source: Box<EvtcError>,
+    },
+}
+
+/// The point in time that relative times are measured from.
+///
+/// See [`Log::relative_time`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TimeOrigin {
+    /// The start of the log.
+    LogStart,
+    /// The start of the fight, i.e. the first time that a boss dealt or took damage.
+    FightStart,
 }
 
 /// A fully processed log file.
@@ -143,8 +224,31 @@ pub struct Log {
     // evtclib assumes that the agents in this vector are sorted by their address. This information
     // is used to speed up the agent_by_addr search.
     agents: Vec<Agent>,
+    // Apart from the events and the boss id, the following fields were added later. They fall back
+    // to their defaults, so that logs serialized by older versions of evtclib can still be read.
+    //
+    // Similarly to the agents, the skills are sorted by their id, which is used in skill_by_id.
+    #[cfg_attr(feature = "serde", serde(default))]
+    skills: Vec<Skill>,
     events: Vec<Event>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    dropped_events: Vec<DroppedEvent>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    raw_events: Option<Vec<raw::CbtEvent>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    raw_event_count: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    unknown_statechanges: usize,
+    // Sorted by instance id and then by the start of the period, see agent_by_instance_id_at.
+    #[cfg_attr(feature = "serde", serde(default))]
+    instance_periods: Vec<InstancePeriod>,
     boss_id: u16,
+    #[cfg_attr(feature = "serde", serde(default))]
+    metadata: LogMetadata,
+    #[cfg_attr(feature = "serde", serde(default))]
+    buff_database: BuffDatabase,
+    #[cfg_attr(feature = "serde", serde(default))]
+    skill_database: SkillDatabase,
 }
 
 impl Log {
@@ -164,11 +268,75 @@ impl Log {
             .map(|i| &self.agents[i])
     }
 
+    /// Return all skills from the skill table of this log.
+    ///
+    /// The skills are sorted by their id.
+    #[inline]
+    pub fn skills(&self) -> &[Skill] {
+        &self.skills
+    }
+
+    /// Return a skill based on its id.
+    pub fn skill_by_id(&self, id: u32) -> Option<&Skill> {
+        self.skills
+            .binary_search_by_key(&id, Skill::id)
+            .ok()
+            .map(|i| &self.skills[i])
+    }
+
+    /// Return the name of the skill with the given id.
+    ///
+    /// This is a shorthand for [`skill_by_id`][Log::skill_by_id] followed by
+    /// [`Skill::name`][Skill::name].
+    pub fn skill_name(&self, id: u32) -> Option<&str> {
+        self.skill_by_id(id).map(Skill::name)
+    }
+
     /// Return an agent based on the instance ID.
+    ///
+    /// This only compares the last instance id that each agent had, and returns the first agent
+    /// (in the order of [`agents`][Log::agents]) that matches. As the game reuses instance ids,
+    /// this is not necessarily the agent that had the instance id at a specific time, use
+    /// [`agent_by_instance_id_at`][Log::agent_by_instance_id_at] for that.
     pub fn agent_by_instance_id(&self, instance_id: u16) -> Option<&Agent> {
         self.agents.iter().find(|a| a.instance_id() == instance_id)
     }
 
+    /// Return the agent that had the given instance ID at the given time.
+    ///
+    /// This is the right way to resolve the instance ids of events (such as
+    /// [`raw::CbtEvent::src_master_instid`][raw::CbtEvent::src_master_instid]), as the game reuses
+    /// instance ids, for example for adds that spawn and die repeatedly.
+    ///
+    /// The periods are only known if the agents have been resolved, see
+    /// [`ProcessingOptions::resolve_agents`][ProcessingOptions::resolve_agents]. If multiple agents
+    /// had the instance id at the given time, the one that got it last is returned.
+    ///
+    /// The periods include their first and last event (see [`InstancePeriod::contains`]). The same
+    /// rule is used to find the [master agents][Agent::master_agent], so for an event with a
+    /// [`src_master_instid`][raw::CbtEvent::src_master_instid], this returns the master of its
+    /// source agent.
+    pub fn agent_by_instance_id_at(&self, instance_id: u16, time: u64) -> Option<&Agent> {
+        agent::find_instance_period(&self.instance_periods, instance_id, time)
+            .and_then(|p| self.agent_by_addr(p.addr()))
+    }
+
+    /// Return all periods in which the agent with the given address had an instance id, in
+    /// chronological order.
+    ///
+    /// The periods are stored by instance id, so this goes through the periods of all agents and
+    /// allocates a new vector on every call. Prefer to cache the result if you need it repeatedly.
+    pub fn instance_periods(&self, addr: u64) -> Vec<InstancePeriod> {
+        let mut periods = self
+            .instance_periods
+            .iter()
+            .filter(|p| p.addr() == addr)
+            .copied()
+            .collect::<Vec<_>>();
+        periods.sort_by_key(|p| p.first_aware());
+        periods
+    }
+
     /// Return the master agent of the given agent.
     ///
     /// * `addr` - The address of the agent which to get the master for.
@@ -264,6 +432,33 @@ impl Log {
         &self.events
     }
 
+    /// Return all events that could not be converted.
+    ///
+    /// Those are raw events that are either not supported by evtclib, or that contain invalid
+    /// data. They are kept together with the reason of the failed conversion, so that no data is
+    /// silently lost. See also [`diagnostics`][Log::diagnostics].
+    pub fn dropped_events(&self) -> &[DroppedEvent] {
+        &self.dropped_events
+    }
+
+    /// Return the raw events of this log.
+    ///
+    /// The raw events are only kept if
+    /// [`ProcessingOptions::retain_raw_events`][ProcessingOptions::retain_raw_events] was set,
+    /// otherwise `None` is returned.
+    pub fn raw_events(&self) -> Option<&[raw::CbtEvent]> {
+        self.raw_events.as_deref()
+    }
+
+    /// Return a report about how many events could not be converted, and why.
+    pub fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::new(
+            self.raw_event_count,
+            self.unknown_statechanges,
+            &self.dropped_events,
+        )
+    }
+
     /// Returns the timespan of the log in milliseconds.
     ///
     /// The timespan is the time between the first registered event and the last registered event,
@@ -280,6 +475,16 @@ impl Log {
         last - first
     }
 
+    /// Check whether this log and the given log were recorded during the same fight.
+    ///
+    /// This is the case if both logs are for the same encounter on the same map and shard, were
+    /// recorded at overlapping server times and share at least one player. Values that are missing
+    /// in one of the logs (e.g. in older logs) are not compared. Logs of the same fight can be
+    /// combined with [`merge_logs`][merge_logs].
+    pub fn is_same_fight(&self, other: &Log) -> bool {
+        merge::is_same_fight(self, other)
+    }
+
     /// Check whether this log represents a "generic" log.
     ///
     /// A generic log is a log that is not tied to a specific boss ID, but rather is triggered by
@@ -305,6 +510,110 @@ impl Log {
             self.encounter().map(Encounter::game_mode)
         }
     }
+
+    /// Returns the metadata of this log.
+    pub fn metadata(&self) -> &LogMetadata {
+        &self.metadata
+    }
+
+    /// Get the timestamp of when the log was started.
+    ///
+    /// The returned value is a unix timestamp in the local time zone.
+    ///
+    /// If the [`LogStart`][EventKind::LogStart] event cannot be found, this function returns
+    /// `None`.
+    pub fn local_start_timestamp(&self) -> Option<u32> {
+        self.metadata.local_start_timestamp()
+    }
+
+    /// Get the timestamp of when the log was ended.
+    ///
+    /// The returned value is a unix timestamp in the local time zone.
+    ///
+    /// If the [`LogEnd`][EventKind::LogEnd] event cannot be found, this function returns `None`.
+    pub fn local_end_timestamp(&self) -> Option<u32> {
+        self.metadata.local_end_timestamp()
+    }
+
+    /// Convert the given event time to a unix timestamp in milliseconds (UTC).
+    ///
+    /// Event times are measured by the clock of the computer that recorded the log, so they are
+    /// mapped to the wall clock using the server timestamps of the [`LogStart`][EventKind::LogStart]
+    /// and [`LogEnd`][EventKind::LogEnd] events. As those timestamps only have a precision of one
+    /// second, the result can be off by up to half a second.
+    ///
+    /// If neither event can be found, or if the result does not fit into an `i64` (which can only
+    /// happen for corrupted logs), this function returns `None`.
+    pub fn utc_timestamp_millis(&self, event_time: u64) -> Option<i64> {
+        // The server timestamps are truncated to seconds, so each of them only gives a range of
+        // possible offsets between the clocks. If both are available, the range can be narrowed
+        // down further.
+        let offsets = |(timestamp, time): (u32, u64)| {
+            let low = (i64::from(timestamp) * 1000).checked_sub(i64::try_from(time).ok()?)?;
+            Some((low, low.checked_add(999)?))
+        };
+        let start = self
+            .metadata
+            .server_start_timestamp()
+            .zip(self.metadata.log_start_time())
+            .and_then(offsets);
+        let end = self
+            .metadata
+            .server_end_timestamp()
+            .zip(self.metadata.log_end_time())
+            .and_then(offsets);
+        let (low, high) = match (start, end) {
+            (Some(start), Some(end)) if start.0.max(end.0) <= start.1.min(end.1) => {
+                (start.0.max(end.0), start.1.min(end.1))
+            }
+            (Some(start), _) => start,
+            (None, Some(end)) => end,
+            (None, None) => return None,
+        };
+        // Both bounds are at most 999 apart, so their mean cannot overflow.
+        let offset = low + (high - low) / 2;
+        i64::try_from(event_time).ok()?.checked_add(offset)
+    }
+
+    /// Convert the given event time to a UTC date and time.
+    ///
+    /// See [`Log::utc_timestamp_millis`] for details about the conversion. Requires the `chrono`
+    /// feature.
+    #[cfg(feature = "chrono")]
+    pub fn to_utc(&self, event_time: u64) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp_millis(self.utc_timestamp_millis(event_time)?)
+    }
+
+    /// Convert the given event time to a UTC date and time.
+    ///
+    /// See [`Log::utc_timestamp_millis`] for details about the conversion. Requires the `time`
+    /// feature.
+    #[cfg(feature = "time")]
+    pub fn to_offset_date_time(&self, event_time: u64) -> Option<time::OffsetDateTime> {
+        let nanos = i128::from(self.utc_timestamp_millis(event_time)?) * 1_000_000;
+        time::OffsetDateTime::from_unix_timestamp_nanos(nanos).ok()
+    }
+
+    /// Returns the game's build id.
+    ///
+    /// If no build id was found, `None` is returned.
+    pub fn build_id(&self) -> Option<u64> {
+        self.metadata.gw2_build()
+    }
+
+    /// Returns the buff information that arcdps has written to this log.
+    ///
+    /// The database is built once during processing, so this is cheap to call.
+    pub fn buff_database(&self) -> &BuffDatabase {
+        &self.buff_database
+    }
+
+    /// Returns the skill information that arcdps has written to this log.
+    ///
+    /// The database is built once during processing, so this is cheap to call.
+    pub fn skill_database(&self) -> &SkillDatabase {
+        &self.skill_database
+    }
 }
 
 /// Convenience data accessing funtions for [`Log`][Log]s.
@@ -330,43 +639,6 @@ impl Log {
         self.analyzer().map(|a| a.is_cm()).unwrap_or(false)
     }
 
-    /// Get the timestamp of when the log was started.
-    ///
-    /// The returned value is a unix timestamp in the local time zone.
-    ///
-    /// If the [`LogStart`][EventKind::LogStart] event cannot be found, this function returns
-    /// `None`.
-    pub fn local_start_timestamp(&self) -> Option<u32> {
-        self.events().iter().find_map(|e| {
-            if let EventKind::LogStart {
-                local_timestamp, ..
-            } = e.kind()
-            {
-                Some(*local_timestamp)
-            } else {
-                None
-            }
-        })
-    }
-
-    /// Get the timestamp of when the log was ended.
-    ///
-    /// The returned value is a unix timestamp in the local time zone.
-    ///
-    /// If the [`LogEnd`][EventKind::LogEnd] event cannot be found, this function returns `None`.
-    pub fn local_end_timestamp(&self) -> Option<u32> {
-        self.events().iter().find_map(|e| {
-            if let EventKind::LogEnd {
-                local_timestamp, ..
-            } = e.kind()
-            {
-                Some(*local_timestamp)
-            } else {
-                None
-            }
-        })
-    }
-
     /// Check if rewards for this fight have been given out.
     ///
     /// This can be used as an indication whether the fight was successful (`true`) or not
@@ -381,6 +653,63 @@ impl Log {
             .any(|e| matches!(e.kind(), EventKind::Reward { .. }))
     }
 
+    /// Returns the time (on the clock of the events) that relative times are measured from.
+    ///
+    /// * [`TimeOrigin::LogStart`] is the time of the [`LogStart`][EventKind::LogStart] event, or
+    ///   the time of the first event if it cannot be found.
+    /// * [`TimeOrigin::FightStart`] is the time of the first damage event (of the
+    ///   [`Damage`][EventCategory::Damage] category) that was dealt or taken by a boss. If there is
+    ///   no such event (for example in WvW logs), the start of the log is used instead.
+    pub fn origin_time(&self, origin: TimeOrigin) -> u64 {
+        let log_start = || {
+            self.metadata
+                .log_start_time()
+                .or_else(|| self.events().first().map(Event::time))
+                .unwrap_or(0)
+        };
+        match origin {
+            TimeOrigin::LogStart => log_start(),
+            TimeOrigin::FightStart => self
+                .events()
+                .iter()
+                .find(|e| match *e.kind() {
+                    EventKind::Physical {
+                        source_agent_addr,
+                        destination_agent_addr,
+                        ..
+                    }
+                    | EventKind::ConditionTick {
+                        source_agent_addr,
+                        destination_agent_addr,
+                        ..
+                    }
+                    | EventKind::InvulnTick {
+                        source_agent_addr,
+                        destination_agent_addr,
+                        ..
+                    } => self.is_boss(source_agent_addr) || self.is_boss(destination_agent_addr),
+                    _ => false,
+                })
+                .map(Event::time)
+                .unwrap_or_else(log_start),
+        }
+    }
+
+    /// Returns the time of the event in milliseconds, relative to the given origin.
+    ///
+    /// The result is negative for events that happened before the origin, such as buffs that were
+    /// applied before the fight started.
+    ///
+    /// As the fight start has to be searched in the events, use [`Log::origin_time`] once instead
+    /// of this function when converting many events.
+    ///
+    /// If the difference does not fit into an `i64` (which can only happen for corrupted logs),
+    /// `None` is returned.
+    pub fn relative_time(&self, event: &Event, origin: TimeOrigin) -> Option<i64> {
+        let time = i64::try_from(event.time()).ok()?;
+        time.checked_sub(i64::try_from(self.origin_time(origin)).ok()?)
+    }
+
     /// Returns all error strings that were captured.
     ///
     /// If no errors were encountered, an empty vec is returned.
@@ -399,16 +728,4 @@ impl Log {
             })
             .collect()
     }
-
-    /// Returns the game's build id.
-    ///
-    /// If no build id was found, `None` is returned.
-    pub fn build_id(&self) -> Option<u64> {
-        for event in self.events() {
-            if let EventKind::Build { build } = event.kind() {
-                return Some(*build);
-            }
-        }
-        None
-    }
 }
@@ -0,0 +1,827 @@
+//! Private module to contain the merging of logs.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::mem::{self, Discriminant};
+
+use thiserror::Error;
+
+use super::event::EffectLocation;
+use super::{Agent, AgentKind, Event, EventCategory, EventKind, Log};
+
+/// The maximum difference (in milliseconds) between the clock offset that is derived from the
+/// server timestamps (which only have a precision of seconds) and the actual offset.
+const ALIGN_TOLERANCE: i64 = 2000;
+
+/// The width (in milliseconds) of the window in which clock offsets are considered to agree.
+const CLUSTER_WIDTH: i64 = 50;
+
+/// The maximum time difference (in milliseconds, after the clocks have been aligned) between two
+/// events that are considered to be the same.
+const MATCH_WINDOW: u64 = 100;
+
+/// Any error that can occur when merging logs.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MergeError {
+    /// No logs were given.
+    #[error("no logs were given")]
+    NoLogs,
+    /// The log at the given index is not from the same fight as the first log.
+    ///
+    /// See [`Log::is_same_fight`][Log::is_same_fight].
+    #[error("log {0} is not from the same fight")]
+    DifferentFight(usize),
+    /// The clock of the log at the given index could not be aligned to the first log.
+    ///
+    /// This happens if the logs have neither server timestamps nor shared events.
+    #[error("the clock of log {0} could not be aligned")]
+    UnalignedClock(usize),
+}
+
+/// Merge multiple logs of the same fight into a single log.
+///
+/// arcdps only records what the local client knows about, so every log of a fight only contains
+/// the events that happened in range of its point of view. If multiple players recorded the same
+/// fight, this function combines their logs into one [`Log`][Log] that contains the agents and
+/// events of all of them:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use evtclib::Compression;
+/// let first = evtclib::process_file("first.zevtc", Compression::Auto)?;
+/// let second = evtclib::process_file("second.zevtc", Compression::Auto)?;
+/// if first.is_same_fight(&second) {
+///     let merged = evtclib::merge_logs(&[first, second])?;
+///     println!("{} events", merged.events().len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Every client has its own agent addresses and its own clock, so the logs are merged as follows:
+///
+/// * Players are matched by their account and character name.
+/// * The clock offset between the logs is estimated from the server timestamps, and then refined
+///   using the events (such as damage and movement) that appear in both logs.
+/// * Other agents are matched by the events that they share with already matched agents. If that
+///   is not possible, an agent of the same kind that is aware at the same time is used.
+/// * Events that appear in multiple logs (at roughly the same time) are only kept once.
+///
+/// The first log is used as the base: Its agent addresses, times, metadata and instance ids are
+/// kept, and the other logs are added to it. As instance ids are assigned by each client, the
+/// [instance id lookups][Log::agent_by_instance_id_at] only know about the instance ids of the
+/// first log. The metadata events of the other logs (except for
+/// [`PointOfView`][EventKind::PointOfView]) are dropped, and the merged log never contains raw
+/// events. The events of the first log keep their order, and the events of the other logs are
+/// inserted according to their (aligned) time.
+///
+/// The [dropped events][Log::dropped_events] of all logs are kept as they are, with the agent
+/// addresses and times of their own log: As they could not be converted, it is not known which of
+/// their fields refer to agents, so they cannot be remapped reliably. The
+/// [diagnostics][Log::diagnostics] count the raw events and the skipped events with unknown
+/// statechanges of all logs. The [buff][Log::buff_database] and [skill][Log::skill_database]
+/// databases contain the information of all logs, where the first log that knows about a buff or
+/// skill is used.
+///
+/// As the matching is based on heuristics, it can fail for agents that do not interact with any
+/// player (such as NPCs that are only seen by a single client), in which case they show up as
+/// separate agents in the merged log. Likewise, the clocks can only be aligned precisely if the
+/// logs overlap in time.
+pub fn merge_logs(logs: &[Log]) -> Result<Log, MergeError> {
+    let (first, rest) = logs.split_first().ok_or(MergeError::NoLogs)?;
+    let mut merged = first.clone();
+    merged.raw_events = None;
+    for (index, log) in rest.iter().enumerate() {
+        let index = index + 1;
+        if !is_same_fight(first, log) {
+            return Err(MergeError::DifferentFight(index));
+        }
+        merge_into(&mut merged, log).ok_or(MergeError::UnalignedClock(index))?;
+    }
+    Ok(merged)
+}
+
+pub(crate) fn is_same_fight(left: &Log, right: &Log) -> bool {
+    fn agree<T: PartialEq>(left: Option<T>, right: Option<T>) -> bool {
+        match (left, right) {
+            (Some(left), Some(right)) => left == right,
+            _ => true,
+        }
+    }
+
+    let (left_meta, right_meta) = (left.metadata(), right.metadata());
+    if left.encounter_id() != right.encounter_id()
+        || !agree(left_meta.map_id(), right_meta.map_id())
+        || !agree(left_meta.shard_id(), right_meta.shard_id())
+    {
+        return false;
+    }
+    if let (Some(left), Some(right)) = (server_range(left), server_range(right)) {
+        if left.0 > right.1 || right.0 > left.1 {
+            return false;
+        }
+    }
+    let players = left
+        .players()
+        .map(|p| identity(p.erase()))
+        .collect::<HashSet<_>>();
+    right
+        .players()
+        .any(|p| players.contains(&identity(p.erase())))
+}
+
+/// Returns the server timestamps (in seconds) between which the log was recorded.
+fn server_range(log: &Log) -> Option<(u64, u64)> {
+    let start = u64::from(log.metadata().server_start_timestamp()?);
+    let end = log
+        .metadata()
+        .server_end_timestamp()
+        .map(u64::from)
+        .unwrap_or_else(|| start + log.span() / 1000);
+    // The timestamps are rounded to seconds.
+    Some((start, end + 1))
+}
+
+/// The properties of an agent that are the same for every client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Identity<'a> {
+    Player {
+        account_name: &'a str,
+        character_name: &'a str,
+    },
+    Character(u16),
+    Gadget(u16, &'a str),
+}
+
+fn identity(agent: &Agent) -> Identity<'_> {
+    match agent.kind() {
+        AgentKind::Player(player) => Identity::Player {
+            account_name: player.account_name(),
+            character_name: player.character_name(),
+        },
+        AgentKind::Character(character) => Identity::Character(character.id()),
+        AgentKind::Gadget(gadget) => Identity::Gadget(gadget.id(), gadget.name()),
+    }
+}
+
+/// Returns the aware times of the agent, shifted by the given offset.
+///
+/// Agents that are never referenced by an event are aware during the whole log, in which case
+/// `None` is returned.
+fn aware_times(agent: &Agent, offset: i64) -> Option<(u64, u64)> {
+    if agent.first_aware() == 0 && agent.last_aware() == u64::MAX {
+        return None;
+    }
+    Some((
+        shift(agent.first_aware(), offset),
+        shift(agent.last_aware(), offset),
+    ))
+}
+
+fn shift(time: u64, offset: i64) -> u64 {
+    time.saturating_add_signed(offset)
+}
+
+/// The parts of an event that are the same for every client, apart from the agents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Signature {
+    kind: Discriminant<EventKind>,
+    id: u32,
+    value: i64,
+}
+
+/// Returns the signature, the source agent and the destination agent of the event.
+fn signature(event: &Event) -> Option<(Signature, u64, u64)> {
+    let (source, destination, id, value) = match *event.kind() {
+        EventKind::Physical {
+            source_agent_addr,
+            destination_agent_addr,
+            skill_id,
+            damage,
+            ..
+        } => (
+            source_agent_addr,
+            destination_agent_addr,
+            skill_id,
+            damage.into(),
+        ),
+        EventKind::ConditionTick {
+            source_agent_addr,
+            destination_agent_addr,
+            condition_id,
+            damage,
+        } => (
+            source_agent_addr,
+            destination_agent_addr,
+            condition_id,
+            damage.into(),
+        ),
+        EventKind::BuffApplication {
+            source_agent_addr,
+            destination_agent_addr,
+            buff_id,
+            duration,
+            ..
+        } => (
+            source_agent_addr,
+            destination_agent_addr,
+            buff_id,
+            duration.into(),
+        ),
+        EventKind::BuffRemove {
+            source_agent_addr,
+            destination_agent_addr,
+            buff_id,
+            total_duration,
+            ..
+        } => (
+            source_agent_addr,
+            destination_agent_addr,
+            buff_id,
+            total_duration.into(),
+        ),
+        // Positions are unique enough to identify the agent. They have no destination, which is
+        // represented by the address 0.
+        EventKind::Position {
+            agent_addr,
+            x,
+            y,
+            z,
+        }
+        | EventKind::Velocity {
+            agent_addr,
+            x,
+            y,
+            z,
+        } => (
+            agent_addr,
+            0,
+            x.to_bits(),
+            i64::from(y.to_bits()) << 32 | i64::from(z.to_bits()),
+        ),
+        EventKind::Facing { agent_addr, x, y } => {
+            (agent_addr, 0, x.to_bits(), i64::from(y.to_bits()))
+        }
+        _ => return None,
+    };
+    let signature = Signature {
+        kind: mem::discriminant(event.kind()),
+        id,
+        value,
+    };
+    Some((signature, source, destination))
+}
+
+/// Returns the entries of the time-sorted slice that are within the given window around `time`.
+fn window<T>(entries: &[(u64, T)], time: u64, width: u64) -> &[(u64, T)] {
+    let start = entries.partition_point(|e| e.0.saturating_add(width) < time);
+    let end = entries.partition_point(|e| e.0 <= time.saturating_add(width));
+    &entries[start..end.max(start)]
+}
+
+/// Merges the given log into the merged log.
+///
+/// Returns `None` if the clocks could not be aligned.
+fn merge_into(merged: &mut Log, log: &Log) -> Option<()> {
+    let offset = clock_offset(merged, log)?;
+    let mut mapping = match_players(merged, log);
+    match_by_events(merged, log, offset, &mut mapping);
+    match_by_identity(merged, log, offset, &mut mapping);
+    let unmatched = log
+        .agents()
+        .iter()
+        .map(Agent::addr)
+        .filter(|addr| !mapping.contains_key(addr))
+        .collect::<HashSet<_>>();
+    merge_agents(merged, log, offset, &mut mapping);
+    merge_events(merged, log, offset, &mapping, &unmatched);
+
+    merged.raw_event_count += log.raw_event_count;
+    merged.unknown_statechanges += log.unknown_statechanges;
+    merged
+        .dropped_events
+        .extend(log.dropped_events.iter().cloned());
+    merged.buff_database.merge(&log.buff_database);
+    merged.skill_database.merge(&log.skill_database);
+
+    for skill in log.skills() {
+        if let Err(index) = merged.skills.binary_search_by_key(&skill.id(), |s| s.id()) {
+            merged.skills.insert(index, skill.clone());
+        }
+    }
+    Some(())
+}
+
+/// Maps the addresses of the players in the log to the addresses in the merged log.
+fn match_players(merged: &Log, log: &Log) -> HashMap<u64, u64> {
+    let players = merged
+        .players()
+        .map(|p| (identity(p.erase()), p.addr()))
+        .collect::<HashMap<_, _>>();
+    log.players()
+        .filter_map(|p| Some((p.addr(), *players.get(&identity(p.erase()))?)))
+        .collect()
+}
+
+/// Estimates the offset that has to be added to the times of the log to get the times of the
+/// merged log.
+///
+/// As the agents are not matched yet, the events are compared using the kinds of the agents.
+fn clock_offset(merged: &Log, log: &Log) -> Option<i64> {
+    type Key<'a> = (Signature, Option<Identity<'a>>, Option<Identity<'a>>);
+    fn key<'a>(log: &'a Log, event: &Event) -> Option<Key<'a>> {
+        let (signature, source, destination) = signature(event)?;
+        let source = log.agent_by_addr(source).map(identity);
+        let destination = log.agent_by_addr(destination).map(identity);
+        Some((signature, source, destination))
+    }
+
+    let coarse = coarse_offset(merged, log);
+
+    let mut times: HashMap<_, Vec<u64>> = HashMap::new();
+    for event in merged.events() {
+        if let Some(key) = key(merged, event) {
+            times.entry(key).or_default().push(event.time());
+        }
+    }
+    for times in times.values_mut() {
+        times.sort_unstable();
+    }
+
+    let mut offsets = Vec::new();
+    for event in log.events() {
+        let Some(times) = key(log, event).and_then(|key| times.get(&key)) else {
+            continue;
+        };
+        let time = event.time() as i64;
+        match coarse {
+            Some(coarse) => {
+                let expected = time + coarse;
+                let start = times.partition_point(|&t| (t as i64) < expected - ALIGN_TOLERANCE);
+                let end = times.partition_point(|&t| (t as i64) <= expected + ALIGN_TOLERANCE);
+                offsets.extend(times[start..end].iter().map(|&t| t as i64 - time));
+            }
+            // Without a rough estimate, only unique events are reliable.
+            None if times.len() == 1 => offsets.push(times[0] as i64 - time),
+            None => (),
+        }
+    }
+
+    if offsets.is_empty() {
+        return coarse;
+    }
+    // Find the densest cluster of offsets and take its median.
+    offsets.sort_unstable();
+    let (mut best_start, mut best_len) = (0, 0);
+    let mut start = 0;
+    for end in 0..offsets.len() {
+        while offsets[end] - offsets[start] > CLUSTER_WIDTH {
+            start += 1;
+        }
+        if end - start + 1 > best_len {
+            (best_start, best_len) = (start, end - start + 1);
+        }
+    }
+    Some(offsets[best_start + best_len / 2])
+}
+
+/// Estimates the clock offset from the log start times in the metadata.
+///
+/// The metadata is used instead of the [`LogStart`][EventKind::LogStart] events, as it is
+/// available even if the metadata events were not kept.
+fn coarse_offset(merged: &Log, log: &Log) -> Option<i64> {
+    fn start(log: &Log) -> Option<i64> {
+        let metadata = log.metadata();
+        let time = i64::try_from(metadata.log_start_time()?).ok()?;
+        time.checked_sub(i64::from(metadata.server_start_timestamp()?) * 1000)
+    }
+    start(merged)?.checked_sub(start(log)?)
+}
+
+/// Matches the agents that share events with already matched agents.
+fn match_by_events(merged: &Log, log: &Log, offset: i64, mapping: &mut HashMap<u64, u64>) {
+    // The events of the merged log, indexed by the signature and one of the agents.
+    let mut by_source: HashMap<_, Vec<(u64, u64)>> = HashMap::new();
+    let mut by_destination: HashMap<_, Vec<(u64, u64)>> = HashMap::new();
+    for event in merged.events() {
+        if let Some((signature, source, destination)) = signature(event) {
+            by_source
+                .entry((signature, source))
+                .or_default()
+                .push((event.time(), destination));
+            by_destination
+                .entry((signature, destination))
+                .or_default()
+                .push((event.time(), source));
+        }
+    }
+    for entries in by_source.values_mut().chain(by_destination.values_mut()) {
+        entries.sort_unstable();
+    }
+
+    let claimed = mapping.values().copied().collect::<HashSet<_>>();
+    let mut votes: HashMap<(u64, u64), u32> = HashMap::new();
+    for event in log.events() {
+        let Some((signature, source, destination)) = signature(event) else {
+            continue;
+        };
+        let known = |addr| match addr {
+            0 => Some(0),
+            _ => mapping.get(&addr).copied(),
+        };
+        let (entries, unmatched) = match (known(source), known(destination)) {
+            (Some(source), None) => (by_source.get(&(signature, source)), destination),
+            (None, Some(destination)) => (by_destination.get(&(signature, destination)), source),
+            _ => continue,
+        };
+        let (Some(entries), Some(agent)) = (entries, log.agent_by_addr(unmatched)) else {
+            continue;
+        };
+        let time = shift(event.time(), offset);
+        let mut candidates = window(entries, time, MATCH_WINDOW)
+            .iter()
+            .map(|&(_, candidate)| candidate)
+            .filter(|candidate| {
+                !claimed.contains(candidate)
+                    && merged
+                        .agent_by_addr(*candidate)
+                        .is_some_and(|c| identity(c) == identity(agent))
+            });
+        // Events that could belong to multiple agents (e.g. area damage that hits multiple adds
+        // of the same kind) are ambiguous and therefore not counted.
+        if let Some(candidate) = candidates.next() {
+            if candidates.all(|c| c == candidate) {
+                *votes.entry((unmatched, candidate)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut votes = votes.into_iter().collect::<Vec<_>>();
+    votes.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let mut claimed = claimed;
+    for ((addr, candidate), _) in votes {
+        if !mapping.contains_key(&addr) && claimed.insert(candidate) {
+            mapping.insert(addr, candidate);
+        }
+    }
+}
+
+/// Matches the remaining agents to unmatched agents of the same kind that are aware at the same
+/// time.
+///
+/// Agents whose aware times are closer together are matched first.
+fn match_by_identity(merged: &Log, log: &Log, offset: i64, mapping: &mut HashMap<u64, u64>) {
+    let mut claimed = mapping.values().copied().collect::<HashSet<_>>();
+    let mut candidates: HashMap<_, Vec<&Agent>> = HashMap::new();
+    for agent in merged.agents() {
+        if !claimed.contains(&agent.addr()) {
+            candidates.entry(identity(agent)).or_default().push(agent);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for agent in log.agents() {
+        if mapping.contains_key(&agent.addr()) {
+            continue;
+        }
+        let Some(candidates) = candidates.get(&identity(agent)) else {
+            continue;
+        };
+        let aware = aware_times(agent, offset);
+        for candidate in candidates {
+            let rank = match (aware, aware_times(candidate, 0)) {
+                (Some(a), Some(c)) if a.0 <= c.1 && c.0 <= a.1 => {
+                    (0, a.0.abs_diff(c.0) + a.1.abs_diff(c.1))
+                }
+                (Some(_), Some(_)) => continue,
+                // An agent without events in one of the logs could be any of the candidates, so
+                // those are matched last.
+                (Some(_), None) | (None, Some(_)) => (1, 0),
+                (None, None) => (2, 0),
+            };
+            pairs.push((rank, agent.addr(), candidate.addr()));
+        }
+    }
+
+    pairs.sort_unstable();
+    for (_, addr, candidate) in pairs {
+        if !mapping.contains_key(&addr) && claimed.insert(candidate) {
+            mapping.insert(addr, candidate);
+        }
+    }
+}
+
+/// Adds the unmatched agents to the merged log and extends the aware times of the matched ones.
+///
+/// Afterwards, the mapping contains all agents of the log.
+fn merge_agents(merged: &mut Log, log: &Log, offset: i64, mapping: &mut HashMap<u64, u64>) {
+    let mut used = merged
+        .agents
+        .iter()
+        .map(Agent::addr)
+        .chain(mapping.values().copied())
+        .collect::<HashSet<_>>();
+    let mut next_addr = merged
+        .agents
+        .iter()
+        .chain(log.agents())
+        .map(|a| a.addr())
+        .max()
+        .unwrap_or(0);
+    for agent in log.agents() {
+        if mapping.contains_key(&agent.addr()) {
+            continue;
+        }
+        let addr = if used.insert(agent.addr()) {
+            agent.addr()
+        } else {
+            next_addr += 1;
+            used.insert(next_addr);
+            next_addr
+        };
+        mapping.insert(agent.addr(), addr);
+    }
+
+    let map = |addr: u64| mapping.get(&addr).copied().unwrap_or(addr);
+    for agent in log.agents() {
+        let addr = map(agent.addr());
+        let master = agent.master_agent().map(map);
+        match merged.agents.binary_search_by_key(&addr, Agent::addr) {
+            Ok(index) => {
+                let existing = &mut merged.agents[index];
+                if let Some((first, last)) = aware_times(agent, offset) {
+                    match aware_times(existing, 0) {
+                        Some((existing_first, existing_last)) => {
+                            existing.set_first_aware(existing_first.min(first));
+                            existing.set_last_aware(existing_last.max(last));
+                        }
+                        None => {
+                            existing.set_first_aware(first);
+                            existing.set_last_aware(last);
+                        }
+                    }
+                }
+                if existing.master_agent().is_none() {
+                    existing.set_master_agent(master);
+                }
+            }
+            Err(index) => {
+                let mut agent = agent.clone();
+                agent.set_addr(addr);
+                agent.set_master_agent(master);
+                if let Some((first, last)) = aware_times(&agent, offset) {
+                    agent.set_first_aware(first);
+                    agent.set_last_aware(last);
+                }
+                merged.agents.insert(index, agent);
+            }
+        }
+    }
+}
+
+/// Adds the events of the log that are not yet in the merged log.
+///
+/// An event is a duplicate if the merged log contains the same event at roughly the same time.
+/// As agents of the same kind cannot always be told apart, an event of an agent of the same kind
+/// is also considered a duplicate, if there is no exact match.
+///
+/// Events that refer to an `unmatched` agent (which was newly added to the merged log) are never
+/// duplicates, as the merged log cannot contain them yet. Otherwise, the events of identical adds
+/// that happen at the same time would be dropped.
+fn merge_events(
+    merged: &mut Log,
+    log: &Log,
+    offset: i64,
+    mapping: &HashMap<u64, u64>,
+    unmatched: &HashSet<u64>,
+) {
+    let mut existing: HashMap<_, Vec<(u64, usize)>> = HashMap::new();
+    for (index, event) in merged.events.iter().enumerate() {
+        existing
+            .entry(mem::discriminant(event.kind()))
+            .or_default()
+            .push((event.time(), index));
+    }
+    for entries in existing.values_mut() {
+        entries.sort_unstable();
+    }
+
+    // Maps every agent to the first agent of the same kind.
+    let mut representatives = HashMap::new();
+    let canonical = merged
+        .agents
+        .iter()
+        .map(|a| {
+            let representative = *representatives.entry(identity(a)).or_insert(a.addr());
+            (a.addr(), representative)
+        })
+        .collect::<HashMap<_, _>>();
+    let canonicalize = |kind: &EventKind| {
+        let mut kind = kind.clone();
+        remap_agents(&mut kind, |addr| {
+            canonical.get(&addr).copied().unwrap_or(addr)
+        });
+        kind
+    };
+    let mut canonical_kinds = vec![None; merged.events.len()];
+
+    let mut used = vec![false; merged.events.len()];
+    let mut added = Vec::new();
+    for event in log.events() {
+        if event.kind().category() == EventCategory::Metadata
+            && !matches!(event.kind(), EventKind::PointOfView { .. })
+        {
+            continue;
+        }
+        let mut kind = event.kind().clone();
+        let mut is_new = false;
+        remap_agents(&mut kind, |addr| {
+            is_new |= unmatched.contains(&addr);
+            mapping.get(&addr).copied().unwrap_or(addr)
+        });
+        let time = shift(event.time(), offset);
+
+        let candidates = existing
+            .get(&mem::discriminant(&kind))
+            .filter(|_| !is_new)
+            .map(|entries| window(entries, time, MATCH_WINDOW))
+            .unwrap_or_default();
+        let duplicate = closest(candidates, time, |index| {
+            !used[index] && merged.events[index].kind() == &kind
+        })
+        .or_else(|| {
+            let canonical_kind = canonicalize(&kind);
+            closest(candidates, time, |index| {
+                !used[index]
+                    && canonical_kinds[index]
+                        .get_or_insert_with(|| canonicalize(merged.events[index].kind()))
+                        == &canonical_kind
+            })
+        });
+        if let Some(index) = duplicate {
+            used[index] = true;
+            continue;
+        }
+
+        let mut event = event.clone();
+        event.set_time(time);
+        event.set_kind(kind);
+        added.push(event);
+    }
+
+    insert_events(&mut merged.events, added);
+}
+
+/// Inserts the added events into the events of the merged log, without reordering the latter.
+///
+/// Every added event is placed before the first (timed) event of the merged log that comes after
+/// it.
+fn insert_events(events: &mut Vec<Event>, mut added: Vec<Event>) {
+    if added.is_empty() {
+        return;
+    }
+    added.sort_by_key(Event::time);
+    let mut added = added.into_iter().peekable();
+    let existing = mem::take(events);
+    events.reserve(existing.len() + added.len());
+    for event in existing {
+        if event.kind().is_timed() {
+            while let Some(next) = added.next_if(|a| a.time() < event.time()) {
+                events.push(next);
+            }
+        }
+        events.push(event);
+    }
+    events.extend(added);
+}
+
+/// Returns the index of the entry that is closest to the given time and matches the predicate.
+fn closest(
+    entries: &[(u64, usize)],
+    time: u64,
+    mut predicate: impl FnMut(usize) -> bool,
+) -> Option<usize> {
+    entries
+        .iter()
+        .filter(|&&(_, index)| predicate(index))
+        .min_by_key(|&&(t, _)| t.abs_diff(time))
+        .map(|&(_, index)| index)
+}
+
+/// Replaces all agent addresses in the event.
+fn remap_agents(kind: &mut EventKind, mut map: impl FnMut(u64) -> u64) {
+    use EventKind::*;
+    match kind {
+        EnterCombat { agent_addr, .. }
+        | ExitCombat { agent_addr }
+        | ChangeUp { agent_addr }
+        | ChangeDown { agent_addr }
+        | ChangeDead { agent_addr }
+        | Spawn { agent_addr }
+        | Despawn { agent_addr }
+        | HealthUpdate { agent_addr, .. }
+        | WeaponSwap { agent_addr, .. }
+        | MaxHealthUpdate { agent_addr, .. }
+        | PointOfView { agent_addr }
+        | StackActive { agent_addr, .. }
+        | StackReset { agent_addr, .. }
+        | Position { agent_addr, .. }
+        | Velocity { agent_addr, .. }
+        | Facing { agent_addr, .. }
+        | TeamChange { agent_addr, .. }
+        | Targetable { agent_addr, .. }
+        | Tag { agent_addr, .. }
+        | BreakbarState { agent_addr, .. }
+        | BreakbarPercent { agent_addr, .. }
+        | BarrierUpdate { agent_addr, .. }
+        | Last90BeforeDown { agent_addr, .. }
+        | LogNpcUpdate { agent_addr, .. }
+        | Glider { agent_addr, .. }
+        | StunBreak { agent_addr, .. } => *agent_addr = map(*agent_addr),
+
+        SkillUse {
+            source_agent_addr, ..
+        }
+        | Guild {
+            source_agent_addr, ..
+        } => *source_agent_addr = map(*source_agent_addr),
+
+        ConditionTick {
+            source_agent_addr,
+            destination_agent_addr,
+            ..
+        }
+        | InvulnTick {
+            source_agent_addr,
+            destination_agent_addr,
+            ..
+        }
+        | Physical {
+            source_agent_addr,
+            destination_agent_addr,
+            ..
+        }
+        | BuffApplication {
+            source_agent_addr,
+            destination_agent_addr,
+            ..
+        }
+        | BuffRemove {
+            source_agent_addr,
+            destination_agent_addr,
+            ..
+        }
+        | BuffInitial {
+            source_agent_addr,
+            destination_agent_addr,
+            ..
+        } => {
+            *source_agent_addr = map(*source_agent_addr);
+            *destination_agent_addr = map(*destination_agent_addr);
+        }
+
+        AttackTarget {
+            agent_addr,
+            parent_agent_addr,
+            ..
+        } => {
+            *agent_addr = map(*agent_addr);
+            *parent_agent_addr = map(*parent_agent_addr);
+        }
+
+        Effect {
+            owner_agent_addr,
+            location,
+            ..
+        } => {
+            *owner_agent_addr = map(*owner_agent_addr);
+            if let EffectLocation::Agent(addr) = location {
+                *addr = map(*addr);
+            }
+        }
+        EffectEnd {
+            owner_agent_addr, ..
+        } => *owner_agent_addr = map(*owner_agent_addr),
+
+        LogStart { .. }
+        | LogEnd { .. }
+        | Language { .. }
+        | Build { .. }
+        | ShardId { .. }
+        | Reward { .. }
+        | MapId { .. }
+        | Error { .. }
+        | StatReset { .. }
+        | InstanceStart { .. }
+        | TickRate { .. }
+        | IdToGuid { .. }
+        | FractalScale { .. }
+        | Ruleset { .. }
+        | SquadMarker { .. }
+        | ArcBuild { .. }
+        | BuffInfo { .. }
+        | BuffFormula { .. }
+        | SkillInfo { .. }
+        | SkillTiming { .. } => (),
+    }
+}
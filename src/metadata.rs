@@ -0,0 +1,138 @@
+//! Private module to contain the log metadata.
+
+use getset::CopyGetters;
+
+use super::{raw, Event, EventKind};
+
+/// General information about a log.
+///
+/// This collects the information from the evtc header, as well as the information that arcdps
+/// stores in special [`EventKind`][EventKind]s, such as the game build or the map id.
+///
+/// The metadata is computed once during [`process`][super::process], and can be accessed through
+/// [`Log::metadata`][super::Log::metadata]. As the events are not guaranteed to be present in
+/// every log, most of the values are optional.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, CopyGetters)]
+pub struct LogMetadata {
+    arcdps_build: String,
+
+    /// The revision of the evtc file.
+    #[get_copy = "pub"]
+    revision: u8,
+
+    /// The game's build id, taken from the [`Build`][EventKind::Build] event.
+    #[get_copy = "pub"]
+    gw2_build: Option<u64>,
+
+    /// The text language, taken from the [`Language`][EventKind::Language] event.
+    #[get_copy = "pub"]
+    language: Option<raw::Language>,
+
+    /// The id of the map, taken from the [`MapId`][EventKind::MapId] event.
+    #[get_copy = "pub"]
+    map_id: Option<u64>,
+
+    /// The shard id of the server, taken from the [`ShardId`][EventKind::ShardId] event.
+    #[get_copy = "pub"]
+    shard_id: Option<u64>,
+
+    /// The address of the agent that recorded the log, taken from the
+    /// [`PointOfView`][EventKind::PointOfView] event.
+    #[get_copy = "pub"]
+    pov_agent_addr: Option<u64>,
+
+    /// The server timestamp of when the log was started.
+    #[get_copy = "pub"]
+    server_start_timestamp: Option<u32>,
+
+    /// The local timestamp of when the log was started.
+    #[get_copy = "pub"]
+    local_start_timestamp: Option<u32>,
+
+    /// The server timestamp of when the log was ended.
+    #[get_copy = "pub"]
+    server_end_timestamp: Option<u32>,
+
+    /// The local timestamp of when the log was ended.
+    #[get_copy = "pub"]
+    local_end_timestamp: Option<u32>,
+
+    /// The time (on the clock of the events) of the [`LogStart`][EventKind::LogStart] event.
+    #[get_copy = "pub"]
+    log_start_time: Option<u64>,
+
+    /// The time (on the clock of the events) of the [`LogEnd`][EventKind::LogEnd] event.
+    #[get_copy = "pub"]
+    log_end_time: Option<u64>,
+}
+
+impl LogMetadata {
+    /// Create the metadata from the given header.
+    ///
+    /// The values from the events are filled in by [`update`][LogMetadata::update].
+    pub(crate) fn new(header: &raw::parser::Header) -> LogMetadata {
+        LogMetadata {
+            arcdps_build: header.arcdps_build.clone(),
+            revision: header.revision,
+            gw2_build: None,
+            language: None,
+            map_id: None,
+            shard_id: None,
+            pov_agent_addr: None,
+            server_start_timestamp: None,
+            local_start_timestamp: None,
+            server_end_timestamp: None,
+            local_end_timestamp: None,
+            log_start_time: None,
+            log_end_time: None,
+        }
+    }
+
+    /// Collect the information from the given event.
+    ///
+    /// If an event occurs multiple times, the first occurence is used.
+    pub(crate) fn update(&mut self, event: &Event) {
+        match *event.kind() {
+            EventKind::Build { build } => {
+                self.gw2_build.get_or_insert(build);
+            }
+            EventKind::Language { language } => {
+                self.language.get_or_insert(language);
+            }
+            EventKind::MapId { map_id } => {
+                self.map_id.get_or_insert(map_id);
+            }
+            EventKind::ShardId { shard_id } => {
+                self.shard_id.get_or_insert(shard_id);
+            }
+            EventKind::PointOfView { agent_addr } => {
+                self.pov_agent_addr.get_or_insert(agent_addr);
+            }
+            EventKind::LogStart {
+                server_timestamp,
+                local_timestamp,
+            } => {
+                self.server_start_timestamp.get_or_insert(server_timestamp);
+                self.local_start_timestamp.get_or_insert(local_timestamp);
+                self.log_start_time.get_or_insert(event.time());
+            }
+            EventKind::LogEnd {
+                server_timestamp,
+                local_timestamp,
+            } => {
+                self.server_end_timestamp.get_or_insert(server_timestamp);
+                self.local_end_timestamp.get_or_insert(local_timestamp);
+                self.log_end_time.get_or_insert(event.time());
+            }
+            _ => (),
+        }
+    }
+
+    /// The arcdps build, as it is stored in the header.
+    ///
+    /// This is usually a date in the form `yyyymmdd`.
+    pub fn arcdps_build(&self) -> &str {
+        &self.arcdps_build
+    }
+}
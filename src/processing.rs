@@ -1,13 +1,176 @@
 //! Private module to contain the processing functions.
 
 use std::{
+    collections::HashMap,
     convert::TryFrom,
     fs::File,
-    io::{BufReader, Read, Seek},
+    io::{BufReader, Read, Seek, SeekFrom},
     path::Path,
 };
 
-use super::{raw, Agent, Event, EvtcError, Log};
+use super::{
+    agent::find_instance_period,
+    event::{EventCategory, FromRawEventError},
+    raw::{self, progress::Tracker},
+    Agent, BuffDatabase, Diagnostics, DroppedEvent, Event, EvtcError, InstancePeriod, Log,
+    LogMetadata, Skill, SkillDatabase,
+};
+
+/// Options that control the processing of a log.
+///
+/// By default, everything is processed: all events are kept, unknown professions are an error,
+/// and the aware times and masters of the agents are resolved. The options can be changed using
+/// the builder methods:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use evtclib::{Compression, EventCategory, ProcessingOptions};
+/// let options = ProcessingOptions::new()
+///     .keep_only(&[EventCategory::State, EventCategory::Reward])
+///     .resolve_agents(false);
+/// let log = evtclib::process_file_with_options("logfile.zevtc", Compression::Zip, &options)?;
+/// # Ok(()) }
+/// ```
+///
+/// Note that the [metadata][Log::metadata] and the [buff][Log::buff_database] and
+/// [skill][Log::skill_database] databases are always collected from all events, regardless of the
+/// kept categories. Other functions (most notably the [analyzers][Log::analyzer]) only work with
+/// the kept events though, so they might give wrong results if you drop events that they rely on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessingOptions {
+    kept_categories: Vec<EventCategory>,
+    unknown_professions_fatal: bool,
+    retain_raw_events: bool,
+    resolve_agents: bool,
+    parse_limits: raw::ParseLimits,
+    monitor: raw::ProgressMonitor,
+    #[cfg(feature = "rayon")]
+    parallel: bool,
+}
+
+impl Default for ProcessingOptions {
+    fn default() -> Self {
+        ProcessingOptions {
+            kept_categories: EventCategory::ALL.to_vec(),
+            unknown_professions_fatal: true,
+            retain_raw_events: false,
+            resolve_agents: true,
+            parse_limits: raw::ParseLimits::default(),
+            monitor: raw::ProgressMonitor::default(),
+            #[cfg(feature = "rayon")]
+            parallel: false,
+        }
+    }
+}
+
+impl ProcessingOptions {
+    /// Create the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only keep events of the given categories.
+    pub fn keep_only(mut self, categories: &[EventCategory]) -> Self {
+        self.kept_categories = categories.to_vec();
+        self
+    }
+
+    /// Keep events of the given category.
+    pub fn include(mut self, category: EventCategory) -> Self {
+        if !self.kept_categories.contains(&category) {
+            self.kept_categories.push(category);
+        }
+        self
+    }
+
+    /// Drop events of the given category.
+    pub fn exclude(mut self, category: EventCategory) -> Self {
+        self.kept_categories.retain(|c| *c != category);
+        self
+    }
+
+    /// Set whether agents with an unknown profession or elite specialization are an error.
+    ///
+    /// If this is set to `false`, such agents are kept with
+    /// [`Profession::Unknown`][super::Profession::Unknown] or
+    /// [`EliteSpec::Unknown`][super::EliteSpec::Unknown] instead of returning
+    /// [`EvtcError::InvalidProfession`][EvtcError::InvalidProfession] or
+    /// [`EvtcError::InvalidEliteSpec`][EvtcError::InvalidEliteSpec].
+    ///
+    /// See also [`Agent::from_raw_lenient`][Agent::from_raw_lenient].
+    pub fn unknown_professions_fatal(mut self, fatal: bool) -> Self {
+        self.unknown_professions_fatal = fatal;
+        self
+    }
+
+    /// Set whether the raw events should be kept in the [`Log`][Log].
+    ///
+    /// The raw events can then be accessed through [`Log::raw_events`][Log::raw_events].
+    pub fn retain_raw_events(mut self, retain: bool) -> Self {
+        self.retain_raw_events = retain;
+        self
+    }
+
+    /// Set whether the aware times and the master agents should be resolved.
+    ///
+    /// If this is set to `false`, [`Agent::first_aware`][Agent::first_aware],
+    /// [`Agent::last_aware`][Agent::last_aware], [`Agent::instance_id`][Agent::instance_id] and
+    /// [`Agent::master_agent`][Agent::master_agent] keep their default values.
+    pub fn resolve_agents(mut self, resolve: bool) -> Self {
+        self.resolve_agents = resolve;
+        self
+    }
+
+    /// Set the limits that are used when parsing the log.
+    ///
+    /// This only has an effect on the functions that do the parsing themselves, such as
+    /// [`process_stream_with_options`][process_stream_with_options] and
+    /// [`process_file_with_options`][process_file_with_options]. See
+    /// [`raw::ParseLimits`][raw::ParseLimits] for more information.
+    pub fn parse_limits(mut self, limits: raw::ParseLimits) -> Self {
+        self.parse_limits = limits;
+        self
+    }
+
+    /// Set the monitor that receives the progress, and that can be used to cancel the parsing and
+    /// processing.
+    ///
+    /// The functions that do the parsing themselves (such as
+    /// [`process_file_with_options`][process_file_with_options]) report all stages, while
+    /// [`process_with_options`][process_with_options] only reports the
+    /// [resolving][raw::Stage::Resolving] and [processing][raw::Stage::Processing]. If the processing is cancelled,
+    /// [`raw::ParseError::Cancelled`][raw::ParseError::Cancelled] is returned (wrapped in
+    /// [`EvtcError::ParseError`][EvtcError::ParseError]). See
+    /// [`raw::ProgressMonitor`][raw::ProgressMonitor] for more information.
+    pub fn monitor(mut self, monitor: raw::ProgressMonitor) -> Self {
+        self.monitor = monitor;
+        self
+    }
+
+    /// Set whether the events should be processed in parallel.
+    ///
+    /// If this is set, the events are converted (and the agents are resolved) in chunks on the
+    /// [rayon](https://docs.rs/rayon) thread pool. The resulting [`Log`][Log] is the same. This
+    /// pays off for large logs (such as WvW logs), for small logs the overhead of distributing the
+    /// work can outweigh the gains.
+    ///
+    /// Requires the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Check whether events of the given category are kept.
+    pub fn keeps(&self, category: EventCategory) -> bool {
+        self.kept_categories.contains(&category)
+    }
+
+    /// Check whether events of all categories are kept.
+    fn keeps_all(&self) -> bool {
+        EventCategory::ALL.iter().all(|c| self.keeps(*c))
+    }
+}
 
 /// Main function to turn a low-level [`Evtc`][raw::Evtc] to a high-level [`Log`][Log].
 ///
@@ -16,42 +179,180 @@ use super::{raw, Agent, Event, EvtcError, Log};
 ///
 /// * Setting the correct aware times for the agents
 /// * Setting the master agents for each agent
-/// * Converting all events
+/// * Converting the skill table
+/// * Converting all events (events that cannot be converted are kept as
+///   [`DroppedEvent`][DroppedEvent]s)
+/// * Collecting the log metadata
+/// * Collecting the buff and skill information
 ///
 /// Note that the structures are quite different, so this function does not consume the given
 /// [`Evtc`][raw::Evtc].
+///
+/// This is the same as [`process_with_options`][process_with_options] with the default options.
 pub fn process(data: &raw::Evtc) -> Result<Log, EvtcError> {
+    process_with_options(data, &ProcessingOptions::default())
+}
+
+/// Turn a low-level [`Evtc`][raw::Evtc] to a high-level [`Log`][Log], using the given options.
+///
+/// See [`process`][process] and [`ProcessingOptions`][ProcessingOptions] for more information.
+pub fn process_with_options(
+    data: &raw::Evtc,
+    options: &ProcessingOptions,
+) -> Result<Log, EvtcError> {
     // Prepare "augmented" agents
-    let mut agents = setup_agents(data)?;
+    let mut agents = setup_agents(data, options)?;
     // We sort the agents so we can do a binary search later in get_agent_by_addr. The order is not
     // really defined or important anyway, so we can just choose whatever works best here.
     agents.sort_by_key(Agent::addr);
-    // Do the first aware/last aware field
-    set_agent_awares(data, &mut agents)?;
 
-    // Set the master addr field
-    set_agent_masters(data, &mut agents)?;
+    let instance_periods = if options.resolve_agents {
+        let mut tracker = options.monitor.start(raw::Stage::Resolving);
+        tracker.set_events_decoded(data.events.len() as u64);
+        tracker.check()?;
+        let periods = resolve_agents(data, &mut agents, options, &mut tracker)?;
+        tracker.check()?;
+        periods
+    } else {
+        Vec::new()
+    };
 
-    let events = data
-        .events
-        .iter()
-        .filter_map(|e| Event::try_from(e).ok())
-        .collect();
+    // Same reasoning as for the agents, we sort them to do a binary search in skill_by_id.
+    let mut skills = data.skills.iter().map(Skill::from).collect::<Vec<_>>();
+    skills.sort_by_key(Skill::id);
+    skills.dedup_by_key(|s| s.id());
+
+    let mut tracker = options.monitor.start(raw::Stage::Processing);
+    tracker.set_events_decoded(data.events.len() as u64);
+    tracker.check()?;
+
+    // If events are filtered, we cannot know how many will be kept, and reserving space for all of
+    // them would defeat the purpose of filtering.
+    let capacity = if options.keeps_all() {
+        data.events.len()
+    } else {
+        0
+    };
+    let mut collector = EventCollector::new(&data.header, capacity);
+    #[cfg(feature = "rayon")]
+    let converted = if options.parallel {
+        Some(parallel::convert_events(&data.events))
+    } else {
+        None
+    };
+    #[cfg(not(feature = "rayon"))]
+    let converted: Option<Vec<_>> = None;
+    match converted {
+        Some(converted) => {
+            for (raw_event, result) in data.events.iter().zip(converted) {
+                collector.add(raw_event, result, options);
+                tracker.event_processed()?;
+            }
+        }
+        None => {
+            for raw_event in &data.events {
+                collector.add(raw_event, Event::try_from(raw_event), options);
+                tracker.event_processed()?;
+            }
+        }
+    }
+    tracker.check()?;
+    let EventCollector {
+        events,
+        dropped_events,
+        metadata,
+        buff_database,
+        skill_database,
+    } = collector;
+
+    let raw_events = if options.retain_raw_events {
+        Some(data.events.clone())
+    } else {
+        None
+    };
 
     Ok(Log {
         agents,
+        skills,
         events,
+        dropped_events,
+        raw_events,
+        raw_event_count: data.events.len(),
+        unknown_statechanges: data.unknown_statechanges,
+        instance_periods,
         boss_id: data.header.combat_id,
+        metadata,
+        buff_database,
+        skill_database,
     })
 }
 
 /// Indicates the given compression method for the file.
+///
+/// The gzip, zstd and xz variants are always present, but they can only be parsed if the
+/// corresponding cargo feature (`gzip`, `zstd` or `xz`) is enabled. Otherwise,
+/// [`EvtcError::UnsupportedCompression`][EvtcError::UnsupportedCompression] is returned.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Compression {
     /// No compression was used.
     None,
     /// The file is wrapped in a zip archive.
     Zip,
+    /// The file is compressed as a gzip stream.
+    Gzip,
+    /// The file is compressed as a zstd stream.
+    Zstd,
+    /// The file is compressed as a xz stream.
+    Xz,
+    /// The compression should be detected from the content of the file.
+    ///
+    /// See [`Compression::detect`][Compression::detect] for more information.
+    Auto,
+}
+
+impl Compression {
+    /// Detect the compression of the given input by looking at its first bytes.
+    ///
+    /// This recognizes uncompressed evtc files (starting with `EVTC`), zip archives (starting
+    /// with `PK`), as well as gzip, zstd and xz streams (by their respective magic numbers). The
+    /// input is rewound to the position it had before, so it can be passed to the parsing
+    /// functions afterwards.
+    ///
+    /// Note that the detection does not depend on the enabled features, so this function might
+    /// return a compression that cannot be parsed.
+    ///
+    /// If the format cannot be recognized, [`EvtcError::UnknownFormat`][EvtcError::UnknownFormat]
+    /// is returned.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::fs::File;
+    /// use evtclib::Compression;
+    /// let mut file = File::open("logfile.zevtc")?;
+    /// assert_eq!(Compression::detect(&mut file)?, Compression::Zip);
+    /// # Ok(()) }
+    /// ```
+    pub fn detect<R: Read + Seek>(input: &mut R) -> Result<Compression, EvtcError> {
+        let start = input.stream_position().map_err(raw::ParseError::from)?;
+        let mut magic = Vec::with_capacity(6);
+        input
+            .take(6)
+            .read_to_end(&mut magic)
+            .map_err(raw::ParseError::from)?;
+        input
+            .seek(SeekFrom::Start(start))
+            .map_err(raw::ParseError::from)?;
+
+        match &magic[..] {
+            [b'E', b'V', b'T', b'C', ..] => Ok(Compression::None),
+            [b'P', b'K', ..] => Ok(Compression::Zip),
+            [0x1f, 0x8b, ..] => Ok(Compression::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Ok(Compression::Zstd),
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00] => Ok(Compression::Xz),
+            _ => Err(EvtcError::UnknownFormat),
+        }
+    }
 }
 
 /// Convenience function to process a given stream directly.
@@ -74,11 +375,41 @@ pub fn process_stream<R: Read + Seek>(
     input: R,
     compression: Compression,
 ) -> Result<Log, EvtcError> {
+    process_stream_with_options(input, compression, &ProcessingOptions::default())
+}
+
+/// Convenience function to process a given stream directly, using the given options.
+///
+/// See [`process_stream`][process_stream] and [`ProcessingOptions`][ProcessingOptions] for more
+/// information.
+pub fn process_stream_with_options<R: Read + Seek>(
+    mut input: R,
+    compression: Compression,
+    options: &ProcessingOptions,
+) -> Result<Log, EvtcError> {
+    let limits = &options.parse_limits;
+    let monitor = &options
+        .monitor
+        .clone()
+        .or_total_bytes(raw::stream_len(&mut input).map_err(raw::ParseError::from)?);
     let evtc = match compression {
-        Compression::None => raw::parse_file(input)?,
-        Compression::Zip => raw::parse_zip(input)?,
+        Compression::None => raw::parse_file_monitored(input, limits, monitor)?,
+        Compression::Zip => raw::parse_zip_monitored(input, limits, monitor)?,
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => raw::parse_gzip_monitored(input, limits, monitor)?,
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => raw::parse_zstd_monitored(input, limits, monitor)?,
+        #[cfg(feature = "xz")]
+        Compression::Xz => raw::parse_xz_monitored(input, limits, monitor)?,
+        Compression::Auto => {
+            let detected = Compression::detect(&mut input)?;
+            return process_stream_with_options(input, detected, options);
+        }
+        // Only reachable if some of the compression features are disabled.
+        #[allow(unreachable_patterns)]
+        other => return Err(EvtcError::UnsupportedCompression(other)),
     };
-    process(&evtc)
+    process_with_options(&evtc, options)
 }
 
 /// Convenience function to process a given file directly.
@@ -97,13 +428,462 @@ pub fn process_stream<R: Read + Seek>(
 /// # Ok(()) }
 /// ```
 pub fn process_file<P: AsRef<Path>>(path: P, compression: Compression) -> Result<Log, EvtcError> {
+    process_file_with_options(path, compression, &ProcessingOptions::default())
+}
+
+/// Convenience function to process a given file directly, using the given options.
+///
+/// See [`process_file`][process_file] and [`ProcessingOptions`][ProcessingOptions] for more
+/// information.
+pub fn process_file_with_options<P: AsRef<Path>>(
+    path: P,
+    compression: Compression,
+    options: &ProcessingOptions,
+) -> Result<Log, EvtcError> {
     let file = File::open(path).map_err(Into::<raw::ParseError>::into)?;
     let buffered = BufReader::new(file);
-    process_stream(buffered, compression)
+    process_stream_with_options(buffered, compression, options)
+}
+
+/// Convenience function to process a single entry of a multi-log archive.
+///
+/// This is a shorthand for [`raw::Archive::parse_with_limits`][raw::Archive::parse_with_limits]
+/// (with the [limits of the options][ProcessingOptions::parse_limits]) followed by
+/// [`process_with_options`][process_with_options]. If the processing fails, the error is wrapped
+/// in [`EvtcError::ArchiveEntry`][EvtcError::ArchiveEntry], so that it contains the name of the
+/// failing entry (just like the parsing errors).
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use evtclib::{raw::Archive, ProcessingOptions};
+/// let mut archive = Archive::new(BufReader::new(File::open("bundle.zip")?))?;
+/// for index in 0..archive.len() {
+///     match evtclib::process_archive_entry(&mut archive, index, &ProcessingOptions::new()) {
+///         Ok(log) => println!("{:?}", log.encounter()),
+///         Err(e) => eprintln!("{}", e),
+///     }
+/// }
+/// # Ok(()) }
+/// ```
+pub fn process_archive_entry<R: Read + Seek>(
+    archive: &mut raw::Archive<R>,
+    index: usize,
+    options: &ProcessingOptions,
+) -> Result<Log, EvtcError> {
+    let evtc = archive.parse_with_limits(index, &options.parse_limits)?;
+    process_with_options(&evtc, options).map_err(|e| EvtcError::ArchiveEntry {
+        name: archive.name(index).unwrap_or_default().to_owned(),
+        source: Box::new(e),
+    })
+}
+
+/// Convenience function to process a given stream asynchronously.
+///
+/// This is the asynchronous counterpart to [`process_stream`][process_stream], and requires the
+/// `async` feature. Uncompressed logs are parsed while they are being read, all other formats are
+/// read into memory first (zip archives have to be seekable, and the decompressors are
+/// synchronous).
+///
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use evtclib::Compression;
+/// let input = tokio::io::BufReader::new(tokio::fs::File::open("logfile.zevtc").await?);
+/// let log = evtclib::process_stream_async(input, Compression::Zip).await?;
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "async")]
+pub async fn process_stream_async<R: tokio::io::AsyncRead + Unpin>(
+    input: R,
+    compression: Compression,
+) -> Result<Log, EvtcError> {
+    process_stream_async_with_options(input, compression, &ProcessingOptions::default()).await
+}
+
+/// Convenience function to process a given stream asynchronously, using the given options.
+///
+/// See [`process_stream_async`][process_stream_async] and
+/// [`ProcessingOptions`][ProcessingOptions] for more information.
+#[cfg(feature = "async")]
+pub async fn process_stream_async_with_options<R: tokio::io::AsyncRead + Unpin>(
+    mut input: R,
+    compression: Compression,
+    options: &ProcessingOptions,
+) -> Result<Log, EvtcError> {
+    use tokio::io::AsyncReadExt;
+
+    if compression == Compression::None {
+        let evtc = raw::parse_file_async_with_limits(input, &options.parse_limits).await?;
+        return process_with_options(&evtc, options);
+    }
+    let mut data = Vec::new();
+    input
+        .read_to_end(&mut data)
+        .await
+        .map_err(Into::<raw::ParseError>::into)?;
+    process_stream_with_options(std::io::Cursor::new(data), compression, options)
+}
+
+/// An iterator over the high-level [`Event`][Event]s of a stream.
+///
+/// This is the streaming counterpart to [`process`][process]: instead of collecting all events
+/// into a [`Log`][Log], the events are read, parsed and converted one at a time. This means that
+/// only a single event has to be kept in memory, which is useful for very long logs (e.g. WvW).
+///
+/// Events that cannot be converted to a high-level [`Event`][Event] are not yielded. Just like
+/// in [`process`][process], they are kept as [`DroppedEvent`][DroppedEvent]s, which can be
+/// accessed through [`dropped_events`][EventStream::dropped_events] and are counted in the
+/// [`diagnostics`][EventStream::diagnostics]. Parsing errors are returned and end the iteration.
+///
+/// Note that since there is no [`Log`][Log], the agents are not augmented with aware times or
+/// master agents. If you need those, you have to use [`process`][process]. For the same reason,
+/// the buff and skill information events are yielded as well, instead of being collected into
+/// the databases.
+///
+/// The main way to obtain an `EventStream` is [`stream_events`][stream_events].
+pub struct EventStream<R> {
+    reader: raw::EventReader<R>,
+    raw_event_count: usize,
+    dropped_events: Vec<DroppedEvent>,
+}
+
+impl<R: Read> EventStream<R> {
+    /// Create a new stream that converts the events read by the given reader.
+    pub fn new(reader: raw::EventReader<R>) -> Self {
+        EventStream {
+            reader,
+            raw_event_count: 0,
+            dropped_events: Vec::new(),
+        }
+    }
+
+    /// Return the events that could not be converted so far.
+    pub fn dropped_events(&self) -> &[DroppedEvent] {
+        &self.dropped_events
+    }
+
+    /// Return a report about the conversion of the events that have been read so far.
+    ///
+    /// After the stream has been exhausted, this is the same report that
+    /// [`Log::diagnostics`][Log::diagnostics] gives for the processed log.
+    pub fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::new(
+            self.raw_event_count,
+            self.reader.unknown_statechanges(),
+            &self.dropped_events,
+        )
+    }
+}
+
+impl<R: Read> Iterator for EventStream<R> {
+    type Item = Result<Event, EvtcError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for raw_event in &mut self.reader {
+            match raw_event {
+                Ok(raw_event) => {
+                    self.raw_event_count += 1;
+                    match Event::try_from(&raw_event) {
+                        Ok(event) => return Some(Ok(event)),
+                        Err(error) => self
+                            .dropped_events
+                            .push(DroppedEvent::new(raw_event, error)),
+                    }
+                }
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+        None
+    }
+}
+
+impl<R: Read> std::iter::FusedIterator for EventStream<R> {}
+
+/// Read the header, agents and skills from the given stream and return an iterator over the
+/// remaining events.
+///
+/// This is a shorthand for using [`raw::parse_partial_file`][raw::parser::parse_partial_file]
+/// followed by an [`EventStream`][EventStream]. The events are lazily read from the input, so the
+/// memory usage stays low, even for very long logs.
+///
+/// The stream should be uncompressed. For zip compressed logs, you can open the
+/// [`ZipArchive`][zip::ZipArchive] yourself and pass the contained file to this function.
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// let input = BufReader::new(File::open("logfile.evtc")?);
+/// let (partial, events) = evtclib::stream_events(input)?;
+/// println!("Log has {} agents", partial.agents.len());
+/// for event in events {
+///     let event = event?;
+///     println!("{:?}", event.kind());
+/// }
+/// # Ok(()) }
+/// ```
+pub fn stream_events<R: Read>(
+    mut input: R,
+) -> Result<(raw::parser::PartialEvtc, EventStream<R>), EvtcError> {
+    let partial = raw::parser::parse_partial_file(&mut input)?;
+    let reader = raw::EventReader::new(input, partial.header.revision)?;
+    Ok((partial, EventStream::new(reader)))
 }
 
-fn setup_agents(data: &raw::Evtc) -> Result<Vec<Agent>, EvtcError> {
-    data.agents.iter().map(Agent::try_from).collect()
+/// Builds a [`Log`][Log] from events that are added one at a time.
+///
+/// [`process`][process] needs the complete [`Evtc`][raw::Evtc] up front. The `LogBuilder` instead
+/// starts with the header, agents and skills, and is then fed the events as they come in (for
+/// example from a realtime combat API). The agents, events, metadata and databases are kept up to
+/// date with every added event, and a [`snapshot`][LogBuilder::snapshot] of the current state can
+/// be taken at any moment.
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # fn receive_events() -> Vec<evtclib::raw::CbtEvent> { vec![] }
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use evtclib::{raw, LogBuilder};
+/// let input = BufReader::new(File::open("logfile.evtc")?);
+/// let partial = raw::parser::parse_partial_file(input)?;
+/// let mut builder = LogBuilder::new(&partial.header, &partial.agents, &partial.skills)?;
+/// for event in receive_events() {
+///     builder.add_event(event);
+/// }
+/// // The builder can still receive more events after taking a snapshot.
+/// let log = builder.snapshot();
+/// println!("{} players, {} events", log.players().count(), log.events().len());
+/// # Ok(()) }
+/// ```
+///
+/// Note that a snapshot clones the whole log, so it should not be taken after every event.
+///
+/// Since the events are only known up to the current one, the masters are resolved differently
+/// than in [`process`][process]: the master of an agent is the agent that has been seen with the
+/// given instance id most recently. For complete logs, this gives the same result in all but
+/// some rare edge cases (e.g. when an instance id is reused while the minion is alive).
+#[derive(Debug, Clone)]
+pub struct LogBuilder {
+    options: ProcessingOptions,
+    boss_id: u16,
+    agents: Vec<Agent>,
+    skills: Vec<Skill>,
+    collector: EventCollector,
+    periods: PeriodCollector,
+    raw_events: Option<Vec<raw::CbtEvent>>,
+    raw_event_count: usize,
+    unknown_statechanges: usize,
+}
+
+impl LogBuilder {
+    /// Create a new builder with the given header, agents and skills, using the default options.
+    pub fn new(
+        header: &raw::parser::Header,
+        agents: &[raw::Agent],
+        skills: &[raw::Skill],
+    ) -> Result<Self, EvtcError> {
+        Self::with_options(header, agents, skills, &ProcessingOptions::default())
+    }
+
+    /// Create a new builder with the given header, agents and skills, using the given options.
+    ///
+    /// The options have the same meaning as for [`process_with_options`][process_with_options],
+    /// except for the ones that only affect the parsing.
+    pub fn with_options(
+        header: &raw::parser::Header,
+        agents: &[raw::Agent],
+        skills: &[raw::Skill],
+        options: &ProcessingOptions,
+    ) -> Result<Self, EvtcError> {
+        let mut builder = LogBuilder {
+            options: options.clone(),
+            boss_id: header.combat_id,
+            agents: Vec::with_capacity(agents.len()),
+            skills: Vec::with_capacity(skills.len()),
+            collector: EventCollector::new(header, 0),
+            periods: PeriodCollector::default(),
+            raw_events: if options.retain_raw_events {
+                Some(Vec::new())
+            } else {
+                None
+            },
+            raw_event_count: 0,
+            unknown_statechanges: 0,
+        };
+        for agent in agents {
+            builder.add_agent(agent)?;
+        }
+        for skill in skills {
+            builder.add_skill(skill);
+        }
+        Ok(builder)
+    }
+
+    /// Add an agent that was not known before.
+    ///
+    /// If an agent with the same address is already known, the existing agent is kept.
+    pub fn add_agent(&mut self, raw_agent: &raw::Agent) -> Result<(), EvtcError> {
+        // The agents are kept sorted by their address, the same as in process.
+        if let Err(pos) = self
+            .agents
+            .binary_search_by_key(&raw_agent.addr, Agent::addr)
+        {
+            let agent = convert_agent(raw_agent, &self.options)?;
+            self.agents.insert(pos, agent);
+        }
+        Ok(())
+    }
+
+    /// Add a skill that was not known before.
+    ///
+    /// If a skill with the same id is already known, the existing skill is kept.
+    pub fn add_skill(&mut self, raw_skill: &raw::Skill) {
+        let skill = Skill::from(raw_skill);
+        if let Err(pos) = self.skills.binary_search_by_key(&skill.id(), Skill::id) {
+            self.skills.insert(pos, skill);
+        }
+    }
+
+    /// Add the next event.
+    ///
+    /// The events have to be added in the order in which they appear in the log. Events that
+    /// cannot be converted are kept as [`DroppedEvent`][DroppedEvent]s, just like in
+    /// [`process`][process].
+    pub fn add_event(&mut self, raw_event: raw::CbtEvent) {
+        if self.options.resolve_agents {
+            update_agent_aware(&mut self.agents, &mut self.periods, &raw_event);
+            let master = match raw_event.src_master_instid {
+                0 => None,
+                instance_id => self.periods.current_holder(instance_id),
+            };
+            if let Some(master_addr) = master {
+                if let Some(slave) = get_agent_by_addr(&mut self.agents, raw_event.src_agent) {
+                    slave.set_master_agent(Some(master_addr));
+                }
+            }
+        }
+        self.collector
+            .add(&raw_event, Event::try_from(&raw_event), &self.options);
+        self.raw_event_count += 1;
+        if let Some(ref mut raw_events) = self.raw_events {
+            raw_events.push(raw_event);
+        }
+    }
+
+    /// Record that the given number of events were skipped because their statechange is unknown.
+    ///
+    /// Such events cannot be added with [`add_event`][LogBuilder::add_event], as the parser does
+    /// not decode them. They are counted in the [diagnostics][Log::diagnostics] of the built log,
+    /// so pass [`Evtc::unknown_statechanges`][raw::Evtc::unknown_statechanges] here when building
+    /// a log from parsed data.
+    pub fn add_unknown_statechanges(&mut self, count: usize) {
+        self.unknown_statechanges += count;
+    }
+
+    /// Returns the agents, as they are known so far.
+    pub fn agents(&self) -> &[Agent] {
+        &self.agents
+    }
+
+    /// Returns the (kept) events that have been added so far.
+    pub fn events(&self) -> &[Event] {
+        &self.collector.events
+    }
+
+    /// Returns a [`Log`][Log] with the current state.
+    ///
+    /// This clones all of the data, use [`build`][LogBuilder::build] if you do not need the
+    /// builder anymore.
+    pub fn snapshot(&self) -> Log {
+        self.clone().build()
+    }
+
+    /// Turn the builder into a [`Log`][Log].
+    pub fn build(self) -> Log {
+        let EventCollector {
+            events,
+            dropped_events,
+            metadata,
+            buff_database,
+            skill_database,
+        } = self.collector;
+        Log {
+            agents: self.agents,
+            skills: self.skills,
+            events,
+            dropped_events,
+            raw_events: self.raw_events,
+            raw_event_count: self.raw_event_count,
+            unknown_statechanges: self.unknown_statechanges,
+            instance_periods: self.periods.finish(),
+            boss_id: self.boss_id,
+            metadata,
+            buff_database,
+            skill_database,
+        }
+    }
+}
+
+/// Collects the converted events, together with the information that is gathered from them.
+#[derive(Debug, Clone)]
+struct EventCollector {
+    events: Vec<Event>,
+    dropped_events: Vec<DroppedEvent>,
+    metadata: LogMetadata,
+    buff_database: BuffDatabase,
+    skill_database: SkillDatabase,
+}
+
+impl EventCollector {
+    fn new(header: &raw::parser::Header, capacity: usize) -> Self {
+        EventCollector {
+            events: Vec::with_capacity(capacity),
+            dropped_events: Vec::new(),
+            metadata: LogMetadata::new(header),
+            buff_database: BuffDatabase::default(),
+            skill_database: SkillDatabase::default(),
+        }
+    }
+
+    /// Add the result of a single conversion.
+    ///
+    /// This has to be called for the events in the order in which they appear in the log.
+    fn add(
+        &mut self,
+        raw_event: &raw::CbtEvent,
+        result: Result<Event, FromRawEventError>,
+        options: &ProcessingOptions,
+    ) {
+        match result {
+            Ok(event) => {
+                self.metadata.update(&event);
+                self.buff_database.add_event(&event);
+                self.skill_database.add_event(&event);
+                if event.kind().is_timed() && options.keeps(event.kind().category()) {
+                    self.events.push(event);
+                }
+            }
+            Err(error) => self
+                .dropped_events
+                .push(DroppedEvent::new(raw_event.clone(), error)),
+        }
+    }
+}
+
+fn setup_agents(data: &raw::Evtc, options: &ProcessingOptions) -> Result<Vec<Agent>, EvtcError> {
+    data.agents
+        .iter()
+        .map(|raw_agent| convert_agent(raw_agent, options))
+        .collect()
+}
+
+fn convert_agent(raw_agent: &raw::Agent, options: &ProcessingOptions) -> Result<Agent, EvtcError> {
+    if options.unknown_professions_fatal {
+        Agent::try_from(raw_agent)
+    } else {
+        Agent::from_raw_lenient(raw_agent)
+    }
 }
 
 fn get_agent_by_addr(agents: &mut [Agent], addr: u64) -> Option<&mut Agent> {
@@ -111,40 +891,273 @@ fn get_agent_by_addr(agents: &mut [Agent], addr: u64) -> Option<&mut Agent> {
     Some(&mut agents[pos])
 }
 
-fn set_agent_awares(data: &raw::Evtc, agents: &mut [Agent]) -> Result<(), EvtcError> {
+/// Set the aware times and masters of the agents, and return the periods in which they had their
+/// instance ids.
+fn resolve_agents(
+    data: &raw::Evtc,
+    agents: &mut [Agent],
+    options: &ProcessingOptions,
+    tracker: &mut Tracker,
+) -> Result<Vec<InstancePeriod>, EvtcError> {
+    #[cfg(feature = "rayon")]
+    {
+        if options.parallel {
+            let periods = parallel::set_agent_awares(data, agents, tracker)?;
+            parallel::set_agent_masters(data, agents, &periods, tracker)?;
+            return Ok(periods);
+        }
+    }
+    #[cfg(not(feature = "rayon"))]
+    let _ = options;
+
+    // Do the first aware/last aware field
+    let periods = set_agent_awares(data, agents, tracker)?;
+
+    // Set the master addr field
+    set_agent_masters(data, agents, &periods, tracker)?;
+    Ok(periods)
+}
+
+fn set_agent_awares(
+    data: &raw::Evtc,
+    agents: &mut [Agent],
+    tracker: &mut Tracker,
+) -> Result<Vec<InstancePeriod>, EvtcError> {
+    let mut periods = PeriodCollector::default();
     for event in &data.events {
-        if event.is_statechange == raw::CbtStateChange::None {
-            if let Some(current_agent) = get_agent_by_addr(agents, event.src_agent) {
-                current_agent.set_instance_id(event.src_instid);
-                if current_agent.first_aware() == 0 {
-                    current_agent.set_first_aware(event.time);
-                }
-                current_agent.set_last_aware(event.time);
+        update_agent_aware(agents, &mut periods, event);
+        tracker.event_processed()?;
+    }
+    Ok(periods.finish())
+}
+
+/// Update the instance id and aware times of the source agent of the given event.
+fn update_agent_aware(agents: &mut [Agent], periods: &mut PeriodCollector, event: &raw::CbtEvent) {
+    if event.is_statechange == raw::CbtStateChange::None {
+        if let Some(current_agent) = get_agent_by_addr(agents, event.src_agent) {
+            current_agent.set_instance_id(event.src_instid);
+            if current_agent.first_aware() == 0 {
+                current_agent.set_first_aware(event.time);
             }
+            current_agent.set_last_aware(event.time);
+            periods.add(InstancePeriod::new(
+                event.src_instid,
+                event.src_agent,
+                event.time,
+            ));
         }
     }
-    Ok(())
 }
 
-fn set_agent_masters(data: &raw::Evtc, agents: &mut [Agent]) -> Result<(), EvtcError> {
+/// Collects the periods in which the agents had their instance ids.
+#[derive(Debug, Clone, Default)]
+struct PeriodCollector {
+    periods: Vec<InstancePeriod>,
+    /// The index of the current period of each agent.
+    current: HashMap<u64, usize>,
+    /// The agent that has been seen with each instance id most recently.
+    holders: HashMap<u16, u64>,
+}
+
+impl PeriodCollector {
+    /// Add a period, extending the current period of the agent if it has the same instance id.
+    ///
+    /// The periods of an agent have to be added in chronological order.
+    fn add(&mut self, period: InstancePeriod) {
+        self.holders.insert(period.instance_id(), period.addr());
+        if let Some(&index) = self.current.get(&period.addr()) {
+            let current = &mut self.periods[index];
+            if current.instance_id() == period.instance_id() {
+                current.set_last_aware(period.last_aware());
+                return;
+            }
+            // The agent has given up its old instance id.
+            let old = current.instance_id();
+            if self.holders.get(&old) == Some(&period.addr()) {
+                self.holders.remove(&old);
+            }
+        }
+        self.current.insert(period.addr(), self.periods.len());
+        self.periods.push(period);
+    }
+
+    /// Return the address of the agent that currently has the given instance id, i.e. the agent
+    /// that has been seen with it most recently.
+    fn current_holder(&self, instance_id: u16) -> Option<u64> {
+        self.holders.get(&instance_id).copied()
+    }
+
+    /// Add the periods of the given collector, which have to come after the periods of this one.
+    #[cfg(feature = "rayon")]
+    fn merge(&mut self, other: PeriodCollector) {
+        for period in other.periods {
+            self.add(period);
+        }
+    }
+
+    /// Return the periods, sorted by instance id and time.
+    fn finish(self) -> Vec<InstancePeriod> {
+        let mut periods = self.periods;
+        sort_periods(&mut periods);
+        periods
+    }
+}
+
+/// Sort the periods by instance id and time, as expected by
+/// [`Log::agent_by_instance_id_at`][Log::agent_by_instance_id_at].
+fn sort_periods(periods: &mut [InstancePeriod]) {
+    periods.sort_by_key(|p| (p.instance_id(), p.first_aware(), p.addr(), p.last_aware()));
+}
+
+fn set_agent_masters(
+    data: &raw::Evtc,
+    agents: &mut [Agent],
+    periods: &[InstancePeriod],
+    tracker: &mut Tracker,
+) -> Result<(), EvtcError> {
     for event in &data.events {
-        if event.src_master_instid != 0 {
-            let mut master_addr = None;
-            for agent in &*agents {
-                if agent.instance_id() == event.src_master_instid
-                    && agent.first_aware() < event.time
-                    && event.time < agent.last_aware()
-                {
-                    master_addr = Some(agent.addr());
-                    break;
+        if let Some(master_addr) = find_master(periods, event) {
+            if let Some(current_slave) = get_agent_by_addr(agents, event.src_agent) {
+                current_slave.set_master_agent(Some(master_addr));
+            }
+        }
+        tracker.event_processed()?;
+    }
+    Ok(())
+}
+
+/// Find the address of the master of the source agent of the given event.
+///
+/// The master is the agent that had the master instance id at the time of the event, using the
+/// same rule as [`Log::agent_by_instance_id_at`][Log::agent_by_instance_id_at].
+fn find_master(periods: &[InstancePeriod], event: &raw::CbtEvent) -> Option<u64> {
+    if event.src_master_instid == 0 {
+        return None;
+    }
+    find_instance_period(periods, event.src_master_instid, event.time).map(InstancePeriod::addr)
+}
+
+/// Parallel versions of the processing steps.
+///
+/// The events are split into chunks, which are processed on the rayon thread pool. The results of
+/// the chunks are then merged in order, so that the outcome is the same as for the sequential
+/// versions.
+#[cfg(feature = "rayon")]
+mod parallel {
+    use std::{collections::HashMap, convert::TryFrom};
+
+    use rayon::prelude::*;
+
+    use super::{
+        find_master, get_agent_by_addr, raw, Agent, Event, EvtcError, FromRawEventError,
+        InstancePeriod, PeriodCollector, Tracker,
+    };
+
+    /// The number of events that make up a chunk.
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Split the events into batches of chunks.
+    ///
+    /// The chunks of a batch are processed in parallel, and the progress is reported after each
+    /// batch.
+    fn batches(events: &[raw::CbtEvent]) -> std::slice::Chunks<'_, raw::CbtEvent> {
+        events.chunks(CHUNK_SIZE * rayon::current_num_threads())
+    }
+
+    pub(super) fn convert_events(
+        events: &[raw::CbtEvent],
+    ) -> Vec<Result<Event, FromRawEventError>> {
+        events.par_iter().map(Event::try_from).collect()
+    }
+
+    /// The aware information of a single agent within a chunk.
+    struct Awareness {
+        instance_id: u16,
+        first_aware: u64,
+        last_aware: u64,
+    }
+
+    pub(super) fn set_agent_awares(
+        data: &raw::Evtc,
+        agents: &mut [Agent],
+        tracker: &mut Tracker,
+    ) -> Result<Vec<InstancePeriod>, EvtcError> {
+        let mut all_periods = PeriodCollector::default();
+        for batch in batches(&data.events) {
+            let known = &*agents;
+            let chunks = batch
+                .par_chunks(CHUNK_SIZE)
+                .map(|chunk| {
+                    let mut awareness = HashMap::new();
+                    let mut periods = PeriodCollector::default();
+                    for event in chunk {
+                        if event.is_statechange != raw::CbtStateChange::None
+                            || known
+                                .binary_search_by_key(&event.src_agent, Agent::addr)
+                                .is_err()
+                        {
+                            continue;
+                        }
+                        let entry = awareness.entry(event.src_agent).or_insert(Awareness {
+                            instance_id: 0,
+                            first_aware: 0,
+                            last_aware: 0,
+                        });
+                        entry.instance_id = event.src_instid;
+                        if entry.first_aware == 0 {
+                            entry.first_aware = event.time;
+                        }
+                        entry.last_aware = event.time;
+                        periods.add(InstancePeriod::new(
+                            event.src_instid,
+                            event.src_agent,
+                            event.time,
+                        ));
+                    }
+                    (awareness, periods)
+                })
+                .collect::<Vec<_>>();
+
+            for (awareness, periods) in chunks {
+                all_periods.merge(periods);
+                for (addr, awareness) in awareness {
+                    let agent = get_agent_by_addr(agents, addr).expect("agent disappeared");
+                    agent.set_instance_id(awareness.instance_id);
+                    if agent.first_aware() == 0 {
+                        agent.set_first_aware(awareness.first_aware);
+                    }
+                    agent.set_last_aware(awareness.last_aware);
                 }
             }
-            if let Some(master_addr) = master_addr {
-                if let Some(current_slave) = get_agent_by_addr(agents, event.src_agent) {
-                    current_slave.set_master_agent(Some(master_addr));
+            tracker.events_processed(batch.len() as u64)?;
+        }
+        Ok(all_periods.finish())
+    }
+
+    pub(super) fn set_agent_masters(
+        data: &raw::Evtc,
+        agents: &mut [Agent],
+        periods: &[InstancePeriod],
+        tracker: &mut Tracker,
+    ) -> Result<(), EvtcError> {
+        for batch in batches(&data.events) {
+            let chunks = batch
+                .par_chunks(CHUNK_SIZE)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .filter_map(|event| Some((event.src_agent, find_master(periods, event)?)))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            for (slave_addr, master_addr) in chunks.into_iter().flatten() {
+                if let Some(slave) = get_agent_by_addr(agents, slave_addr) {
+                    slave.set_master_agent(Some(master_addr));
                 }
             }
+            tracker.events_processed(batch.len() as u64)?;
         }
+        Ok(())
     }
-    Ok(())
 }
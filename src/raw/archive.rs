@@ -0,0 +1,224 @@
+//! Support for zip archives that contain multiple logs.
+//!
+//! arcdps itself writes `.zevtc` files, which are zip archives with a single entry. Logs are
+//! however often shared as bundles, where a single zip archive contains many `.evtc` or `.zevtc`
+//! files. [`Archive`][Archive] allows you to list and parse the logs in such a bundle:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::fs::File;
+//! use std::io::BufReader;
+//! use evtclib::raw::Archive;
+//! let mut archive = Archive::new(BufReader::new(File::open("bundle.zip")?))?;
+//! for index in 0..archive.len() {
+//!     let evtc = archive.parse(index)?;
+//!     println!("{}: {} agents", archive.name(index).unwrap(), evtc.agents.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Nested archives (such as a `.zevtc` inside of a `.zip`) are unpacked transparently. Errors that
+//! occur while parsing an entry are wrapped in [`ParseError::ArchiveEntry`], which contains the
+//! name of the entry.
+//!
+//! Bundles are often downloaded from untrusted sources, so [`Archive::with_limits`] can be used to
+//! enforce [`ParseLimits`], which also bound the size of nested archives.
+use std::io::{BufReader, Cursor, Read, Seek};
+
+use zip::{result::ZipError, ZipArchive};
+
+use super::parser::{self, PartialEvtc};
+use super::{Evtc, Limit, ParseError, ParseLimits, ParseResult};
+
+/// File name suffixes of the entries that are considered to be logs.
+pub(crate) const LOG_SUFFIXES: &[&str] = &[".evtc", ".zevtc", ".evtc.zip"];
+
+/// A zip archive that contains (possibly) multiple logs.
+///
+/// Only entries with a name ending in `.evtc`, `.zevtc` or `.evtc.zip` are considered to be logs,
+/// other entries (and directories) are ignored. The indices that are used by the methods of this
+/// type refer to the list of log entries, not to the indices of the underlying zip archive.
+pub struct Archive<R> {
+    zip: ZipArchive<R>,
+    /// Names of the log entries, together with their index in the zip archive.
+    entries: Vec<(String, usize)>,
+    limits: ParseLimits,
+}
+
+impl<R: Read + Seek> Archive<R> {
+    /// Open the given zip archive and list the log entries.
+    pub fn new(input: R) -> ParseResult<Self> {
+        Self::with_limits(input, ParseLimits::default())
+    }
+
+    /// Open the given zip archive and list the log entries, respecting the given limits when
+    /// parsing the entries.
+    ///
+    /// The [decompressed size limit][ParseLimits::max_decompressed_size] applies to every entry
+    /// separately, and also to nested archives (which have to be read into memory). See
+    /// [`ParseLimits`][ParseLimits] for more information.
+    pub fn with_limits(input: R, limits: ParseLimits) -> ParseResult<Self> {
+        let mut zip = ZipArchive::new(input)?;
+        let mut entries = Vec::new();
+        for index in 0..zip.len() {
+            let file = zip.by_index_raw(index)?;
+            let lowercase = file.name().to_lowercase();
+            if !file.is_dir() && LOG_SUFFIXES.iter().any(|s| lowercase.ends_with(s)) {
+                entries.push((file.name().to_owned(), index));
+            }
+        }
+        Ok(Archive {
+            zip,
+            entries,
+            limits,
+        })
+    }
+
+    /// Return the number of log entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether the archive contains no logs.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Return the names of all log entries, in the order in which they appear in the archive.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(name, _)| name as &str)
+    }
+
+    /// Return the name of the log entry with the given index.
+    pub fn name(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(|(name, _)| name as &str)
+    }
+
+    /// Return the index of the log entry with the given name.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|(n, _)| n == name)
+    }
+
+    /// Parse the log entry with the given index.
+    pub fn parse(&mut self, index: usize) -> ParseResult<Evtc> {
+        let limits = self.limits;
+        self.parse_with_limits(index, &limits)
+    }
+
+    /// Parse the log entry with the given index, respecting the given limits instead of the ones
+    /// that the archive was opened with.
+    pub fn parse_with_limits(&mut self, index: usize, limits: &ParseLimits) -> ParseResult<Evtc> {
+        self.with_entry(index, limits, |input| {
+            super::parse_decompressed(input, limits, None)
+        })
+    }
+
+    /// Parse the log entry with the given name.
+    pub fn parse_by_name(&mut self, name: &str) -> ParseResult<Evtc> {
+        let index = self.index_by_name(name)?;
+        self.parse(index)
+    }
+
+    /// Parse the header, agents and skills of the log entry with the given index.
+    ///
+    /// This is cheaper than [`parse`][Archive::parse] for plain `.evtc` entries, as the events do
+    /// not have to be decompressed.
+    pub fn parse_partial(&mut self, index: usize) -> ParseResult<PartialEvtc> {
+        let limits = self.limits;
+        self.with_entry(index, &limits, |input| {
+            parser::parse_partial_file_with_limits(input, &limits)
+        })
+    }
+
+    /// Parse the header, agents and skills of the log entry with the given name.
+    pub fn parse_partial_by_name(&mut self, name: &str) -> ParseResult<PartialEvtc> {
+        let index = self.index_by_name(name)?;
+        self.parse_partial(index)
+    }
+
+    /// Consume the archive and return the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.zip.into_inner()
+    }
+
+    fn index_by_name(&self, name: &str) -> ParseResult<usize> {
+        self.index_of(name)
+            .ok_or_else(|| entry_error(name, ZipError::FileNotFound.into()))
+    }
+
+    /// Run the given parser on the (uncompressed) content of the given entry.
+    ///
+    /// Errors are wrapped in [`ParseError::ArchiveEntry`].
+    fn with_entry<T>(
+        &mut self,
+        index: usize,
+        limits: &ParseLimits,
+        parse: impl FnOnce(&mut dyn Read) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        let (name, zip_index) = match self.entries.get(index) {
+            Some((name, zip_index)) => (name.clone(), *zip_index),
+            None => {
+                return Err(entry_error(
+                    &format!("#{}", index),
+                    ZipError::FileNotFound.into(),
+                ))
+            }
+        };
+        read_entry(&mut self.zip, zip_index, limits, parse).map_err(|e| entry_error(&name, e))
+    }
+}
+
+fn read_entry<R: Read + Seek, T>(
+    zip: &mut ZipArchive<R>,
+    index: usize,
+    limits: &ParseLimits,
+    parse: impl FnOnce(&mut dyn Read) -> ParseResult<T>,
+) -> ParseResult<T> {
+    let mut file = BufReader::new(zip.by_index(index)?);
+    // We cannot know whether an entry is a nested archive just by looking at the name (.evtc.zip
+    // is a nested archive, but a user might as well have renamed a .zevtc to .evtc), so we sniff
+    // the content instead.
+    let mut magic = [0; 2];
+    file.read_exact(&mut magic)?;
+    let mut input = (&magic[..]).chain(file);
+    if &magic == b"PK" {
+        // The zip reader needs to seek, so we have to read the whole nested archive into memory.
+        let data = read_limited(input, limits.max_decompressed_size)?;
+        let mut nested = ZipArchive::new(Cursor::new(data))?;
+        let mut nested_file = BufReader::new(nested.by_index(0)?);
+        parse(&mut nested_file)
+    } else {
+        parse(&mut input)
+    }
+}
+
+/// Read the whole input into memory, failing if it is larger than the given maximum.
+fn read_limited<R: Read>(mut input: R, maximum: Option<u64>) -> ParseResult<Vec<u8>> {
+    let mut data = Vec::new();
+    let maximum = match maximum {
+        Some(maximum) => maximum,
+        None => {
+            input.read_to_end(&mut data)?;
+            return Ok(data);
+        }
+    };
+    // Read one byte more than allowed, so that we can tell whether the input ends at the limit.
+    input
+        .take(maximum.saturating_add(1))
+        .read_to_end(&mut data)?;
+    if data.len() as u64 > maximum {
+        return Err(ParseError::LimitExceeded {
+            limit: Limit::DecompressedSize,
+            maximum,
+        });
+    }
+    Ok(data)
+}
+
+fn entry_error(name: &str, source: ParseError) -> ParseError {
+    ParseError::ArchiveEntry {
+        name: name.to_owned(),
+        source: Box::new(source),
+    }
+}
@@ -0,0 +1,197 @@
+//! Asynchronous versions of the parsing functions.
+//!
+//! This module is only available with the `async` feature. The functions mirror the ones in
+//! [`parser`][super::parser], but they read from a [`tokio::io::AsyncRead`][AsyncRead] instead of
+//! a [`std::io::Read`][std::io::Read]. The actual decoding is shared with the synchronous parser,
+//! only the reading of the bytes is done asynchronously.
+//!
+//! Especially [`parse_partial_file_async`][parse_partial_file_async] is useful to check uploaded
+//! files early, as it fails as soon as the header is malformed, without having to wait for the
+//! rest of the upload.
+//!
+//! The same advice about buffering applies: Wrap your reader in a
+//! [`tokio::io::BufReader`][tokio::io::BufReader], unless it does buffering on its own.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use tokio::io::BufReader;
+//! use tokio::fs::File;
+//! let input = BufReader::new(File::open("log.evtc").await?);
+//! let evtc = evtclib::raw::parse_file_async(input).await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::io::{Cursor, ErrorKind};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::parser::{self, PartialEvtc, AGENT_SIZE, EVENT_SIZE, HEADER_SIZE, SKILL_SIZE};
+use super::{Evtc, Limit, ParseError, ParseLimits, ParseResult};
+
+/// Parse a partial EVTC file asynchronously.
+///
+/// See [`parser::parse_partial_file`][parser::parse_partial_file].
+///
+/// * `input` - Input stream.
+pub async fn parse_partial_file_async<R: AsyncRead + Unpin>(input: R) -> ParseResult<PartialEvtc> {
+    parse_partial_file_async_with_limits(input, &ParseLimits::default()).await
+}
+
+/// Parse a partial EVTC file asynchronously, respecting the given limits.
+///
+/// See [`parser::parse_partial_file_with_limits`][parser::parse_partial_file_with_limits].
+///
+/// * `input` - Input stream.
+/// * `limits` - The limits to enforce.
+pub async fn parse_partial_file_async_with_limits<R: AsyncRead + Unpin>(
+    mut input: R,
+    limits: &ParseLimits,
+) -> ParseResult<PartialEvtc> {
+    let mut header = [0; HEADER_SIZE];
+    input.read_exact(&mut header).await?;
+    let header = parser::parse_header(&header[..])?;
+    ParseLimits::check(
+        Limit::Agents,
+        limits.max_agents.map(Into::into),
+        header.agent_count.into(),
+    )?;
+
+    // We read the agents and skills one by one instead of all at once, so that a bogus count in a
+    // (possibly malicious) file does not make us allocate a huge buffer up front.
+    let mut agents = Vec::new();
+    let mut agent = [0; AGENT_SIZE];
+    for _ in 0..header.agent_count {
+        input.read_exact(&mut agent).await?;
+        agents.push(parser::parse_agent(&agent[..])?);
+    }
+
+    let skill_count = input.read_u32_le().await?;
+    ParseLimits::check(
+        Limit::Skills,
+        limits.max_skills.map(Into::into),
+        skill_count.into(),
+    )?;
+    let mut skills = Vec::new();
+    let mut skill = [0; SKILL_SIZE];
+    for _ in 0..skill_count {
+        input.read_exact(&mut skill).await?;
+        skills.push(parser::parse_skill(&skill[..])?);
+    }
+
+    Ok(PartialEvtc {
+        header,
+        skill_count,
+        agents,
+        skills,
+    })
+}
+
+/// Finish a partial EVTC by reading the events asynchronously.
+///
+/// See [`parser::finish_parsing`][parser::finish_parsing].
+///
+/// * `partial` - The partial EVTC.
+/// * `input` - The input stream.
+pub async fn finish_parsing_async<R: AsyncRead + Unpin>(
+    partial: PartialEvtc,
+    input: R,
+) -> ParseResult<Evtc> {
+    finish_parsing_async_with_limits(partial, input, &ParseLimits::default()).await
+}
+
+/// Finish a partial EVTC by reading the events asynchronously, respecting the given limits.
+///
+/// See [`parser::finish_parsing_with_limits`][parser::finish_parsing_with_limits].
+///
+/// * `partial` - The partial EVTC.
+/// * `input` - The input stream.
+/// * `limits` - The limits to enforce.
+pub async fn finish_parsing_async_with_limits<R: AsyncRead + Unpin>(
+    partial: PartialEvtc,
+    mut input: R,
+    limits: &ParseLimits,
+) -> ParseResult<Evtc> {
+    let parser = parser::event_decoder(partial.header.revision)?;
+
+    let mut events = Vec::new();
+    let mut unknown_statechanges = 0;
+    let mut bytes = [0; EVENT_SIZE];
+    loop {
+        match input.read_exact(&mut bytes).await {
+            Ok(_) => (),
+            // Same as the synchronous parser, we expect the events to go until the end of the
+            // file.
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        match parser(&bytes) {
+            Ok(x) => {
+                events.push(x);
+                ParseLimits::check(Limit::Events, limits.max_events, events.len() as u64)?;
+            }
+            Err(ParseError::UnknownStateChange(_)) => {
+                // Ignore unknown statechanges, as advised by arcdps.
+                unknown_statechanges += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(Evtc {
+        header: partial.header,
+        skill_count: partial.skill_count,
+        agents: partial.agents,
+        skills: partial.skills,
+        events,
+        unknown_statechanges,
+    })
+}
+
+/// Parse a complete EVTC file asynchronously.
+///
+/// See [`parser::parse_file`][parser::parse_file].
+///
+/// * `input` - Input stream.
+pub async fn parse_file_async<R: AsyncRead + Unpin>(input: R) -> ParseResult<Evtc> {
+    parse_file_async_with_limits(input, &ParseLimits::default()).await
+}
+
+/// Parse a complete EVTC file asynchronously, respecting the given limits.
+///
+/// See [`parser::parse_file_with_limits`][parser::parse_file_with_limits].
+///
+/// * `input` - Input stream.
+/// * `limits` - The limits to enforce.
+pub async fn parse_file_async_with_limits<R: AsyncRead + Unpin>(
+    mut input: R,
+    limits: &ParseLimits,
+) -> ParseResult<Evtc> {
+    let partial = parse_partial_file_async_with_limits(&mut input, limits).await?;
+    finish_parsing_async_with_limits(partial, input, limits).await
+}
+
+/// Parse a complete log that was compressed as a zip file asynchronously.
+///
+/// The zip format keeps its index at the end of the file, so the archive is read into memory
+/// completely before it is decompressed with [`parse_zip`][super::parse_zip].
+///
+/// * `input` - Input stream.
+pub async fn parse_zip_async<R: AsyncRead + Unpin>(input: R) -> ParseResult<Evtc> {
+    parse_zip_async_with_limits(input, &ParseLimits::default()).await
+}
+
+/// Parse a complete log that was compressed as a zip file asynchronously, respecting the given
+/// limits.
+///
+/// See [`parse_zip_with_limits`][super::parse_zip_with_limits].
+///
+/// * `input` - Input stream.
+/// * `limits` - The limits to enforce.
+pub async fn parse_zip_async_with_limits<R: AsyncRead + Unpin>(
+    mut input: R,
+    limits: &ParseLimits,
+) -> ParseResult<Evtc> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data).await?;
+    super::parse_zip_with_limits(Cursor::new(data), limits)
+}
@@ -0,0 +1,244 @@
+//! Filters to skip events while parsing.
+//!
+//! Many applications only need a handful of event types. For example, most of the
+//! [`Analyzer::outcome`][crate::Analyzer::outcome] implementations only look at
+//! [`Reward`][CbtStateChange::Reward] and [`ChangeDead`][CbtStateChange::ChangeDead] events. An
+//! [`EventFilter`][EventFilter] is checked right after the record of an event has been read, and
+//! events that do not match are skipped without being decoded:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use evtclib::raw::{self, CbtStateChange, EventFilter};
+//! let filter = EventFilter::none()
+//!     .include_statechange(CbtStateChange::Reward)
+//!     .include_statechange(CbtStateChange::ChangeDead)
+//!     .include_statechange(CbtStateChange::MaxHealthUpdate);
+//! let data = std::fs::read("log.evtc")?;
+//! let evtc = raw::parser::parse_bytes_filtered(&data, &filter)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Note that the high-level processing uses the events to find the aware times and the masters
+//! of the agents. If you [`process`][crate::process] a log without the
+//! [`Physical`][EventClass::Physical] (or any other non-statechange) events, those will be
+//! incomplete.
+
+use super::parser::EVENT_SIZE;
+use super::{CbtActivation, CbtBuffRemove, CbtEvent, CbtStateChange};
+
+/// The classes of events that are not statechanges.
+///
+/// arcdps distinguishes the events by looking at the fields in this order: statechange,
+/// activation, buff removal and buff. The statechanges are selected individually, see
+/// [`EventFilter::include_statechange`][EventFilter::include_statechange].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventClass {
+    /// Skill activations (`is_activation` is set).
+    Activation,
+    /// Buff removals (`is_buffremove` is set).
+    BuffRemove,
+    /// Buff applications and buff damage, such as condition ticks (`buff` is set).
+    Buff,
+    /// Direct (physical) damage (none of the above is set).
+    Physical,
+}
+
+impl EventClass {
+    /// All event classes.
+    pub const ALL: &'static [EventClass] = &[
+        EventClass::Activation,
+        EventClass::BuffRemove,
+        EventClass::Buff,
+        EventClass::Physical,
+    ];
+
+    fn bit(self) -> u8 {
+        match self {
+            EventClass::Activation => 1,
+            EventClass::BuffRemove => 2,
+            EventClass::Buff => 4,
+            EventClass::Physical => 8,
+        }
+    }
+}
+
+/// A filter that selects which events should be decoded.
+///
+/// By default (and with [`EventFilter::all`][EventFilter::all]), every event matches. Use
+/// [`EventFilter::none`][EventFilter::none] to start with a filter that rejects everything, and
+/// then include the events that you are interested in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EventFilter {
+    /// A bit set of the included statechange values.
+    statechanges: [u64; 4],
+    /// A bit set of the included [`EventClass`]es.
+    classes: u8,
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl EventFilter {
+    /// Create a filter that matches every event.
+    pub fn all() -> Self {
+        EventFilter {
+            statechanges: [u64::MAX; 4],
+            classes: EventClass::ALL.iter().map(|c| c.bit()).sum(),
+        }
+    }
+
+    /// Create a filter that matches no event.
+    pub fn none() -> Self {
+        EventFilter {
+            statechanges: [0; 4],
+            classes: 0,
+        }
+    }
+
+    /// Include the events with the given statechange.
+    pub fn include_statechange(mut self, statechange: CbtStateChange) -> Self {
+        let value = statechange as u8;
+        self.statechanges[usize::from(value / 64)] |= 1 << (value % 64);
+        self
+    }
+
+    /// Exclude the events with the given statechange.
+    pub fn exclude_statechange(mut self, statechange: CbtStateChange) -> Self {
+        let value = statechange as u8;
+        self.statechanges[usize::from(value / 64)] &= !(1 << (value % 64));
+        self
+    }
+
+    /// Include the events of the given class.
+    pub fn include(mut self, class: EventClass) -> Self {
+        self.classes |= class.bit();
+        self
+    }
+
+    /// Exclude the events of the given class.
+    pub fn exclude(mut self, class: EventClass) -> Self {
+        self.classes &= !class.bit();
+        self
+    }
+
+    /// Check whether this filter matches every event.
+    pub fn is_all(&self) -> bool {
+        *self == Self::all()
+    }
+
+    /// Check whether the given (decoded) event matches this filter.
+    pub fn matches(&self, event: &CbtEvent) -> bool {
+        self.check(
+            event.is_statechange as u8,
+            event.is_activation,
+            event.is_buffremove,
+            event.buff,
+        )
+    }
+
+    /// Check whether the given raw record matches this filter.
+    ///
+    /// Only the bytes that are needed to classify the event are looked at, which is much cheaper
+    /// than decoding the whole event. The result is the same as decoding the event and using
+    /// [`matches`][EventFilter::matches].
+    ///
+    /// * `bytes` - The raw record of the event.
+    /// * `revision` - The revision of the file. Unknown revisions are treated like revision 1.
+    pub fn matches_bytes(&self, bytes: &[u8; EVENT_SIZE], revision: u8) -> bool {
+        // Offsets of statechange, activation, buffremove and buff.
+        let (statechange, activation, buffremove, buff) = match revision {
+            0 => (59, 54, 55, 52),
+            _ => (56, 51, 52, 49),
+        };
+        self.check(
+            bytes[statechange],
+            CbtActivation::from(bytes[activation]),
+            CbtBuffRemove::from(bytes[buffremove]),
+            bytes[buff],
+        )
+    }
+
+    fn check(
+        &self,
+        statechange: u8,
+        activation: CbtActivation,
+        buffremove: CbtBuffRemove,
+        buff: u8,
+    ) -> bool {
+        if statechange != CbtStateChange::None as u8 {
+            return self.statechanges[usize::from(statechange / 64)] & (1 << (statechange % 64))
+                != 0;
+        }
+        // Unknown activations and removals are classified like the conversion to events does.
+        let class = if !matches!(activation, CbtActivation::None | CbtActivation::Other(_)) {
+            EventClass::Activation
+        } else if !matches!(buffremove, CbtBuffRemove::None | CbtBuffRemove::Other(_)) {
+            EventClass::BuffRemove
+        } else if buff != 0 {
+            EventClass::Buff
+        } else {
+            EventClass::Physical
+        };
+        self.classes & class.bit() != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_and_none() {
+        let event = CbtEvent::default();
+        assert!(EventFilter::all().matches(&event));
+        assert!(!EventFilter::none().matches(&event));
+        assert!(EventFilter::default().is_all());
+        assert!(!EventFilter::none().is_all());
+    }
+
+    #[test]
+    fn test_statechange() {
+        let filter = EventFilter::none().include_statechange(CbtStateChange::Reward);
+        let reward = CbtEvent {
+            is_statechange: CbtStateChange::Reward,
+            ..Default::default()
+        };
+        let dead = CbtEvent {
+            is_statechange: CbtStateChange::ChangeDead,
+            ..Default::default()
+        };
+        assert!(filter.matches(&reward));
+        assert!(!filter.matches(&dead));
+        assert!(!filter.matches(&CbtEvent::default()));
+
+        let filter = EventFilter::all().exclude_statechange(CbtStateChange::Reward);
+        assert!(!filter.matches(&reward));
+        assert!(filter.matches(&dead));
+    }
+
+    #[test]
+    fn test_classes() {
+        let filter = EventFilter::none().include(EventClass::Buff);
+        let buff = CbtEvent {
+            buff: 1,
+            ..Default::default()
+        };
+        let removal = CbtEvent {
+            buff: 1,
+            is_buffremove: CbtBuffRemove::All,
+            ..Default::default()
+        };
+        assert!(filter.matches(&buff));
+        assert!(!filter.matches(&removal));
+        assert!(!filter.matches(&CbtEvent::default()));
+
+        let filter = EventFilter::all().exclude(EventClass::Physical);
+        assert!(filter.matches(&buff));
+        assert!(filter.matches(&removal));
+        assert!(!filter.matches(&CbtEvent::default()));
+    }
+}
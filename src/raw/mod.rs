@@ -6,25 +6,337 @@
 //! functions whenever possible.
 mod types;
 
-use zip::ZipArchive;
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
 
 pub use self::types::{
-    Agent, CbtActivation, CbtBuffRemove, CbtCustomSkill, CbtEvent, CbtResult, CbtStateChange,
-    Language, Skill, IFF,
+    Agent, Attribute, BuffCategory, CbtActivation, CbtBuffRemove, CbtCustomSkill, CbtEvent,
+    CbtResult, CbtStateChange, Language, Skill, StateChangeData, IFF,
 };
 
 pub mod parser;
 
-pub use self::parser::{parse_file, Evtc, ParseError, ParseResult};
+pub mod filter;
+pub use self::filter::{EventClass, EventFilter};
+
+pub use self::parser::{
+    parse_bytes, parse_bytes_with_limits, parse_file, parse_file_monitored, parse_file_with_limits,
+    EventReader, Evtc, Limit, ParseError, ParseLimits, ParseResult, PartialEvtc,
+};
+
+#[cfg(feature = "rayon")]
+pub use self::parser::{parse_bytes_parallel, parse_bytes_parallel_with_limits};
+
+pub mod progress;
+use self::progress::Tracker;
+pub use self::progress::{CancellationToken, Progress, ProgressMonitor, Stage};
+
+pub mod writer;
+
+pub mod archive;
+pub use self::archive::Archive;
+
+pub mod recovery;
+pub use self::recovery::{parse_file_recovering, parse_zip_recovering, RecoveryReport};
+
+#[cfg(feature = "async")]
+pub mod async_parser;
+#[cfg(feature = "async")]
+pub use self::async_parser::{
+    parse_file_async, parse_file_async_with_limits, parse_partial_file_async,
+    parse_partial_file_async_with_limits, parse_zip_async, parse_zip_async_with_limits,
+};
+
+pub use self::writer::{write_file, WriteError, WriteResult};
 
 use std::ffi::CStr;
-use std::io::{BufReader, Read, Seek};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+/// Name of the log file inside of zip archives that are written by [`write_zip`][write_zip].
+const ZIP_ENTRY_NAME: &str = "log.evtc";
 
 /// Parse a complete log that was compressed as a zip file.
+///
+/// This always parses the first entry of the archive. If you have an archive with multiple logs,
+/// use [`Archive`][Archive] instead.
 pub fn parse_zip<R: Read + Seek>(input: R) -> ParseResult<Evtc> {
+    parse_zip_with_limits(input, &ParseLimits::default())
+}
+
+/// Parse a complete log that was compressed as a zip file, respecting the given limits.
+///
+/// See [`ParseLimits`][ParseLimits] for more information.
+pub fn parse_zip_with_limits<R: Read + Seek>(input: R, limits: &ParseLimits) -> ParseResult<Evtc> {
+    let mut archive = ZipArchive::new(input)?;
+    let file = archive.by_index(0)?;
+    parse_decompressed(file, limits, None)
+}
+
+/// Parse a complete log that was compressed as a zip file, reporting the progress to the given
+/// monitor.
+///
+/// The bytes read are counted in the compressed input, and the total size is determined by
+/// seeking to its end. See [`ProgressMonitor`][ProgressMonitor] for more information.
+pub fn parse_zip_monitored<R: Read + Seek>(
+    mut input: R,
+    limits: &ParseLimits,
+    monitor: &ProgressMonitor,
+) -> ParseResult<Evtc> {
+    let mut tracker = monitor.start(Stage::Parsing);
+    let end = stream_len(&mut input)?;
+    tracker.set_total_bytes(end);
+
+    let mut archive = ZipArchive::new(tracker.reader(input))?;
+    let file = archive.by_index(0)?;
+    let evtc = parse_decompressed(file, limits, Some(&mut tracker))?;
+    // The central directory at the end of the archive has already been read at the start.
+    tracker.set_position(end);
+    tracker.check()?;
+    Ok(evtc)
+}
+
+/// Determine the length of the given stream, without changing its position.
+pub(crate) fn stream_len<S: Seek>(input: &mut S) -> std::io::Result<u64> {
+    let start = input.stream_position()?;
+    let end = input.seek(SeekFrom::End(0))?;
+    input.seek(SeekFrom::Start(start))?;
+    Ok(end)
+}
+
+/// Parse the header, agents and skills of a log that was compressed as a zip file.
+///
+/// Only the start of the file has to be decompressed, which makes this much faster than
+/// [`parse_zip`][parse_zip].
+pub fn parse_partial_zip<R: Read + Seek>(input: R) -> ParseResult<PartialEvtc> {
     let mut archive = ZipArchive::new(input)?;
-    let mut file = BufReader::new(archive.by_index(0)?);
-    parse_file(&mut file)
+    let file = BufReader::new(archive.by_index(0)?);
+    parser::parse_partial_file(file)
+}
+
+/// Parse the decompressed content of a log, making sure that it does not exceed the
+/// [decompressed size limit][ParseLimits::max_decompressed_size].
+///
+/// The size that is given in the compressed file cannot be trusted, so we count the bytes while
+/// reading them instead.
+///
+/// If a tracker is given, it is used to count the events. The bytes should already be counted by
+/// the (compressed) input.
+fn parse_decompressed<R: Read>(
+    input: R,
+    limits: &ParseLimits,
+    tracker: Option<&mut Tracker>,
+) -> ParseResult<Evtc> {
+    let maximum = match limits.max_decompressed_size {
+        Some(maximum) => maximum,
+        None => return parse_tracked(BufReader::new(input), limits, tracker),
+    };
+    let mut input = BufReader::new(input.take(maximum));
+    let result = parse_tracked(&mut input, limits, tracker);
+    // If we have read everything that we were allowed to read, the result is only valid if the
+    // input actually ends there. Otherwise, we have just parsed a truncated version of the file
+    // (or failed to do so).
+    let input = input.into_inner();
+    if input.limit() == 0 && input.into_inner().read(&mut [0])? != 0 {
+        return Err(ParseError::LimitExceeded {
+            limit: Limit::DecompressedSize,
+            maximum,
+        });
+    }
+    result
+}
+
+fn parse_tracked<R: Read>(
+    input: R,
+    limits: &ParseLimits,
+    tracker: Option<&mut Tracker>,
+) -> ParseResult<Evtc> {
+    match tracker {
+        Some(tracker) => parser::parse_file_tracked(input, limits, tracker),
+        None => parser::parse_file_with_limits(input, limits),
+    }
+}
+
+/// Write a complete log, compressed as a zip file.
+///
+/// The resulting archive contains a single, deflate-compressed file, which is the same layout that
+/// arcdps uses for `.zevtc` files.
+pub fn write_zip<W: Write + Seek>(evtc: &Evtc, output: W) -> WriteResult<()> {
+    let mut archive = ZipWriter::new(output);
+    archive.start_file(ZIP_ENTRY_NAME, FileOptions::default())?;
+    write_buffered(evtc, &mut archive)?;
+    archive.finish()?;
+    Ok(())
+}
+
+/// Write the log through a buffer, making sure that the buffer is flushed afterwards.
+fn write_buffered<W: Write>(evtc: &Evtc, output: W) -> WriteResult<()> {
+    let mut buffered = BufWriter::new(output);
+    write_file(evtc, &mut buffered)?;
+    buffered.flush()?;
+    Ok(())
+}
+
+/// Parse a complete log that was compressed as a gzip stream.
+///
+/// Requires the `gzip` feature.
+#[cfg(feature = "gzip")]
+pub fn parse_gzip<R: Read>(input: R) -> ParseResult<Evtc> {
+    parse_gzip_with_limits(input, &ParseLimits::default())
+}
+
+/// Parse a complete log that was compressed as a gzip stream, respecting the given limits.
+///
+/// See [`ParseLimits`][ParseLimits] for more information. Requires the `gzip` feature.
+#[cfg(feature = "gzip")]
+pub fn parse_gzip_with_limits<R: Read>(input: R, limits: &ParseLimits) -> ParseResult<Evtc> {
+    parse_decompressed(flate2::read::MultiGzDecoder::new(input), limits, None)
+}
+
+/// Parse a complete log that was compressed as a gzip stream, reporting the progress to the
+/// given monitor.
+///
+/// The bytes read are counted in the compressed input. See [`ProgressMonitor`][ProgressMonitor]
+/// for more information.
+#[cfg(feature = "gzip")]
+pub fn parse_gzip_monitored<R: Read>(
+    input: R,
+    limits: &ParseLimits,
+    monitor: &ProgressMonitor,
+) -> ParseResult<Evtc> {
+    let mut tracker = monitor.start(Stage::Parsing);
+    let decoder = flate2::read::MultiGzDecoder::new(tracker.reader(input));
+    let evtc = parse_decompressed(decoder, limits, Some(&mut tracker))?;
+    tracker.check()?;
+    Ok(evtc)
+}
+
+/// Parse the header, agents and skills of a log that was compressed as a gzip stream.
+///
+/// Only the start of the stream has to be decompressed, which makes this much faster than
+/// [`parse_gzip`][parse_gzip]. Requires the `gzip` feature.
+#[cfg(feature = "gzip")]
+pub fn parse_partial_gzip<R: Read>(input: R) -> ParseResult<PartialEvtc> {
+    parser::parse_partial_file(BufReader::new(flate2::read::MultiGzDecoder::new(input)))
+}
+
+/// Write a complete log, compressed as a gzip stream.
+///
+/// Requires the `gzip` feature.
+#[cfg(feature = "gzip")]
+pub fn write_gzip<W: Write>(evtc: &Evtc, output: W) -> WriteResult<()> {
+    let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+    write_buffered(evtc, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/// Parse a complete log that was compressed as a zstd stream.
+///
+/// Requires the `zstd` feature.
+#[cfg(feature = "zstd")]
+pub fn parse_zstd<R: Read>(input: R) -> ParseResult<Evtc> {
+    parse_zstd_with_limits(input, &ParseLimits::default())
+}
+
+/// Parse a complete log that was compressed as a zstd stream, respecting the given limits.
+///
+/// See [`ParseLimits`][ParseLimits] for more information. Requires the `zstd` feature.
+#[cfg(feature = "zstd")]
+pub fn parse_zstd_with_limits<R: Read>(input: R, limits: &ParseLimits) -> ParseResult<Evtc> {
+    parse_decompressed(zstd::stream::read::Decoder::new(input)?, limits, None)
+}
+
+/// Parse a complete log that was compressed as a zstd stream, reporting the progress to the
+/// given monitor.
+///
+/// The bytes read are counted in the compressed input. See [`ProgressMonitor`][ProgressMonitor]
+/// for more information.
+#[cfg(feature = "zstd")]
+pub fn parse_zstd_monitored<R: Read>(
+    input: R,
+    limits: &ParseLimits,
+    monitor: &ProgressMonitor,
+) -> ParseResult<Evtc> {
+    let mut tracker = monitor.start(Stage::Parsing);
+    let decoder = zstd::stream::read::Decoder::new(tracker.reader(input))?;
+    let evtc = parse_decompressed(decoder, limits, Some(&mut tracker))?;
+    tracker.check()?;
+    Ok(evtc)
+}
+
+/// Parse the header, agents and skills of a log that was compressed as a zstd stream.
+///
+/// Only the start of the stream has to be decompressed, which makes this much faster than
+/// [`parse_zstd`][parse_zstd]. Requires the `zstd` feature.
+#[cfg(feature = "zstd")]
+pub fn parse_partial_zstd<R: Read>(input: R) -> ParseResult<PartialEvtc> {
+    parser::parse_partial_file(BufReader::new(zstd::stream::read::Decoder::new(input)?))
+}
+
+/// Write a complete log, compressed as a zstd stream.
+///
+/// The default compression level of zstd is used. Requires the `zstd` feature.
+#[cfg(feature = "zstd")]
+pub fn write_zstd<W: Write>(evtc: &Evtc, output: W) -> WriteResult<()> {
+    let mut encoder = zstd::stream::write::Encoder::new(output, 0)?;
+    write_buffered(evtc, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/// Parse a complete log that was compressed as a xz stream.
+///
+/// Requires the `xz` feature.
+#[cfg(feature = "xz")]
+pub fn parse_xz<R: Read>(input: R) -> ParseResult<Evtc> {
+    parse_xz_with_limits(input, &ParseLimits::default())
+}
+
+/// Parse a complete log that was compressed as a xz stream, respecting the given limits.
+///
+/// See [`ParseLimits`][ParseLimits] for more information. Requires the `xz` feature.
+#[cfg(feature = "xz")]
+pub fn parse_xz_with_limits<R: Read>(input: R, limits: &ParseLimits) -> ParseResult<Evtc> {
+    parse_decompressed(xz2::read::XzDecoder::new_multi_decoder(input), limits, None)
+}
+
+/// Parse a complete log that was compressed as a xz stream, reporting the progress to the
+/// given monitor.
+///
+/// The bytes read are counted in the compressed input. See [`ProgressMonitor`][ProgressMonitor]
+/// for more information.
+#[cfg(feature = "xz")]
+pub fn parse_xz_monitored<R: Read>(
+    input: R,
+    limits: &ParseLimits,
+    monitor: &ProgressMonitor,
+) -> ParseResult<Evtc> {
+    let mut tracker = monitor.start(Stage::Parsing);
+    let decoder = xz2::read::XzDecoder::new_multi_decoder(tracker.reader(input));
+    let evtc = parse_decompressed(decoder, limits, Some(&mut tracker))?;
+    tracker.check()?;
+    Ok(evtc)
+}
+
+/// Parse the header, agents and skills of a log that was compressed as a xz stream.
+///
+/// Only the start of the stream has to be decompressed, which makes this much faster than
+/// [`parse_xz`][parse_xz]. Requires the `xz` feature.
+#[cfg(feature = "xz")]
+pub fn parse_partial_xz<R: Read>(input: R) -> ParseResult<PartialEvtc> {
+    parser::parse_partial_file(BufReader::new(xz2::read::XzDecoder::new_multi_decoder(
+        input,
+    )))
+}
+
+/// Write a complete log, compressed as a xz stream.
+///
+/// The default preset (6) is used. Requires the `xz` feature.
+#[cfg(feature = "xz")]
+pub fn write_xz<W: Write>(evtc: &Evtc, output: W) -> WriteResult<()> {
+    let mut encoder = xz2::write::XzEncoder::new(output, 6);
+    write_buffered(evtc, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
 }
 
 /// Return a [`CStr`][CStr] up to the first nul byte.
@@ -39,10 +39,10 @@
 //!
 //! Compared to the "original" enum definitions, we also add
 //! [`IFF::None`](../enum.IFF.html) and
-//! [`CbtResult::None`](../enum.CbtResult.html). This makes parsing easier, as
-//! we can use those values instead of some other garbage. The other enums
-//! already have the `None` variant, and the corresponding byte is zeroed, so
-//! there's no problem with those.
+//! [`CbtResult::None`](../enum.CbtResult.html), which are used if the field
+//! is not used by the event (for example because its byte belongs to the
+//! [`StateChangeData`](../enum.StateChangeData.html)). Bytes that do not match
+//! any known value are kept in the `Other` variants of the enums.
 //!
 //! # Buffering
 //!
@@ -63,14 +63,22 @@
 //! raw file: cargo run --release  0.79s user 1.47s system 98% cpu 2.279 total
 //! ```
 //!
+//! If the complete (uncompressed) file is already in memory, or if you can memory map it, use
+//! [`parse_bytes`][parse_bytes] instead. It decodes the events directly from the buffer and avoids
+//! the overhead of the [`Read`][Read] trait altogether.
+//!
 //! # Resources
 //!
 //! * [evtc readme](https://www.deltaconnected.com/arcdps/evtc/README.txt)
 //! * [C++ output code](https://www.deltaconnected.com/arcdps/evtc/writeencounter.cpp)
 
-use byteorder::{LittleEndian, ReadBytesExt, LE};
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, LE};
 use num_traits::FromPrimitive;
-use std::io::{self, ErrorKind, Read};
+use std::convert::TryFrom;
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read},
+};
 use thiserror::Error;
 
 use super::*;
@@ -104,6 +112,10 @@ pub struct Evtc {
     pub skills: Vec<Skill>,
     /// The combat events.
     pub events: Vec<CbtEvent>,
+    /// The number of events that were skipped because their statechange is not known.
+    ///
+    /// As advised by arcdps, those events are not part of [`events`][Evtc::events].
+    pub unknown_statechanges: usize,
 }
 
 /// A partially-parsed EVTC file, containing everything but the events.
@@ -161,8 +173,106 @@ pub enum ParseError {
     /// The given ZIP archive is invalid.
     #[error("invalid archive: {0}")]
     InvalidZip(#[from] zip::result::ZipError),
+    /// An entry of a multi-log archive could not be parsed.
+    ///
+    /// See [`Archive`][super::Archive].
+    #[error("archive entry {name}: {source}")]
+    ArchiveEntry {
+        /// The name of the entry that failed.
+        name: String,
+        /// The error that occured while parsing the entry.
+        source: Box<ParseError>,
+    },
+    /// The file exceeds one of the configured [`ParseLimits`][ParseLimits].
+    #[error("{limit} limit exceeded (maximum is {maximum})")]
+    LimitExceeded {
+        /// The limit that was exceeded.
+        limit: Limit,
+        /// The configured maximum.
+        maximum: u64,
+    },
+    /// The parsing was cancelled through a [`CancellationToken`][super::CancellationToken].
+    #[error("parsing was cancelled")]
+    Cancelled,
+}
+
+/// The different limits that can be set in [`ParseLimits`][ParseLimits].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    /// The number of agents.
+    Agents,
+    /// The number of skills.
+    Skills,
+    /// The number of events.
+    Events,
+    /// The decompressed size of the file in bytes.
+    DecompressedSize,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Limit::Agents => "agent count",
+            Limit::Skills => "skill count",
+            Limit::Events => "event count",
+            Limit::DecompressedSize => "decompressed size",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Limits for the resources that parsing a file may use.
+///
+/// The counts in an evtc file are taken at face value by the parser, so a crafted file can make it
+/// do a lot of work (or allocate a lot of memory) with very little input. If you parse files from
+/// untrusted sources, you should set some limits:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use evtclib::raw::{self, ParseLimits};
+/// let limits = ParseLimits {
+///     max_agents: Some(10_000),
+///     max_skills: Some(10_000),
+///     max_events: Some(10_000_000),
+///     max_decompressed_size: Some(1024 * 1024 * 1024),
+/// };
+/// let file = std::fs::File::open("upload.zevtc")?;
+/// let evtc = raw::parse_zip_with_limits(std::io::BufReader::new(file), &limits)?;
+/// # Ok(()) }
+/// ```
+///
+/// A limit of `None` means that there is no limit, which is the default for all of them. If a
+/// limit is exceeded, parsing stops with [`ParseError::LimitExceeded`][ParseError::LimitExceeded].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseLimits {
+    /// The maximum number of agents.
+    pub max_agents: Option<u32>,
+    /// The maximum number of skills.
+    pub max_skills: Option<u32>,
+    /// The maximum number of events.
+    pub max_events: Option<u64>,
+    /// The maximum size of the decompressed file in bytes.
+    ///
+    /// This only applies to compressed files.
+    pub max_decompressed_size: Option<u64>,
 }
 
+impl ParseLimits {
+    /// Return an error if the given value exceeds the given limit.
+    pub(crate) fn check(limit: Limit, maximum: Option<u64>, value: u64) -> ParseResult<()> {
+        match maximum {
+            Some(maximum) if value > maximum => Err(ParseError::LimitExceeded { limit, maximum }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The maximum number of elements that are allocated up front based on a count in the file.
+///
+/// The counts cannot be trusted, so we only use them as a hint for the allocation, and let the
+/// vector grow as the elements are actually read.
+const MAX_PREALLOCATION: u32 = 1024;
+
 /// A type indicating the parse result.
 pub type ParseResult<T> = Result<T, ParseError>;
 
@@ -218,7 +328,7 @@ pub fn parse_header<R: Read>(mut input: R) -> ParseResult<Header> {
 /// * `input` - Input stream.
 /// * `count` - Number of agents (found in the header).
 pub fn parse_agents<R: Read>(mut input: R, count: u32) -> ParseResult<Vec<Agent>> {
-    let mut result = Vec::with_capacity(count as usize);
+    let mut result = Vec::with_capacity(count.min(MAX_PREALLOCATION) as usize);
     for _ in 0..count {
         result.push(parse_agent(&mut input)?);
     }
@@ -266,7 +376,7 @@ pub fn parse_agent<R: Read>(mut input: R) -> ParseResult<Agent> {
 /// * `input` - Input stream.
 /// * `count` - Number of skills to parse.
 pub fn parse_skills<R: Read>(mut input: R, count: u32) -> ParseResult<Vec<Skill>> {
-    let mut result = Vec::with_capacity(count as usize);
+    let mut result = Vec::with_capacity(count.min(MAX_PREALLOCATION) as usize);
     for _ in 0..count {
         result.push(parse_skill(&mut input)?);
     }
@@ -306,90 +416,233 @@ pub fn parse_skill<R: Read>(mut input: R) -> ParseResult<Skill> {
 ///
 /// If you use one of the higher-level functions, such as [`parse_file`][parse_file] or
 /// [`finish_parsing`][finish_parsing], you do not have to concern yourself with that detail.
+///
+/// If you do not want to keep all events in memory at once, use an [`EventReader`][EventReader]
+/// instead.
 pub fn parse_events<R: Read>(
-    mut input: R,
+    input: R,
     parser: fn(&mut R) -> ParseResult<CbtEvent>,
 ) -> ParseResult<Vec<CbtEvent>> {
-    let mut result = Vec::new();
-    loop {
-        let event = parser(&mut input);
-        match event {
-            Ok(x) => result.push(x),
-            Err(ParseError::UnknownStateChange(_)) => {
-                // Ignore unknown statechanges, as advised by arcdps.
-            }
-            Err(ParseError::Io(ref e)) if e.kind() == ErrorKind::UnexpectedEof => {
-                return Ok(result)
+    EventReader::with_parser(input, parser).collect()
+}
+
+/// An iterator that lazily parses combat events from a stream.
+///
+/// Unlike [`parse_events`][parse_events], this does not collect all events into a `Vec`, but
+/// instead parses them one by one, as they are requested. This keeps the memory usage low, even for
+/// very long logs.
+///
+/// The reader expects the stream to be positioned at the first event, which is the case after
+/// [`parse_partial_file`][parse_partial_file]:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use evtclib::raw::parser::{parse_partial_file, EventReader};
+/// let mut input = BufReader::new(File::open("the-log.evtc")?);
+/// let partial = parse_partial_file(&mut input)?;
+/// for event in EventReader::new(input, partial.header.revision)? {
+///     let event = event?;
+///     println!("{:?}", event);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Events with unknown statechanges are skipped (see
+/// [`unknown_statechanges`][EventReader::unknown_statechanges]), and the iterator ends at the end of
+/// the input, just like [`parse_events`][parse_events]. After an error has been returned, the
+/// iterator is fused and will not return any more events.
+pub struct EventReader<R> {
+    input: R,
+    parser: fn(&mut R) -> ParseResult<CbtEvent>,
+    filter: Option<Filtering>,
+    finished: bool,
+    unknown_statechanges: usize,
+}
+
+/// The state that an [`EventReader`][EventReader] needs to filter the events.
+struct Filtering {
+    filter: EventFilter,
+    revision: u8,
+    decode: EventDecoder,
+}
+
+impl Filtering {
+    /// Read the next record, and decode it if it matches the filter.
+    fn read<R: Read>(&self, input: &mut R) -> ParseResult<Option<CbtEvent>> {
+        let mut bytes = [0; EVENT_SIZE];
+        input.read_exact(&mut bytes)?;
+        if !self.filter.matches_bytes(&bytes, self.revision) {
+            return Ok(None);
+        }
+        (self.decode)(&bytes).map(Some)
+    }
+}
+
+impl<R: Read> EventReader<R> {
+    /// Create a new reader for events of the given revision.
+    ///
+    /// Returns [`ParseError::UnknownRevision`][ParseError::UnknownRevision] if the revision is not
+    /// known.
+    ///
+    /// * `input` - Input stream.
+    /// * `revision` - The revision of the file, as found in the header.
+    #[allow(clippy::redundant_closure)]
+    pub fn new(input: R, revision: u8) -> ParseResult<Self> {
+        // The following closures seem redundant, but they are needed to convice Rust that we can
+        // actually use parse_event_rev* here. That is because we require a lifetime bound of
+        //   for<'r> fn(&'r mut R) -> ParseResult
+        // which we cannot get by just plugging in parse_event_rev*.
+        let parser: fn(&mut R) -> ParseResult<CbtEvent> = match revision {
+            0 => |r| parse_event_rev0(r),
+            1 => |r| parse_event_rev1(r),
+            x => return Err(ParseError::UnknownRevision(x)),
+        };
+        Ok(Self::with_parser(input, parser))
+    }
+
+    /// Create a new reader that uses the given function to parse single events.
+    ///
+    /// See [`parse_events`][parse_events] for the requirements of `parser`.
+    ///
+    /// * `input` - Input stream.
+    /// * `parser` - The parse function to use.
+    pub fn with_parser(input: R, parser: fn(&mut R) -> ParseResult<CbtEvent>) -> Self {
+        EventReader {
+            input,
+            parser,
+            filter: None,
+            finished: false,
+            unknown_statechanges: 0,
+        }
+    }
+
+    /// Create a new reader for events of the given revision, which skips events that do not
+    /// match the given filter.
+    ///
+    /// The filter is checked before the events are decoded, see [`EventFilter`][EventFilter].
+    ///
+    /// * `input` - Input stream.
+    /// * `revision` - The revision of the file, as found in the header.
+    /// * `filter` - The filter that the events have to match.
+    pub fn with_filter(input: R, revision: u8, filter: EventFilter) -> ParseResult<Self> {
+        let mut reader = Self::new(input, revision)?;
+        reader.filter = Some(Filtering {
+            filter,
+            revision,
+            decode: event_decoder(revision)?,
+        });
+        Ok(reader)
+    }
+
+    /// Return the number of events that have been skipped so far because their statechange is
+    /// not known.
+    pub fn unknown_statechanges(&self) -> usize {
+        self.unknown_statechanges
+    }
+
+    /// Return the underlying input stream.
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = ParseResult<CbtEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            let result = match self.filter {
+                Some(ref filtering) => filtering.read(&mut self.input),
+                None => (self.parser)(&mut self.input).map(Some),
+            };
+            match result {
+                Ok(Some(x)) => return Some(Ok(x)),
+                Ok(None) => {
+                    // Skipped by the filter.
+                }
+                Err(ParseError::UnknownStateChange(_)) => {
+                    // Ignore unknown statechanges, as advised by arcdps.
+                    self.unknown_statechanges += 1;
+                }
+                Err(ParseError::Io(ref e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    self.finished = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
             }
-            Err(e) => return Err(e),
         }
     }
 }
 
+impl<R: Read> std::iter::FusedIterator for EventReader<R> {}
+
+/// The size of the file header in bytes.
+pub(crate) const HEADER_SIZE: usize = 20;
+
+/// The size of a single agent in bytes.
+pub(crate) const AGENT_SIZE: usize = 96;
+
+/// The size of a single skill in bytes.
+pub(crate) const SKILL_SIZE: usize = 68;
+
+/// The size of a single combat event in bytes.
+///
+/// This is the same for both revision 0 and revision 1, as arcdps kept the struct size when
+/// changing the layout.
+pub const EVENT_SIZE: usize = 64;
+
 /// Parse a single combat event.
 ///
 /// This works for old combat events, i.e. files with revision == 0.
 ///
 /// * `input` - Input stream.
 pub fn parse_event_rev0<R: Read>(mut input: R) -> ParseResult<CbtEvent> {
-    let time = input.read_u64::<LittleEndian>()?;
-    let src_agent = input.read_u64::<LE>()?;
-    let dst_agent = input.read_u64::<LE>()?;
-    let value = input.read_i32::<LE>()?;
-    let buff_dmg = input.read_i32::<LE>()?;
-    let overstack_value = input.read_u16::<LE>()? as u32;
-    let skillid = input.read_u16::<LE>()? as u32;
-    let src_instid = input.read_u16::<LE>()?;
-    let dst_instid = input.read_u16::<LE>()?;
-    let src_master_instid = input.read_u16::<LE>()?;
-
-    // We can skip 9 bytes of internal tracking garbage.
-    let mut skip = [0; 9];
-    input.read_exact(&mut skip)?;
+    let mut bytes = [0; EVENT_SIZE];
+    input.read_exact(&mut bytes)?;
+    parse_event_rev0_bytes(&bytes)
+}
 
-    let iff = IFF::from_u8(input.read_u8()?).unwrap_or(IFF::None);
-    let buff = input.read_u8()?;
-    let result = CbtResult::from_u8(input.read_u8()?).unwrap_or(CbtResult::None);
-    let is_activation = CbtActivation::from_u8(input.read_u8()?).unwrap_or(CbtActivation::None);
-    let is_buffremove = CbtBuffRemove::from_u8(input.read_u8()?).unwrap_or(CbtBuffRemove::None);
-    let is_ninety = input.read_u8()? != 0;
-    let is_fifty = input.read_u8()? != 0;
-    let is_moving = input.read_u8()? != 0;
-    let statechange = input.read_u8()?;
+/// Parse a single combat event from its raw bytes.
+///
+/// This works for old combat events, i.e. files with revision == 0.
+///
+/// * `bytes` - The bytes of the event.
+pub fn parse_event_rev0_bytes(bytes: &[u8; EVENT_SIZE]) -> ParseResult<CbtEvent> {
+    // We can skip 9 bytes of internal tracking garbage (42..51) and two more bytes at the end
+    // (62..64).
+    let statechange = bytes[59];
     let is_statechange =
-        CbtStateChange::from_u8(statechange).ok_or(ParseError::UnknownStateChange(statechange));
-    let is_flanking = input.read_u8()? != 0;
-    let is_shields = input.read_u8()? != 0;
-
-    // Two more bytes of internal tracking garbage.
-    input.read_u16::<LE>()?;
-
-    Ok(CbtEvent {
-        time,
-        src_agent,
-        dst_agent,
-        value,
-        buff_dmg,
-        overstack_value,
-        skillid,
-        src_instid,
-        dst_instid,
-        src_master_instid,
-        dst_master_instid: 0,
-        iff,
-        buff,
-        result,
-        is_activation,
-        is_buffremove,
-        is_ninety,
-        is_fifty,
-        is_moving,
-        is_statechange: is_statechange?,
-        is_flanking,
-        is_shields,
-        is_offcycle: false,
-        padding_end: 0,
-    })
+        CbtStateChange::from_u8(statechange).ok_or(ParseError::UnknownStateChange(statechange))?;
+    let mut flags = [0; 12];
+    flags[..11].copy_from_slice(&bytes[51..62]);
+
+    let mut event = CbtEvent {
+        time: LE::read_u64(&bytes[0..8]),
+        src_agent: LE::read_u64(&bytes[8..16]),
+        dst_agent: LE::read_u64(&bytes[16..24]),
+        value: LE::read_i32(&bytes[24..28]),
+        buff_dmg: LE::read_i32(&bytes[28..32]),
+        overstack_value: LE::read_u16(&bytes[32..34]) as u32,
+        skillid: LE::read_u16(&bytes[34..36]) as u32,
+        src_instid: LE::read_u16(&bytes[36..38]),
+        dst_instid: LE::read_u16(&bytes[38..40]),
+        src_master_instid: LE::read_u16(&bytes[40..42]),
+        is_statechange,
+        ..Default::default()
+    };
+    decode_flags(&mut event, &flags);
+    // Revision 0 events have no is_offcycle field.
+    event.is_offcycle = false;
+    Ok(event)
 }
 
 /// Parse a single combat event.
@@ -398,72 +651,223 @@ pub fn parse_event_rev0<R: Read>(mut input: R) -> ParseResult<CbtEvent> {
 ///
 /// * `input` - Input stream.
 pub fn parse_event_rev1<R: Read>(mut input: R) -> ParseResult<CbtEvent> {
-    let time = input.read_u64::<LittleEndian>()?;
-    let src_agent = input.read_u64::<LE>()?;
-    let dst_agent = input.read_u64::<LE>()?;
-    let value = input.read_i32::<LE>()?;
-    let buff_dmg = input.read_i32::<LE>()?;
-    let overstack_value = input.read_u32::<LE>()?;
-    let skillid = input.read_u32::<LE>()?;
-    let src_instid = input.read_u16::<LE>()?;
-    let dst_instid = input.read_u16::<LE>()?;
-    let src_master_instid = input.read_u16::<LE>()?;
-    let dst_master_instid = input.read_u16::<LE>()?;
-
-    let iff = IFF::from_u8(input.read_u8()?).unwrap_or(IFF::None);
-    let buff = input.read_u8()?;
-    let result = CbtResult::from_u8(input.read_u8()?).unwrap_or(CbtResult::None);
-    let is_activation = CbtActivation::from_u8(input.read_u8()?).unwrap_or(CbtActivation::None);
-    let is_buffremove = CbtBuffRemove::from_u8(input.read_u8()?).unwrap_or(CbtBuffRemove::None);
-    let is_ninety = input.read_u8()? != 0;
-    let is_fifty = input.read_u8()? != 0;
-    let is_moving = input.read_u8()? != 0;
-    let statechange = input.read_u8()?;
+    let mut bytes = [0; EVENT_SIZE];
+    input.read_exact(&mut bytes)?;
+    parse_event_rev1_bytes(&bytes)
+}
+
+/// Parse a single combat event from its raw bytes.
+///
+/// This works for new combat events, i.e. files with revision == 1.
+///
+/// * `bytes` - The bytes of the event.
+pub fn parse_event_rev1_bytes(bytes: &[u8; EVENT_SIZE]) -> ParseResult<CbtEvent> {
+    let statechange = bytes[56];
     let is_statechange =
-        CbtStateChange::from_u8(statechange).ok_or(ParseError::UnknownStateChange(statechange));
-    let is_flanking = input.read_u8()? != 0;
-    let is_shields = input.read_u8()? != 0;
-    let is_offcycle = input.read_u8()? != 0;
-
-    // Should only be padding in most cases, but could also be useful for some events (like
-    // STACKRESET).
-    let padding_end = input.read_u32::<LE>()?;
-
-    Ok(CbtEvent {
-        time,
-        src_agent,
-        dst_agent,
-        value,
-        buff_dmg,
-        overstack_value,
-        skillid,
-        src_instid,
-        dst_instid,
-        src_master_instid,
-        dst_master_instid,
-        iff,
-        buff,
-        result,
-        is_activation,
-        is_buffremove,
-        is_ninety,
-        is_fifty,
-        is_moving,
-        is_statechange: is_statechange?,
-        is_flanking,
-        is_shields,
-        is_offcycle,
-        padding_end,
-    })
+        CbtStateChange::from_u8(statechange).ok_or(ParseError::UnknownStateChange(statechange))?;
+    let mut flags = [0; 12];
+    flags.copy_from_slice(&bytes[48..60]);
+
+    let mut event = CbtEvent {
+        time: LE::read_u64(&bytes[0..8]),
+        src_agent: LE::read_u64(&bytes[8..16]),
+        dst_agent: LE::read_u64(&bytes[16..24]),
+        value: LE::read_i32(&bytes[24..28]),
+        buff_dmg: LE::read_i32(&bytes[28..32]),
+        overstack_value: LE::read_u32(&bytes[32..36]),
+        skillid: LE::read_u32(&bytes[36..40]),
+        src_instid: LE::read_u16(&bytes[40..42]),
+        dst_instid: LE::read_u16(&bytes[42..44]),
+        src_master_instid: LE::read_u16(&bytes[44..46]),
+        dst_master_instid: LE::read_u16(&bytes[46..48]),
+        is_statechange,
+        // Should only be padding in most cases, but could also be useful for some events (like
+        // STACKRESET).
+        padding_end: LE::read_u32(&bytes[60..64]),
+        ..Default::default()
+    };
+    decode_flags(&mut event, &flags);
+    Ok(event)
+}
+
+/// Decode the bytes of the fields `iff` through `is_offcycle` into the given event.
+///
+/// The bytes that belong to the [statechange specific data][StateChangeData] are not decoded into
+/// the typed fields, which keep their default values.
+fn decode_flags(event: &mut CbtEvent, flags: &[u8; 12]) {
+    let word = |start: usize| LE::read_u32(&flags[start..start + 4]);
+    let data = match event.is_statechange {
+        CbtStateChange::Effect => StateChangeData::Effect {
+            orientation: [word(0), word(4)],
+            duration: LE::read_u16(&flags[10..12]),
+        },
+        CbtStateChange::Effect2 => StateChangeData::Effect2 {
+            duration: word(0),
+            tracking_id: word(4),
+            orientation_x: LE::read_i16(&flags[10..12]),
+        },
+        CbtStateChange::BuffInfo => StateChangeData::BuffInfo {
+            category: flags[11],
+        },
+        _ => StateChangeData::None,
+    };
+    let covered = data.covered_flags();
+    let flag = |index: usize| {
+        if covered.contains(&index) {
+            None
+        } else {
+            Some(flags[index])
+        }
+    };
+
+    event.statechange_data = data;
+    event.iff = flag(0).map(IFF::from).unwrap_or_default();
+    event.buff = flag(1).unwrap_or(0);
+    event.result = flag(2).map(CbtResult::from).unwrap_or_default();
+    event.is_activation = flag(3).map(CbtActivation::from).unwrap_or_default();
+    event.is_buffremove = flag(4).map(CbtBuffRemove::from).unwrap_or_default();
+    event.is_ninety = flag(5).is_some_and(|b| b != 0);
+    event.is_fifty = flag(6).is_some_and(|b| b != 0);
+    event.is_moving = flag(7).is_some_and(|b| b != 0);
+    event.is_flanking = flag(9).is_some_and(|b| b != 0);
+    event.is_shields = flag(10).is_some_and(|b| b != 0);
+    event.is_offcycle = flag(11).is_some_and(|b| b != 0);
+}
+
+/// A function that decodes a single event record.
+pub(crate) type EventDecoder = fn(&[u8; EVENT_SIZE]) -> ParseResult<CbtEvent>;
+
+/// Return the function that decodes events of the given revision.
+pub(crate) fn event_decoder(revision: u8) -> ParseResult<EventDecoder> {
+    match revision {
+        0 => Ok(parse_event_rev0_bytes),
+        1 => Ok(parse_event_rev1_bytes),
+        x => Err(ParseError::UnknownRevision(x)),
+    }
+}
+
+/// Parse all combat events from an in-memory buffer.
+///
+/// This is the slice-based counterpart to [`parse_events`][parse_events]. As the events are stored
+/// as fixed-size records, they can be decoded directly from the buffer, without going through a
+/// [`Read`][Read] implementation.
+///
+/// Events with unknown statechanges are skipped. Trailing bytes that do not make up a complete
+/// event are ignored, the same way that [`parse_events`][parse_events] stops at the end of the
+/// file.
+///
+/// * `data` - The event section of the file.
+/// * `revision` - The revision of the file, as found in the header.
+pub fn parse_events_bytes(data: &[u8], revision: u8) -> ParseResult<Vec<CbtEvent>> {
+    parse_events_bytes_with(data, revision, None, &ParseLimits::default()).map(|(events, _)| events)
+}
+
+/// Parse the combat events from an in-memory buffer, skipping events that do not match the given
+/// filter.
+///
+/// See [`parse_events_bytes`][parse_events_bytes] and [`EventFilter`][EventFilter].
+///
+/// * `data` - The event section of the file.
+/// * `revision` - The revision of the file, as found in the header.
+/// * `filter` - The filter that the events have to match.
+pub fn parse_events_bytes_filtered(
+    data: &[u8],
+    revision: u8,
+    filter: &EventFilter,
+) -> ParseResult<Vec<CbtEvent>> {
+    parse_events_bytes_with(data, revision, Some(filter), &ParseLimits::default())
+        .map(|(events, _)| events)
+}
+
+/// Parse the combat events from an in-memory buffer, and count the skipped events with unknown
+/// statechanges.
+fn parse_events_bytes_with(
+    data: &[u8],
+    revision: u8,
+    filter: Option<&EventFilter>,
+    limits: &ParseLimits,
+) -> ParseResult<(Vec<CbtEvent>, usize)> {
+    let parser = event_decoder(revision)?;
+    let mut result = Vec::with_capacity(data.len() / EVENT_SIZE);
+    let mut unknown_statechanges = 0;
+    for chunk in data.chunks_exact(EVENT_SIZE) {
+        let bytes = <&[u8; EVENT_SIZE]>::try_from(chunk).expect("chunk has the wrong size");
+        if let Some(filter) = filter {
+            if !filter.matches_bytes(bytes, revision) {
+                continue;
+            }
+        }
+        match parser(bytes) {
+            Ok(x) => {
+                result.push(x);
+                ParseLimits::check(Limit::Events, limits.max_events, result.len() as u64)?;
+            }
+            Err(ParseError::UnknownStateChange(_)) => {
+                // Ignore unknown statechanges, as advised by arcdps.
+                unknown_statechanges += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((result, unknown_statechanges))
+}
+
+/// Parse all combat events from an in-memory buffer in parallel.
+///
+/// This is the parallel version of [`parse_events_bytes`][parse_events_bytes], which splits the
+/// events into chunks and decodes them on the [rayon](https://docs.rs/rayon) thread pool. The
+/// result is the same, including the order of the events. Requires the `rayon` feature.
+///
+/// * `data` - The event section of the file.
+/// * `revision` - The revision of the file, as found in the header.
+#[cfg(feature = "rayon")]
+pub fn parse_events_bytes_parallel(data: &[u8], revision: u8) -> ParseResult<Vec<CbtEvent>> {
+    use rayon::prelude::*;
+
+    let parser = event_decoder(revision)?;
+    data.par_chunks_exact(EVENT_SIZE)
+        .filter_map(|chunk| {
+            let bytes = <&[u8; EVENT_SIZE]>::try_from(chunk).expect("chunk has the wrong size");
+            match parser(bytes) {
+                Ok(x) => Some(Ok(x)),
+                // Ignore unknown statechanges, as advised by arcdps.
+                Err(ParseError::UnknownStateChange(_)) => None,
+                Err(e) => Some(Err(e)),
+            }
+        })
+        .collect()
 }
 
 /// Parse a partial EVTC file.
 ///
 /// * `input` - Input stream.
-pub fn parse_partial_file<R: Read>(mut input: R) -> ParseResult<PartialEvtc> {
+pub fn parse_partial_file<R: Read>(input: R) -> ParseResult<PartialEvtc> {
+    parse_partial_file_with_limits(input, &ParseLimits::default())
+}
+
+/// Parse a partial EVTC file, respecting the given limits.
+///
+/// The agent and skill counts are checked before the agents and skills are read.
+///
+/// * `input` - Input stream.
+/// * `limits` - The limits to enforce.
+pub fn parse_partial_file_with_limits<R: Read>(
+    mut input: R,
+    limits: &ParseLimits,
+) -> ParseResult<PartialEvtc> {
     let header = parse_header(&mut input)?;
+    ParseLimits::check(
+        Limit::Agents,
+        limits.max_agents.map(Into::into),
+        header.agent_count.into(),
+    )?;
     let agents = parse_agents(&mut input, header.agent_count)?;
     let skill_count = input.read_u32::<LittleEndian>()?;
+    ParseLimits::check(
+        Limit::Skills,
+        limits.max_skills.map(Into::into),
+        skill_count.into(),
+    )?;
     let skills = parse_skills(input, skill_count)?;
 
     Ok(PartialEvtc {
@@ -478,17 +882,128 @@ pub fn parse_partial_file<R: Read>(mut input: R) -> ParseResult<PartialEvtc> {
 ///
 /// * `partial` - The partial EVTC.
 /// * `input` - The input stream.
-#[allow(clippy::redundant_closure)]
 pub fn finish_parsing<R: Read>(partial: PartialEvtc, input: R) -> ParseResult<Evtc> {
-    // The following closures seem redundant, but they are needed to convice Rust that we can
-    // actually use parse_event_rev* here. That is because we require a lifetime bound of
-    //   for<'r> fn(&'r mut R) -> ParseResult
-    // which we cannot get by just plugging in parse_event_rev*.
-    let events = match partial.header.revision {
-        0 => parse_events(input, |r| parse_event_rev0(r))?,
-        1 => parse_events(input, |r| parse_event_rev1(r))?,
-        x => return Err(ParseError::UnknownRevision(x)),
-    };
+    finish_parsing_with_limits(partial, input, &ParseLimits::default())
+}
+
+/// Finish a partial EVTC by reading the events, respecting the given limits.
+///
+/// * `partial` - The partial EVTC.
+/// * `input` - The input stream.
+/// * `limits` - The limits to enforce.
+pub fn finish_parsing_with_limits<R: Read>(
+    partial: PartialEvtc,
+    input: R,
+    limits: &ParseLimits,
+) -> ParseResult<Evtc> {
+    let mut events = Vec::new();
+    let mut reader = EventReader::new(input, partial.header.revision)?;
+    for event in &mut reader {
+        events.push(event?);
+        ParseLimits::check(Limit::Events, limits.max_events, events.len() as u64)?;
+    }
+
+    Ok(Evtc {
+        header: partial.header,
+        skill_count: partial.skill_count,
+        agents: partial.agents,
+        skills: partial.skills,
+        events,
+        unknown_statechanges: reader.unknown_statechanges(),
+    })
+}
+
+/// Finish a partial EVTC by reading the events, reporting the progress to the given monitor.
+///
+/// The bytes read are counted from the given input, starting at the size of the header, the
+/// agents and the skills. See [`ProgressMonitor`][ProgressMonitor] for more information.
+///
+/// * `partial` - The partial EVTC.
+/// * `input` - The input stream.
+/// * `limits` - The limits that should be enforced.
+/// * `monitor` - The monitor that receives the progress.
+pub fn finish_parsing_monitored<R: Read>(
+    partial: PartialEvtc,
+    input: R,
+    limits: &ParseLimits,
+    monitor: &ProgressMonitor,
+) -> ParseResult<Evtc> {
+    let mut tracker = monitor.start(Stage::Parsing);
+    tracker.set_position(
+        (HEADER_SIZE + partial.agents.len() * AGENT_SIZE + 4 + partial.skills.len() * SKILL_SIZE)
+            as u64,
+    );
+    tracker.check()?;
+    let input = tracker.reader(input);
+    let (events, unknown_statechanges) =
+        read_events_tracked(input, partial.header.revision, limits, &mut tracker)?;
+    tracker.check()?;
+
+    Ok(Evtc {
+        header: partial.header,
+        skill_count: partial.skill_count,
+        agents: partial.agents,
+        skills: partial.skills,
+        events,
+        unknown_statechanges,
+    })
+}
+
+/// Read all events, counting them with the given tracker.
+///
+/// Returns the events and the number of skipped events with unknown statechanges.
+fn read_events_tracked<R: Read>(
+    input: R,
+    revision: u8,
+    limits: &ParseLimits,
+    tracker: &mut Tracker,
+) -> ParseResult<(Vec<CbtEvent>, usize)> {
+    let mut events = Vec::new();
+    let mut reader = EventReader::new(input, revision)?;
+    for event in &mut reader {
+        events.push(event?);
+        ParseLimits::check(Limit::Events, limits.max_events, events.len() as u64)?;
+        tracker.event_decoded()?;
+    }
+    Ok((events, reader.unknown_statechanges()))
+}
+
+/// Finish a partial EVTC by reading the events that match the given filter.
+///
+/// See [`EventFilter`][EventFilter] for more information.
+///
+/// * `partial` - The partial EVTC.
+/// * `input` - The input stream.
+/// * `filter` - The filter that the events have to match.
+pub fn finish_parsing_filtered<R: Read>(
+    partial: PartialEvtc,
+    input: R,
+    filter: &EventFilter,
+) -> ParseResult<Evtc> {
+    finish_parsing_filtered_with_limits(partial, input, filter, &ParseLimits::default())
+}
+
+/// Finish a partial EVTC by reading the events that match the given filter, respecting the given
+/// limits.
+///
+/// Only the events that match the filter count towards the event limit.
+///
+/// * `partial` - The partial EVTC.
+/// * `input` - The input stream.
+/// * `filter` - The filter that the events have to match.
+/// * `limits` - The limits to enforce.
+pub fn finish_parsing_filtered_with_limits<R: Read>(
+    partial: PartialEvtc,
+    input: R,
+    filter: &EventFilter,
+    limits: &ParseLimits,
+) -> ParseResult<Evtc> {
+    let mut events = Vec::new();
+    let mut reader = EventReader::with_filter(input, partial.header.revision, filter.clone())?;
+    for event in &mut reader {
+        events.push(event?);
+        ParseLimits::check(Limit::Events, limits.max_events, events.len() as u64)?;
+    }
 
     Ok(Evtc {
         header: partial.header,
@@ -496,13 +1011,215 @@ pub fn finish_parsing<R: Read>(partial: PartialEvtc, input: R) -> ParseResult<Ev
         agents: partial.agents,
         skills: partial.skills,
         events,
+        unknown_statechanges: reader.unknown_statechanges(),
     })
 }
 
 /// Parse a complete EVTC file.
 ///
 /// * `input` - Input stream.
-pub fn parse_file<R: Read>(mut input: R) -> ParseResult<Evtc> {
-    let partial = parse_partial_file(&mut input)?;
-    finish_parsing(partial, input)
+pub fn parse_file<R: Read>(input: R) -> ParseResult<Evtc> {
+    parse_file_with_limits(input, &ParseLimits::default())
+}
+
+/// Parse a complete EVTC file, respecting the given limits.
+///
+/// See [`ParseLimits`][ParseLimits] for more information.
+///
+/// * `input` - Input stream.
+/// * `limits` - The limits to enforce.
+pub fn parse_file_with_limits<R: Read>(mut input: R, limits: &ParseLimits) -> ParseResult<Evtc> {
+    let partial = parse_partial_file_with_limits(&mut input, limits)?;
+    finish_parsing_with_limits(partial, input, limits)
+}
+
+/// Parse a complete EVTC file, reporting the progress to the given monitor.
+///
+/// See [`ProgressMonitor`][ProgressMonitor] for more information. Since the input is not
+/// seekable, the total size of the input is only known if it has been set with
+/// [`ProgressMonitor::total_bytes`][ProgressMonitor::total_bytes].
+///
+/// * `input` - Input stream.
+/// * `limits` - The limits that should be enforced.
+/// * `monitor` - The monitor that receives the progress.
+pub fn parse_file_monitored<R: Read>(
+    input: R,
+    limits: &ParseLimits,
+    monitor: &ProgressMonitor,
+) -> ParseResult<Evtc> {
+    let mut tracker = monitor.start(Stage::Parsing);
+    let evtc = parse_file_tracked(tracker.reader(input), limits, &mut tracker)?;
+    tracker.check()?;
+    Ok(evtc)
+}
+
+/// Parse a complete EVTC file, counting the events with the given tracker.
+///
+/// The input is not wrapped, so that the caller can decide which bytes should be counted (e.g.
+/// the compressed ones).
+pub(crate) fn parse_file_tracked<R: Read>(
+    mut input: R,
+    limits: &ParseLimits,
+    tracker: &mut Tracker,
+) -> ParseResult<Evtc> {
+    let partial = parse_partial_file_with_limits(&mut input, limits)?;
+    tracker.check()?;
+    let (events, unknown_statechanges) =
+        read_events_tracked(input, partial.header.revision, limits, tracker)?;
+
+    Ok(Evtc {
+        header: partial.header,
+        skill_count: partial.skill_count,
+        agents: partial.agents,
+        skills: partial.skills,
+        events,
+        unknown_statechanges,
+    })
+}
+
+/// Parse a complete EVTC file, but only keep the events that match the given filter.
+///
+/// See [`EventFilter`][EventFilter] for more information.
+///
+/// * `input` - Input stream.
+/// * `filter` - The filter that the events have to match.
+pub fn parse_file_filtered<R: Read>(input: R, filter: &EventFilter) -> ParseResult<Evtc> {
+    parse_file_filtered_with_limits(input, filter, &ParseLimits::default())
+}
+
+/// Parse a complete EVTC file, but only keep the events that match the given filter, respecting
+/// the given limits.
+///
+/// See [`parse_file_filtered`][parse_file_filtered] and [`ParseLimits`][ParseLimits].
+///
+/// * `input` - Input stream.
+/// * `filter` - The filter that the events have to match.
+/// * `limits` - The limits to enforce.
+pub fn parse_file_filtered_with_limits<R: Read>(
+    mut input: R,
+    filter: &EventFilter,
+    limits: &ParseLimits,
+) -> ParseResult<Evtc> {
+    let partial = parse_partial_file_with_limits(&mut input, limits)?;
+    finish_parsing_filtered_with_limits(partial, input, filter, limits)
+}
+
+/// Parse a complete EVTC file from an in-memory buffer.
+///
+/// This is faster than [`parse_file`][parse_file] if you already have the complete (uncompressed)
+/// file in memory, as the events are decoded directly from the buffer. This also works with
+/// memory-mapped files, as those can be used as a `&[u8]` as well:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data = std::fs::read("the-log.evtc")?;
+/// let evtc = evtclib::raw::parse_bytes(&data)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// * `data` - The file content.
+pub fn parse_bytes(data: &[u8]) -> ParseResult<Evtc> {
+    parse_bytes_with_limits(data, &ParseLimits::default())
+}
+
+/// Parse a complete EVTC file from an in-memory buffer, respecting the given limits.
+///
+/// See [`parse_bytes`][parse_bytes] and [`ParseLimits`][ParseLimits].
+///
+/// * `data` - The file content.
+/// * `limits` - The limits to enforce.
+pub fn parse_bytes_with_limits(data: &[u8], limits: &ParseLimits) -> ParseResult<Evtc> {
+    let mut input = data;
+    let partial = parse_partial_file_with_limits(&mut input, limits)?;
+    let (events, unknown_statechanges) =
+        parse_events_bytes_with(input, partial.header.revision, None, limits)?;
+
+    Ok(Evtc {
+        header: partial.header,
+        skill_count: partial.skill_count,
+        agents: partial.agents,
+        skills: partial.skills,
+        events,
+        unknown_statechanges,
+    })
+}
+
+/// Parse a complete EVTC file from an in-memory buffer, but only keep the events that match the
+/// given filter.
+///
+/// See [`parse_bytes`][parse_bytes] and [`EventFilter`][EventFilter].
+///
+/// * `data` - The file content.
+/// * `filter` - The filter that the events have to match.
+pub fn parse_bytes_filtered(data: &[u8], filter: &EventFilter) -> ParseResult<Evtc> {
+    parse_bytes_filtered_with_limits(data, filter, &ParseLimits::default())
+}
+
+/// Parse a complete EVTC file from an in-memory buffer, but only keep the events that match the
+/// given filter, respecting the given limits.
+///
+/// Only the events that match the filter count towards the event limit.
+///
+/// * `data` - The file content.
+/// * `filter` - The filter that the events have to match.
+/// * `limits` - The limits to enforce.
+pub fn parse_bytes_filtered_with_limits(
+    data: &[u8],
+    filter: &EventFilter,
+    limits: &ParseLimits,
+) -> ParseResult<Evtc> {
+    let mut input = data;
+    let partial = parse_partial_file_with_limits(&mut input, limits)?;
+    let (events, unknown_statechanges) =
+        parse_events_bytes_with(input, partial.header.revision, Some(filter), limits)?;
+
+    Ok(Evtc {
+        header: partial.header,
+        skill_count: partial.skill_count,
+        agents: partial.agents,
+        skills: partial.skills,
+        events,
+        unknown_statechanges,
+    })
+}
+
+/// Parse a complete EVTC file from an in-memory buffer, decoding the events in parallel.
+///
+/// See [`parse_bytes`][parse_bytes] and [`parse_events_bytes_parallel`][parse_events_bytes_parallel].
+/// This pays off for large logs (such as WvW logs), for small logs the overhead of distributing
+/// the work can outweigh the gains. Requires the `rayon` feature.
+///
+/// * `data` - The file content.
+#[cfg(feature = "rayon")]
+pub fn parse_bytes_parallel(data: &[u8]) -> ParseResult<Evtc> {
+    parse_bytes_parallel_with_limits(data, &ParseLimits::default())
+}
+
+/// Parse a complete EVTC file from an in-memory buffer, decoding the events in parallel and
+/// respecting the given limits.
+///
+/// See [`parse_bytes_parallel`][parse_bytes_parallel] and [`ParseLimits`][ParseLimits]. As the
+/// events are decoded in parallel, the event limit is only checked after all of them have been
+/// decoded. Requires the `rayon` feature.
+///
+/// * `data` - The file content.
+/// * `limits` - The limits to enforce.
+#[cfg(feature = "rayon")]
+pub fn parse_bytes_parallel_with_limits(data: &[u8], limits: &ParseLimits) -> ParseResult<Evtc> {
+    let mut input = data;
+    let partial = parse_partial_file_with_limits(&mut input, limits)?;
+    let events = parse_events_bytes_parallel(input, partial.header.revision)?;
+    ParseLimits::check(Limit::Events, limits.max_events, events.len() as u64)?;
+    // Without a filter, every complete record that was not decoded has an unknown statechange.
+    let unknown_statechanges = input.len() / EVENT_SIZE - events.len();
+
+    Ok(Evtc {
+        header: partial.header,
+        skill_count: partial.skill_count,
+        agents: partial.agents,
+        skills: partial.skills,
+        events,
+        unknown_statechanges,
+    })
 }
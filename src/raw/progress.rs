@@ -0,0 +1,323 @@
+//! Progress reporting and cancellation for long-running parses.
+//!
+//! Parsing and processing a big log (especially a WvW log) can take a few seconds. A
+//! [`ProgressMonitor`][ProgressMonitor] can be passed to the `*_monitored` parsing functions (or
+//! to [`ProcessingOptions::monitor`][crate::ProcessingOptions::monitor]) to get periodic
+//! [`Progress`][Progress] updates, and to cancel the parsing through a
+//! [`CancellationToken`][CancellationToken]:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::fs::File;
+//! use std::io::BufReader;
+//! use evtclib::raw::{self, CancellationToken, ProgressMonitor};
+//! let token = CancellationToken::new();
+//! // Give a clone of the token to the cancel button.
+//! let monitor = ProgressMonitor::new()
+//!     .on_progress(|progress| println!("{:?}", progress.fraction()))
+//!     .cancellation_token(token.clone());
+//! let input = BufReader::new(File::open("logfile.zevtc")?);
+//! let evtc = raw::parse_zip_monitored(input, &raw::ParseLimits::default(), &monitor)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! If the token is cancelled, the parsing stops with [`ParseError::Cancelled`][ParseError::Cancelled]
+//! the next time that the progress is checked.
+use std::{
+    cell::Cell,
+    fmt,
+    io::{self, Read, Seek, SeekFrom},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use super::{ParseError, ParseResult};
+
+/// The number of events between two progress checks.
+const PROGRESS_INTERVAL: u64 = 4096;
+
+/// The stage that a parse is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// The raw events are read from the input.
+    Parsing,
+    /// The aware times and masters of the agents are resolved.
+    ///
+    /// This takes two passes over the events, both of which are counted in
+    /// [`Progress::events_processed`][Progress::events_processed]. This stage only exists when
+    /// using the high-level processing functions with
+    /// [`ProcessingOptions::resolve_agents`][crate::ProcessingOptions::resolve_agents].
+    Resolving,
+    /// The raw events are converted to high-level events.
+    ///
+    /// This stage only exists when using the high-level processing functions.
+    Processing,
+}
+
+/// A snapshot of the progress, as given to the [`ProgressMonitor`][ProgressMonitor] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// The current stage.
+    pub stage: Stage,
+    /// The number of bytes that have been read from the input (i.e. the compressed bytes, if the
+    /// log is compressed).
+    ///
+    /// For seekable inputs, this is the position in the input. The bytes are only counted during
+    /// [`Stage::Parsing`][Stage::Parsing].
+    pub bytes_read: u64,
+    /// The total size of the input, if it is known.
+    pub total_bytes: Option<u64>,
+    /// The number of events that have been decoded so far.
+    pub events_decoded: u64,
+    /// The number of events that have been processed so far.
+    ///
+    /// This is always 0 during [`Stage::Parsing`][Stage::Parsing].
+    pub events_processed: u64,
+}
+
+impl Progress {
+    /// Returns the progress of the current stage as a number between 0 and 1, if it is known.
+    ///
+    /// While parsing, this is based on the bytes read, which requires the total size of the input.
+    /// While resolving and processing, this is based on the number of processed events.
+    pub fn fraction(&self) -> Option<f64> {
+        let (done, total) = match self.stage {
+            Stage::Parsing => (self.bytes_read, self.total_bytes?),
+            Stage::Resolving => (self.events_processed, 2 * self.events_decoded),
+            Stage::Processing => (self.events_processed, self.events_decoded),
+        };
+        if total == 0 {
+            return Some(1.0);
+        }
+        Some((done as f64 / total as f64).min(1.0))
+    }
+}
+
+/// A token that can be used to cancel a running parse.
+///
+/// The token can be cloned and moved to a different thread. Cancelling any of the clones cancels
+/// all of them.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a new token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Check whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+type Callback = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// Reports the progress of a parse and allows it to be cancelled.
+///
+/// The progress is checked after the header, every few thousand events and at the end. The
+/// default monitor does nothing.
+#[derive(Clone, Default)]
+pub struct ProgressMonitor {
+    callback: Option<Callback>,
+    token: Option<CancellationToken>,
+    total_bytes: Option<u64>,
+}
+
+impl fmt::Debug for ProgressMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressMonitor")
+            .field("callback", &self.callback.as_ref().map(|_| ".."))
+            .field("token", &self.token)
+            .field("total_bytes", &self.total_bytes)
+            .finish()
+    }
+}
+
+/// Two monitors are equal if they share the same callback and token.
+impl PartialEq for ProgressMonitor {
+    fn eq(&self, other: &Self) -> bool {
+        let callback = match (&self.callback, &other.callback) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        let token = match (&self.token, &other.token) {
+            (Some(a), Some(b)) => Arc::ptr_eq(&a.cancelled, &b.cancelled),
+            (None, None) => true,
+            _ => false,
+        };
+        callback && token && self.total_bytes == other.total_bytes
+    }
+}
+
+impl Eq for ProgressMonitor {}
+
+impl ProgressMonitor {
+    /// Create a new monitor that does nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the function that is called with the progress.
+    ///
+    /// The function is called on the thread that does the parsing, so it should return quickly.
+    pub fn on_progress<F: Fn(Progress) + Send + Sync + 'static>(mut self, callback: F) -> Self {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Set the token that can be used to cancel the parsing.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Set the total size of the input.
+    ///
+    /// This is only needed if the size cannot be determined automatically, which is the case for
+    /// inputs that are not seekable.
+    pub fn total_bytes(mut self, total_bytes: u64) -> Self {
+        self.total_bytes = Some(total_bytes);
+        self
+    }
+
+    /// Set the total size of the input, unless it has been set already.
+    pub(crate) fn or_total_bytes(mut self, total_bytes: u64) -> Self {
+        self.total_bytes.get_or_insert(total_bytes);
+        self
+    }
+
+    /// Check whether the parsing has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.token
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Start tracking a new parse.
+    pub(crate) fn start(&self, stage: Stage) -> Tracker<'_> {
+        Tracker {
+            monitor: self,
+            position: Rc::new(Cell::new(0)),
+            progress: Progress {
+                stage,
+                bytes_read: 0,
+                total_bytes: self.total_bytes,
+                events_decoded: 0,
+                events_processed: 0,
+            },
+        }
+    }
+}
+
+/// The state of a single monitored parse.
+pub(crate) struct Tracker<'a> {
+    monitor: &'a ProgressMonitor,
+    position: Rc<Cell<u64>>,
+    progress: Progress,
+}
+
+impl<'a> Tracker<'a> {
+    /// Wrap the given input, so that the bytes read from it are counted.
+    pub(crate) fn reader<R>(&self, input: R) -> TrackedReader<R> {
+        TrackedReader {
+            input,
+            position: Rc::clone(&self.position),
+        }
+    }
+
+    /// Set the current position in the input.
+    pub(crate) fn set_position(&self, position: u64) {
+        self.position.set(position);
+    }
+
+    /// Set the total size, unless it was given explicitly.
+    pub(crate) fn set_total_bytes(&mut self, total_bytes: u64) {
+        self.progress.total_bytes.get_or_insert(total_bytes);
+    }
+
+    /// Set the number of decoded events (for the resolving and processing stages).
+    pub(crate) fn set_events_decoded(&mut self, events: u64) {
+        self.progress.events_decoded = events;
+    }
+
+    /// Count a decoded event, and check the progress if needed.
+    pub(crate) fn event_decoded(&mut self) -> ParseResult<()> {
+        self.progress.events_decoded += 1;
+        if self
+            .progress
+            .events_decoded
+            .is_multiple_of(PROGRESS_INTERVAL)
+        {
+            self.check()?;
+        }
+        Ok(())
+    }
+
+    /// Count a processed event, and check the progress if needed.
+    pub(crate) fn event_processed(&mut self) -> ParseResult<()> {
+        self.progress.events_processed += 1;
+        if self
+            .progress
+            .events_processed
+            .is_multiple_of(PROGRESS_INTERVAL)
+        {
+            self.check()?;
+        }
+        Ok(())
+    }
+
+    /// Count a batch of processed events, and check the progress.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn events_processed(&mut self, count: u64) -> ParseResult<()> {
+        self.progress.events_processed += count;
+        self.check()
+    }
+
+    /// Check for cancellation and report the current progress.
+    pub(crate) fn check(&mut self) -> ParseResult<()> {
+        if self.monitor.is_cancelled() {
+            return Err(ParseError::Cancelled);
+        }
+        if let Some(ref callback) = self.monitor.callback {
+            self.progress.bytes_read = self.position.get();
+            callback(self.progress);
+        }
+        Ok(())
+    }
+}
+
+/// A reader that counts the bytes that are read, see [`Tracker::reader`].
+pub(crate) struct TrackedReader<R> {
+    input: R,
+    position: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for TrackedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.input.read(buf)?;
+        self.position.set(self.position.get() + read as u64);
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for TrackedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = self.input.seek(pos)?;
+        self.position.set(position);
+        Ok(position)
+    }
+}
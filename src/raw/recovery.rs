@@ -0,0 +1,201 @@
+//! Best-effort parsing of incomplete or corrupted logs.
+//!
+//! If the game (or arcdps) crashes during a fight, the resulting file might stop in the middle of
+//! an event. The normal parsing functions treat the end of the file as the end of the event list,
+//! so such a log looks just like a complete one. The functions in this module parse as much as
+//! possible, and additionally return a [`RecoveryReport`][RecoveryReport] that says whether (and
+//! where) the log is incomplete:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::fs::File;
+//! use std::io::BufReader;
+//! let input = BufReader::new(File::open("crashed.zevtc")?);
+//! let (evtc, report) = evtclib::raw::parse_zip_recovering(input)?;
+//! if !report.is_complete() {
+//!     println!("The log is incomplete, the stats might be wrong!");
+//! }
+//! let log = evtclib::process(&evtc)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only the event list can be recovered. If the header, the agents or the skills are damaged, an
+//! error is returned, just like with [`parse_file`][super::parse_file].
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
+
+use crc32fast::Hasher;
+use zip::ZipArchive;
+
+use super::parser::{self, AGENT_SIZE, EVENT_SIZE, HEADER_SIZE, SKILL_SIZE};
+use super::{CbtStateChange, Evtc, ParseError, ParseResult};
+
+/// Information about the completeness of a log, as returned by the recovering parsers.
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    /// The byte offset (in the uncompressed file) right after the last complete event.
+    pub events_end: u64,
+    /// The number of bytes after the last complete event that were dropped.
+    pub trailing_bytes: u64,
+    /// Whether the log is missing its [`LogEnd`][CbtStateChange::LogEnd] event.
+    pub missing_log_end: bool,
+    /// Whether the checksum of the compressed file did not match its content.
+    pub checksum_failed: bool,
+    /// The error that stopped the parsing of the events early, if any.
+    ///
+    /// This is not set for a file that simply ends in the middle of an event, that case is covered
+    /// by [`trailing_bytes`][RecoveryReport::trailing_bytes].
+    pub error: Option<ParseError>,
+}
+
+impl RecoveryReport {
+    /// Check whether the log looks complete.
+    ///
+    /// This is the case if no bytes were dropped, no error occured, the checksum matched and the
+    /// log has a [`LogEnd`][CbtStateChange::LogEnd] event.
+    pub fn is_complete(&self) -> bool {
+        self.trailing_bytes == 0
+            && !self.missing_log_end
+            && !self.checksum_failed
+            && self.error.is_none()
+    }
+}
+
+/// Parse a complete EVTC file, recovering as many events as possible.
+///
+/// * `input` - Input stream.
+pub fn parse_file_recovering<R: Read>(mut input: R) -> ParseResult<(Evtc, RecoveryReport)> {
+    let partial = parser::parse_partial_file(&mut input)?;
+    let decode = parser::event_decoder(partial.header.revision)?;
+
+    let mut report = RecoveryReport {
+        events_end: HEADER_SIZE as u64
+            + AGENT_SIZE as u64 * u64::from(partial.header.agent_count)
+            + 4
+            + SKILL_SIZE as u64 * u64::from(partial.skill_count),
+        ..Default::default()
+    };
+    let mut events = Vec::new();
+    let mut unknown_statechanges = 0;
+    let mut bytes = [0; EVENT_SIZE];
+    loop {
+        let (filled, error) = read_record(&mut input, &mut bytes);
+        if filled < EVENT_SIZE {
+            report.trailing_bytes = filled as u64;
+            if let Some(error) = error {
+                report.error = Some(error.into());
+            }
+            break;
+        }
+        match decode(&bytes) {
+            Ok(x) => events.push(x),
+            Err(ParseError::UnknownStateChange(_)) => {
+                // Ignore unknown statechanges, as advised by arcdps.
+                unknown_statechanges += 1;
+            }
+            Err(e) => {
+                report.trailing_bytes = EVENT_SIZE as u64;
+                report.error = Some(e);
+                break;
+            }
+        }
+        report.events_end += EVENT_SIZE as u64;
+    }
+    if report.error.is_some() {
+        // Count the rest of the data, so that we know how much we are dropping. If that fails as
+        // well, we keep the first error.
+        if let Ok(rest) = io::copy(&mut input, &mut io::sink()) {
+            report.trailing_bytes += rest;
+        }
+    }
+    report.missing_log_end = !events
+        .iter()
+        .any(|e| e.is_statechange == CbtStateChange::LogEnd);
+
+    let evtc = Evtc {
+        header: partial.header,
+        skill_count: partial.skill_count,
+        agents: partial.agents,
+        skills: partial.skills,
+        events,
+        unknown_statechanges,
+    };
+    Ok((evtc, report))
+}
+
+/// Parse a complete log that was compressed as a zip file, recovering as many events as possible.
+///
+/// Besides the problems that [`parse_file_recovering`][parse_file_recovering] detects, this also
+/// reports whether the checksum of the zip entry matched its content.
+///
+/// * `input` - Input stream.
+pub fn parse_zip_recovering<R: Read + Seek>(input: R) -> ParseResult<(Evtc, RecoveryReport)> {
+    let mut archive = ZipArchive::new(input)?;
+    let file = archive.by_index(0)?;
+    let mut file = ChecksumReader {
+        expected: file.crc32(),
+        size: file.size(),
+        inner: file,
+        hasher: Hasher::new(),
+        read: 0,
+        mismatch: false,
+    };
+    let (evtc, mut report) = parse_file_recovering(BufReader::new(&mut file))?;
+    report.checksum_failed = file.mismatch;
+    Ok((evtc, report))
+}
+
+/// A reader that computes the CRC32 checksum of a zip entry while it is being read.
+///
+/// The zip reader checks the checksum as well, but it signals a mismatch with an error once the
+/// end of the entry is reached. As all data has been read at that point, we verify the checksum
+/// ourselves and treat such an error as the end of the input instead.
+struct ChecksumReader<R> {
+    inner: R,
+    hasher: Hasher,
+    /// The checksum and the uncompressed size, as stored in the archive.
+    expected: u32,
+    size: u64,
+    /// The number of bytes that have been read so far.
+    read: u64,
+    /// Whether the whole entry has been read and its checksum did not match.
+    mismatch: bool,
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.inner.read(buf);
+        let complete = match result {
+            Ok(n) => {
+                self.hasher.update(&buf[..n]);
+                self.read += n as u64;
+                n == 0 || self.read == self.size
+            }
+            Err(_) => self.read == self.size,
+        };
+        if complete && self.hasher.clone().finalize() != self.expected {
+            self.mismatch = true;
+            return result.or(Ok(0));
+        }
+        result
+    }
+}
+
+/// Read a single event record, stopping early at the end of the input.
+///
+/// Returns the number of bytes that were read, together with the error that occured (if any).
+fn read_record<R: Read>(
+    input: &mut R,
+    buffer: &mut [u8; EVENT_SIZE],
+) -> (usize, Option<io::Error>) {
+    let mut filled = 0;
+    while filled < EVENT_SIZE {
+        match input.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e) => return (filled, Some(e)),
+        }
+    }
+    (filled, None)
+}
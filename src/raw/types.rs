@@ -2,12 +2,18 @@
 //!
 //! This module contains the translated definitions from arcdps's C structs.
 use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
 
+use std::convert::TryFrom;
 use std::hash::Hash;
 
 /// The "friend or foe" enum.
+///
+/// Values that are not known to evtclib are kept in [`IFF::Other`][IFF::Other]. Note that
+/// [`FromPrimitive`][FromPrimitive] only returns known values, use the [`From`][From] conversion
+/// if you want the fallback.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, FromPrimitive)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
 pub enum IFF {
     /// Green vs green, red vs red.
     Friend,
@@ -16,18 +22,62 @@ pub enum IFF {
     /// Something very wrong happened.
     Unknown,
     /// Field is not used in this kind of event.
+    #[default]
     None,
+    /// A value that is not known to evtclib.
+    ///
+    /// This keeps the original byte, so that no information is lost.
+    Other(u8),
 }
 
-impl Default for IFF {
-    fn default() -> Self {
-        IFF::None
+impl From<u8> for IFF {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => IFF::Friend,
+            1 => IFF::Foe,
+            2 => IFF::Unknown,
+            3 => IFF::None,
+            other => IFF::Other(other),
+        }
+    }
+}
+
+impl From<IFF> for u8 {
+    fn from(value: IFF) -> Self {
+        match value {
+            IFF::Friend => 0,
+            IFF::Foe => 1,
+            IFF::Unknown => 2,
+            IFF::None => 3,
+            IFF::Other(other) => other,
+        }
+    }
+}
+
+impl FromPrimitive for IFF {
+    fn from_i64(n: i64) -> Option<Self> {
+        u8::try_from(n).ok().and_then(Self::from_u8)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        u8::try_from(n).ok().and_then(Self::from_u8)
+    }
+
+    fn from_u8(n: u8) -> Option<Self> {
+        match IFF::from(n) {
+            IFF::Other(_) => None,
+            known => Some(known),
+        }
     }
 }
 
 /// Combat result (physical)
+///
+/// Values that are not known to evtclib are kept in [`CbtResult::Other`][CbtResult::Other]. Note that
+/// [`FromPrimitive`][FromPrimitive] only returns known values, use the [`From`][From] conversion
+/// if you want the fallback.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, FromPrimitive)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
 pub enum CbtResult {
     /// Good physical hit
     Normal,
@@ -52,20 +102,81 @@ pub enum CbtResult {
     /// Hit was breakbar damage.
     Breakbar,
     /// Field is not used in this kind of event.
+    #[default]
     None,
+    /// A value that is not known to evtclib.
+    ///
+    /// This keeps the original byte, so that no information is lost.
+    Other(u8),
 }
 
-impl Default for CbtResult {
-    fn default() -> Self {
-        CbtResult::None
+impl From<u8> for CbtResult {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => CbtResult::Normal,
+            1 => CbtResult::Crit,
+            2 => CbtResult::Glance,
+            3 => CbtResult::Block,
+            4 => CbtResult::Evade,
+            5 => CbtResult::Interrupt,
+            6 => CbtResult::Absorb,
+            7 => CbtResult::Blind,
+            8 => CbtResult::KillingBlow,
+            9 => CbtResult::Downed,
+            10 => CbtResult::Breakbar,
+            11 => CbtResult::None,
+            other => CbtResult::Other(other),
+        }
+    }
+}
+
+impl From<CbtResult> for u8 {
+    fn from(value: CbtResult) -> Self {
+        match value {
+            CbtResult::Normal => 0,
+            CbtResult::Crit => 1,
+            CbtResult::Glance => 2,
+            CbtResult::Block => 3,
+            CbtResult::Evade => 4,
+            CbtResult::Interrupt => 5,
+            CbtResult::Absorb => 6,
+            CbtResult::Blind => 7,
+            CbtResult::KillingBlow => 8,
+            CbtResult::Downed => 9,
+            CbtResult::Breakbar => 10,
+            CbtResult::None => 11,
+            CbtResult::Other(other) => other,
+        }
+    }
+}
+
+impl FromPrimitive for CbtResult {
+    fn from_i64(n: i64) -> Option<Self> {
+        u8::try_from(n).ok().and_then(Self::from_u8)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        u8::try_from(n).ok().and_then(Self::from_u8)
+    }
+
+    fn from_u8(n: u8) -> Option<Self> {
+        match CbtResult::from(n) {
+            CbtResult::Other(_) => None,
+            known => Some(known),
+        }
     }
 }
 
 /// Combat activation
+///
+/// Values that are not known to evtclib are kept in [`CbtActivation::Other`][CbtActivation::Other]. Note that
+/// [`FromPrimitive`][FromPrimitive] only returns known values, use the [`From`][From] conversion
+/// if you want the fallback.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, FromPrimitive)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
 pub enum CbtActivation {
     /// Field is not used in this kind of event.
+    #[default]
     None,
     /// Activation without quickness
     Normal,
@@ -77,11 +188,54 @@ pub enum CbtActivation {
     CancelCancel,
     /// Animation completed fully
     Reset,
+    /// A value that is not known to evtclib.
+    ///
+    /// This keeps the original byte, so that no information is lost.
+    Other(u8),
 }
 
-impl Default for CbtActivation {
-    fn default() -> Self {
-        CbtActivation::None
+impl From<u8> for CbtActivation {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => CbtActivation::None,
+            1 => CbtActivation::Normal,
+            2 => CbtActivation::Quickness,
+            3 => CbtActivation::CancelFire,
+            4 => CbtActivation::CancelCancel,
+            5 => CbtActivation::Reset,
+            other => CbtActivation::Other(other),
+        }
+    }
+}
+
+impl From<CbtActivation> for u8 {
+    fn from(value: CbtActivation) -> Self {
+        match value {
+            CbtActivation::None => 0,
+            CbtActivation::Normal => 1,
+            CbtActivation::Quickness => 2,
+            CbtActivation::CancelFire => 3,
+            CbtActivation::CancelCancel => 4,
+            CbtActivation::Reset => 5,
+            CbtActivation::Other(other) => other,
+        }
+    }
+}
+
+impl FromPrimitive for CbtActivation {
+    fn from_i64(n: i64) -> Option<Self> {
+        u8::try_from(n).ok().and_then(Self::from_u8)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        u8::try_from(n).ok().and_then(Self::from_u8)
+    }
+
+    fn from_u8(n: u8) -> Option<Self> {
+        match CbtActivation::from(n) {
+            CbtActivation::Other(_) => None,
+            known => Some(known),
+        }
     }
 }
 
@@ -90,9 +244,10 @@ impl Default for CbtActivation {
 /// The referenced fields are of the [`CbtEvent`](struct.CbtEvent.html)
 /// struct.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, FromPrimitive)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, FromPrimitive, Default)]
 pub enum CbtStateChange {
     /// Field is not used in this kind of event.
+    #[default]
     None,
     /// `src_agent` entered combat.
     ///
@@ -211,19 +366,87 @@ pub enum CbtStateChange {
     /// `src_agent` is at barrier percent, `dst_agent` is the percentage times 10000 (so 99.5%
     /// will be 9950).
     BarrierUpdate,
-}
-
-impl Default for CbtStateChange {
-    fn default() -> Self {
-        CbtStateChange::None
-    }
+    /// `src_agent` is the species id of the agent that triggered the reset, e.g. the boss species
+    /// id.
+    StatReset,
+    /// Used by extensions, the layout depends on the extension that created the event.
+    Extension,
+    /// Internal use by arcdps (realtime only), won't see anywhere.
+    ApiDelayed,
+    /// `src_agent` is the time (in milliseconds) at which the instance was likely started.
+    InstanceStart,
+    /// Tick rate health.
+    ///
+    /// * `src_agent` is 25 - tickrate, when the tickrate is 20 or lower.
+    TickRate,
+    /// `src_agent` is the enemy agent that went down, `dst_agent` is the time in milliseconds
+    /// since the agent was last at 90% health.
+    Last90BeforeDown,
+    /// An effect was created (retired, replaced by [`Effect2`][CbtStateChange::Effect2]).
+    ///
+    /// * `src_agent` is the owner.
+    /// * `dst_agent` is the agent the effect is at, if any.
+    /// * `value` through `overstack_value` is the position (`float[3]`) if there is no
+    ///   `dst_agent`.
+    /// * `iff` through `is_moving` is the x and y orientation (`float[2]`).
+    /// * `pad61` is the z orientation (`float`).
+    /// * `is_shields` and `is_offcycle` is the duration (`uint16_t`), or the tracking id if
+    ///   `skillid` is zero.
+    /// * `skillid` is the effect id, or zero if a tracked effect has ended.
+    Effect,
+    /// Maps a volatile id to a persistent content GUID.
+    ///
+    /// * `src_agent` and `dst_agent` is the 16 byte GUID.
+    /// * `overstack_value` is the content type (0 = effect, 1 = marker).
+    /// * `skillid` is the volatile content id.
+    IdToGuid,
+    /// `src_agent` is the species id (0 if changed to generic), `dst_agent` is the related agent,
+    /// `value` is the server unix timestamp.
+    LogNpcUpdate,
+    /// Internal use by arcdps, won't see anywhere.
+    IdleEvent,
+    /// Used by extensions, treats `skillid` as skill for the evtc skill table.
+    ExtensionCombat,
+    /// `src_agent` is the fractal scale.
+    FractalScale,
+    /// An effect was created.
+    ///
+    /// * `src_agent` is the owner.
+    /// * `dst_agent` is the agent the effect is at, if any.
+    /// * `value` through `overstack_value` is the position (`float[3]`) if there is no
+    ///   `dst_agent`.
+    /// * `iff` through `is_activation` is the duration (`uint32_t`).
+    /// * `is_buffremove` through `is_moving` is the tracking id (`uint32_t`).
+    /// * `is_shields` through `pad64` is the orientation (`int16_t[3]`), multiplied by 1000.
+    /// * `is_flanking` is set if the effect is on a non-static platform.
+    /// * `skillid` is the effect id, or zero if a tracked effect has ended.
+    Effect2,
+    /// `src_agent` is a bitmask of the ruleset (1 = PvE, 2 = WvW, 4 = PvP).
+    Ruleset,
+    /// A squad marker was placed or removed.
+    ///
+    /// * `src_agent` and `dst_agent` is the position (`float[3]`). If the values are all zero or
+    ///   infinity, the marker was removed.
+    /// * `skillid` is the index of the marker (e.g. 0 is arrow).
+    SquadMarker,
+    /// `time` is the start of the arcdps build string.
+    ArcBuild,
+    /// `src_agent` is the agent, `value` is 1 if the glider was deployed and 0 if it was stowed.
+    Glider,
+    /// `src_agent` is the agent, `value` is the remaining duration.
+    StunBreak,
 }
 
 /// Combat buff remove type
+///
+/// Values that are not known to evtclib are kept in [`CbtBuffRemove::Other`][CbtBuffRemove::Other]. Note that
+/// [`FromPrimitive`][FromPrimitive] only returns known values, use the [`From`][From] conversion
+/// if you want the fallback.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, FromPrimitive)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
 pub enum CbtBuffRemove {
     /// Field is not used in this kind of event.
+    #[default]
     None,
     /// All stacks removed.
     All,
@@ -235,11 +458,50 @@ pub enum CbtBuffRemove {
     ///
     /// (Ignore for strip/cleanse calc, use for in/out volume)-
     Manual,
+    /// A value that is not known to evtclib.
+    ///
+    /// This keeps the original byte, so that no information is lost.
+    Other(u8),
 }
 
-impl Default for CbtBuffRemove {
-    fn default() -> Self {
-        CbtBuffRemove::None
+impl From<u8> for CbtBuffRemove {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => CbtBuffRemove::None,
+            1 => CbtBuffRemove::All,
+            2 => CbtBuffRemove::Single,
+            3 => CbtBuffRemove::Manual,
+            other => CbtBuffRemove::Other(other),
+        }
+    }
+}
+
+impl From<CbtBuffRemove> for u8 {
+    fn from(value: CbtBuffRemove) -> Self {
+        match value {
+            CbtBuffRemove::None => 0,
+            CbtBuffRemove::All => 1,
+            CbtBuffRemove::Single => 2,
+            CbtBuffRemove::Manual => 3,
+            CbtBuffRemove::Other(other) => other,
+        }
+    }
+}
+
+impl FromPrimitive for CbtBuffRemove {
+    fn from_i64(n: i64) -> Option<Self> {
+        u8::try_from(n).ok().and_then(Self::from_u8)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        u8::try_from(n).ok().and_then(Self::from_u8)
+    }
+
+    fn from_u8(n: u8) -> Option<Self> {
+        match CbtBuffRemove::from(n) {
+            CbtBuffRemove::Other(_) => None,
+            known => Some(known),
+        }
     }
 }
 
@@ -257,9 +519,10 @@ pub enum CbtCustomSkill {
 
 /// Language
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, FromPrimitive)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, FromPrimitive, Default)]
 pub enum Language {
     /// English.
+    #[default]
     Eng = 0,
     /// French.
     Fre = 2,
@@ -269,12 +532,6 @@ pub enum Language {
     Spa = 4,
 }
 
-impl Default for Language {
-    fn default() -> Self {
-        Language::Eng
-    }
-}
-
 /// Buff formula attributes.
 ///
 /// Variants prefixed with `Custom` are not native to the game client but rather variants added by
@@ -373,6 +630,67 @@ pub struct CbtEvent {
     pub is_offcycle: bool,
     /// Padding for some events.
     pub padding_end: u32,
+    /// Statechange specific data that is stored in the bytes of the fields `iff` through
+    /// `is_offcycle`.
+    ///
+    /// See [`StateChangeData`][StateChangeData] for more information.
+    pub statechange_data: StateChangeData,
+}
+
+/// Data of some statechange events that is stored in the bytes of the fields `iff` through
+/// `is_offcycle` of [`CbtEvent`][CbtEvent], but that does not fit the typed fields.
+///
+/// For those events, the bytes are stored here instead of the typed fields, which are left at
+/// their default values (and ignored when the event is written). All other events use
+/// [`StateChangeData::None`][StateChangeData::None].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum StateChangeData {
+    /// The event has no statechange specific data in the flag bytes.
+    #[default]
+    None,
+    /// The data of an [`Effect`][CbtStateChange::Effect] event.
+    ///
+    /// This covers the bytes of `iff` through `is_moving`, `is_shields` and `is_offcycle`.
+    Effect {
+        /// The x and y orientation, as bit patterns of `f32` values.
+        orientation: [u32; 2],
+        /// The duration, or the tracking id if the effect has ended.
+        duration: u16,
+    },
+    /// The data of an [`Effect2`][CbtStateChange::Effect2] event.
+    ///
+    /// This covers the bytes of `iff` through `is_moving`, `is_shields` and `is_offcycle`.
+    Effect2 {
+        /// The duration of the effect.
+        duration: u32,
+        /// The tracking id of the effect.
+        tracking_id: u32,
+        /// The x orientation, multiplied by 1000.
+        ///
+        /// The y and z orientation are stored in [`CbtEvent::padding_end`][CbtEvent::padding_end].
+        orientation_x: i16,
+    },
+    /// The data of a [`BuffInfo`][CbtStateChange::BuffInfo] event.
+    ///
+    /// This covers the byte of `is_offcycle`.
+    BuffInfo {
+        /// The category of the buff, see [`BuffCategory`][BuffCategory].
+        category: u8,
+    },
+}
+
+impl StateChangeData {
+    /// Returns the indices of the flag bytes (counted from `iff`) that are covered by this data.
+    pub(crate) fn covered_flags(&self) -> &'static [usize] {
+        match self {
+            StateChangeData::None => &[],
+            StateChangeData::Effect { .. } | StateChangeData::Effect2 { .. } => {
+                &[0, 1, 2, 3, 4, 5, 6, 7, 10, 11]
+            }
+            StateChangeData::BuffInfo { .. } => &[11],
+        }
+    }
 }
 
 /// An agent.
@@ -402,7 +720,7 @@ impl Agent {
     /// Gadgets are entities spawned by some skills, like the "Binding Roots"
     /// spawned by Entangle.
     pub fn is_gadget(&self) -> bool {
-        self.is_elite == std::u32::MAX && (self.prof & 0xffff_0000) == 0xffff_0000
+        self.is_elite == u32::MAX && (self.prof & 0xffff_0000) == 0xffff_0000
     }
 
     /// Checks whether this agent is a character.
@@ -410,12 +728,12 @@ impl Agent {
     /// Characters are entities like clones, pets, minions, spirits, but also
     /// minis.
     pub fn is_character(&self) -> bool {
-        self.is_elite == std::u32::MAX && (self.prof & 0xffff_0000) != 0xffff_0000
+        self.is_elite == u32::MAX && (self.prof & 0xffff_0000) != 0xffff_0000
     }
 
     /// Checks whether this agent is a player.
     pub fn is_player(&self) -> bool {
-        self.is_elite != std::u32::MAX
+        self.is_elite != u32::MAX
     }
 }
 
@@ -0,0 +1,345 @@
+//! This module contains functions to write an EVTC file.
+//!
+//! The writing functions are the counterpart to the functions in the [`parser`][super::parser]
+//! module. They follow the same layout (see the [parser documentation][super::parser#layout]),
+//! and they allow you to write single parts of the data as well as complete files.
+//!
+//! Writing a [`Evtc`][Evtc] and parsing it back will yield the same [`Evtc`][Evtc] again:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::fs::File;
+//! use std::io::BufReader;
+//! let evtc = evtclib::raw::parse_zip(BufReader::new(File::open("log.zevtc")?))?;
+//! let mut output = Vec::new();
+//! evtclib::raw::write_file(&evtc, &mut output)?;
+//! assert_eq!(evtclib::raw::parse_file(&output[..])?, evtc);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! To keep this promise, writing fails if the data cannot be parsed back to the same value:
+//!
+//! * [`Header::agent_count`][Header::agent_count] and [`Evtc::skill_count`][Evtc::skill_count]
+//!   have to match the length of the agent and skill vectors, otherwise
+//!   [`WriteError::CountMismatch`][WriteError::CountMismatch] is returned. If you add or remove
+//!   agents or skills, you have to update the counts as well.
+//! * The events that the parser skipped because of their unknown statechange are not part of the
+//!   [`Evtc`][Evtc], so they cannot be written back. If
+//!   [`Evtc::unknown_statechanges`][Evtc::unknown_statechanges] is not zero,
+//!   [`WriteError::UnknownStateChanges`][WriteError::UnknownStateChanges] is returned. Set it to
+//!   zero if you want to write the log without those events.
+//!
+//! # Padding
+//!
+//! Padding bytes and bytes that arcdps uses for internal tracking are not kept by the parser, so
+//! they are written as zeroes, and boolean flags are written as `0` or `1`. Apart from that, the
+//! events are written byte-for-byte as they were parsed (see [`StateChangeData`][StateChangeData]
+//! for the bytes that some statechanges use for their own data). This means that the output is
+//! the canonical form of the file: writing the same [`Evtc`][Evtc] will always produce the same
+//! bytes, and a file that was written by evtclib is reproduced exactly.
+//!
+//! # Buffering
+//!
+//! Just like parsing, writing does many small writes. It is advised that you wrap the writer in a
+//! `BufWriter`, if the underlying writer does not do buffering on its own.
+
+use byteorder::{ByteOrder, WriteBytesExt, LE};
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use thiserror::Error;
+
+use super::parser::{Header, PartialEvtc};
+use super::*;
+
+/// Any error that can occur during writing.
+#[derive(Error, Debug)]
+pub enum WriteError {
+    /// The error stems from an underlying input/output error.
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    /// A value cannot be represented in the output format.
+    ///
+    /// This happens for example if the arcdps build is not exactly 8 bytes long, or if a value is
+    /// too big to fit into the smaller fields of revision 0.
+    #[error("invalid data")]
+    InvalidData,
+    /// The revision of the header is not known.
+    #[error("unknown revision: {0}")]
+    UnknownRevision(u8),
+    /// The agent count or the skill count does not match the number of agents or skills.
+    #[error("the agent or skill count does not match the data")]
+    CountMismatch,
+    /// The log contains events with unknown statechanges, which cannot be written back.
+    #[error("{0} events with unknown statechanges cannot be written")]
+    UnknownStateChanges(usize),
+    /// The ZIP archive could not be written.
+    #[error("invalid archive: {0}")]
+    InvalidZip(#[from] zip::result::ZipError),
+}
+
+/// A type indicating the write result.
+pub type WriteResult<T> = Result<T, WriteError>;
+
+/// Write the header of an evtc file.
+///
+/// * `header` - The header to write.
+/// * `agent_count` - The number of agents that will follow the header.
+/// * `output` - Output stream.
+pub fn write_header<W: Write>(header: &Header, agent_count: u32, mut output: W) -> WriteResult<()> {
+    if header.arcdps_build.len() != 8 {
+        return Err(WriteError::InvalidData);
+    }
+    output.write_all(b"EVTC")?;
+    output.write_all(header.arcdps_build.as_bytes())?;
+    output.write_u8(header.revision)?;
+    output.write_u16::<LE>(header.combat_id)?;
+    // Zero delimiter.
+    output.write_u8(0)?;
+    output.write_u32::<LE>(agent_count)?;
+    Ok(())
+}
+
+/// Write the agent array.
+///
+/// Note that this does not write the agent count, as that is part of the header.
+///
+/// * `agents` - The agents to write.
+/// * `output` - Output stream.
+pub fn write_agents<W: Write>(agents: &[Agent], mut output: W) -> WriteResult<()> {
+    for agent in agents {
+        write_agent(agent, &mut output)?;
+    }
+    Ok(())
+}
+
+/// Write a single agent.
+///
+/// * `agent` - The agent to write.
+/// * `output` - Output stream.
+pub fn write_agent<W: Write>(agent: &Agent, mut output: W) -> WriteResult<()> {
+    output.write_u64::<LE>(agent.addr)?;
+    output.write_u32::<LE>(agent.prof)?;
+    output.write_u32::<LE>(agent.is_elite)?;
+    output.write_i16::<LE>(agent.toughness)?;
+    output.write_i16::<LE>(agent.concentration)?;
+    output.write_i16::<LE>(agent.healing)?;
+    // First padding.
+    output.write_i16::<LE>(0)?;
+    output.write_i16::<LE>(agent.condition)?;
+    // Second padding.
+    output.write_i16::<LE>(0)?;
+    output.write_all(&agent.name)?;
+    // Padding at the end to get the struct to 96 bytes, see parse_agent.
+    output.write_all(&[0; 4])?;
+    Ok(())
+}
+
+/// Write the skill array, including the skill count.
+///
+/// * `skills` - The skills to write.
+/// * `output` - Output stream.
+pub fn write_skills<W: Write>(skills: &[Skill], mut output: W) -> WriteResult<()> {
+    let count = u32::try_from(skills.len()).map_err(|_| WriteError::InvalidData)?;
+    output.write_u32::<LE>(count)?;
+    for skill in skills {
+        write_skill(skill, &mut output)?;
+    }
+    Ok(())
+}
+
+/// Write a single skill.
+///
+/// * `skill` - The skill to write.
+/// * `output` - Output stream.
+pub fn write_skill<W: Write>(skill: &Skill, mut output: W) -> WriteResult<()> {
+    output.write_i32::<LE>(skill.id)?;
+    output.write_all(&skill.name)?;
+    Ok(())
+}
+
+/// Write all combat events.
+///
+/// * `events` - The events to write.
+/// * `output` - Output stream.
+/// * `writer` - The write function to use.
+///
+/// The `writer` should be one of [`write_event_rev0`][write_event_rev0] or
+/// [`write_event_rev1`][write_event_rev1], depending on the revision of the file you are dealing
+/// with.
+pub fn write_events<W: Write>(
+    events: &[CbtEvent],
+    mut output: W,
+    writer: fn(&CbtEvent, &mut W) -> WriteResult<()>,
+) -> WriteResult<()> {
+    for event in events {
+        writer(event, &mut output)?;
+    }
+    Ok(())
+}
+
+/// Write a single combat event.
+///
+/// This works for old combat events, i.e. files with revision == 0.
+///
+/// Fields that do not exist in revision 0 (`dst_master_instid`, `is_offcycle` and `padding_end`)
+/// are not written. If a value does not fit into the smaller revision 0 fields,
+/// [`WriteError::InvalidData`][WriteError::InvalidData] is returned.
+///
+/// * `event` - The event to write.
+/// * `output` - Output stream.
+pub fn write_event_rev0<W: Write>(event: &CbtEvent, mut output: W) -> WriteResult<()> {
+    let overstack_value =
+        u16::try_from(event.overstack_value).map_err(|_| WriteError::InvalidData)?;
+    let skillid = u16::try_from(event.skillid).map_err(|_| WriteError::InvalidData)?;
+
+    output.write_u64::<LE>(event.time)?;
+    output.write_u64::<LE>(event.src_agent)?;
+    output.write_u64::<LE>(event.dst_agent)?;
+    output.write_i32::<LE>(event.value)?;
+    output.write_i32::<LE>(event.buff_dmg)?;
+    output.write_u16::<LE>(overstack_value)?;
+    output.write_u16::<LE>(skillid)?;
+    output.write_u16::<LE>(event.src_instid)?;
+    output.write_u16::<LE>(event.dst_instid)?;
+    output.write_u16::<LE>(event.src_master_instid)?;
+
+    // 9 bytes of internal tracking garbage.
+    output.write_all(&[0; 9])?;
+
+    output.write_all(&flag_bytes(event)[..11])?;
+
+    // Two more bytes of internal tracking garbage.
+    output.write_u16::<LE>(0)?;
+    Ok(())
+}
+
+/// Write a single combat event.
+///
+/// This works for new combat events, i.e. files with revision == 1.
+///
+/// * `event` - The event to write.
+/// * `output` - Output stream.
+pub fn write_event_rev1<W: Write>(event: &CbtEvent, mut output: W) -> WriteResult<()> {
+    output.write_u64::<LE>(event.time)?;
+    output.write_u64::<LE>(event.src_agent)?;
+    output.write_u64::<LE>(event.dst_agent)?;
+    output.write_i32::<LE>(event.value)?;
+    output.write_i32::<LE>(event.buff_dmg)?;
+    output.write_u32::<LE>(event.overstack_value)?;
+    output.write_u32::<LE>(event.skillid)?;
+    output.write_u16::<LE>(event.src_instid)?;
+    output.write_u16::<LE>(event.dst_instid)?;
+    output.write_u16::<LE>(event.src_master_instid)?;
+    output.write_u16::<LE>(event.dst_master_instid)?;
+
+    output.write_all(&flag_bytes(event))?;
+    output.write_u32::<LE>(event.padding_end)?;
+    Ok(())
+}
+
+/// Return the bytes of the fields `iff` through `is_offcycle` of the given event.
+///
+/// The bytes that belong to the [statechange specific data][StateChangeData] are taken from it,
+/// all others from the typed fields.
+fn flag_bytes(event: &CbtEvent) -> [u8; 12] {
+    let mut bytes = [
+        event.iff.into(),
+        event.buff,
+        event.result.into(),
+        event.is_activation.into(),
+        event.is_buffremove.into(),
+        event.is_ninety as u8,
+        event.is_fifty as u8,
+        event.is_moving as u8,
+        event.is_statechange as u8,
+        event.is_flanking as u8,
+        event.is_shields as u8,
+        event.is_offcycle as u8,
+    ];
+    match event.statechange_data {
+        StateChangeData::None => (),
+        StateChangeData::Effect {
+            orientation,
+            duration,
+        } => {
+            LE::write_u32(&mut bytes[0..4], orientation[0]);
+            LE::write_u32(&mut bytes[4..8], orientation[1]);
+            LE::write_u16(&mut bytes[10..12], duration);
+        }
+        StateChangeData::Effect2 {
+            duration,
+            tracking_id,
+            orientation_x,
+        } => {
+            LE::write_u32(&mut bytes[0..4], duration);
+            LE::write_u32(&mut bytes[4..8], tracking_id);
+            LE::write_i16(&mut bytes[10..12], orientation_x);
+        }
+        StateChangeData::BuffInfo { category } => bytes[11] = category,
+    }
+    bytes
+}
+
+/// Check that the counts in the header match the agents and skills.
+fn check_counts(
+    header: &Header,
+    skill_count: u32,
+    agents: &[Agent],
+    skills: &[Skill],
+) -> WriteResult<()> {
+    if header.agent_count as usize != agents.len() || skill_count as usize != skills.len() {
+        return Err(WriteError::CountMismatch);
+    }
+    Ok(())
+}
+
+/// Write a partial EVTC file.
+///
+/// This writes everything up to (but not including) the events.
+///
+/// If the agent or skill count does not match the data,
+/// [`WriteError::CountMismatch`][WriteError::CountMismatch] is returned.
+///
+/// * `partial` - The partial EVTC.
+/// * `output` - Output stream.
+pub fn write_partial_file<W: Write>(partial: &PartialEvtc, mut output: W) -> WriteResult<()> {
+    check_counts(
+        &partial.header,
+        partial.skill_count,
+        &partial.agents,
+        &partial.skills,
+    )?;
+    write_header(&partial.header, partial.header.agent_count, &mut output)?;
+    write_agents(&partial.agents, &mut output)?;
+    write_skills(&partial.skills, &mut output)?;
+    Ok(())
+}
+
+/// Write a complete EVTC file.
+///
+/// The events are written in the format given by the header's revision.
+///
+/// If the agent or skill count does not match the data, or if the log contains
+/// [unknown statechanges][Evtc::unknown_statechanges], an error is returned (see the [module
+/// documentation][self]).
+///
+/// * `evtc` - The EVTC to write.
+/// * `output` - Output stream.
+#[allow(clippy::redundant_closure)]
+pub fn write_file<W: Write>(evtc: &Evtc, mut output: W) -> WriteResult<()> {
+    // See EventReader::new on why the closures are needed.
+    let writer: fn(&CbtEvent, &mut W) -> WriteResult<()> = match evtc.header.revision {
+        0 => |e, w| write_event_rev0(e, w),
+        1 => |e, w| write_event_rev1(e, w),
+        x => return Err(WriteError::UnknownRevision(x)),
+    };
+    check_counts(&evtc.header, evtc.skill_count, &evtc.agents, &evtc.skills)?;
+    if evtc.unknown_statechanges != 0 {
+        return Err(WriteError::UnknownStateChanges(evtc.unknown_statechanges));
+    }
+    write_header(&evtc.header, evtc.header.agent_count, &mut output)?;
+    write_agents(&evtc.agents, &mut output)?;
+    write_skills(&evtc.skills, &mut output)?;
+    write_events(&evtc.events, output, writer)
+}
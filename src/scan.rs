@@ -0,0 +1,297 @@
+//! Private module to contain the header-only scanning of logs.
+
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{BufReader, Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use getset::CopyGetters;
+
+use super::{raw, AgentKind, Compression, Encounter, EvtcError, GameMode, Player};
+
+/// A lightweight summary of a log, obtained without decoding the events.
+///
+/// This is useful if you need to list many logs, for example to build an index of a log folder.
+/// It is much cheaper to obtain than a full [`Log`][super::Log], as only the header, the agents
+/// and the skills have to be read (and decompressed). Use [`scan_file`][scan_file] to obtain a
+/// `LogHeader`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, CopyGetters)]
+pub struct LogHeader {
+    arcdps_build: String,
+
+    /// The revision of the evtc file.
+    #[get_copy = "pub"]
+    revision: u8,
+
+    /// The encounter id, as found in the header.
+    ///
+    /// See also [`Log::encounter_id`][super::Log::encounter_id].
+    #[get_copy = "pub"]
+    encounter_id: u16,
+
+    players: Vec<Player>,
+
+    /// The size of the file in bytes, as it is stored on disk (i.e. compressed).
+    #[get_copy = "pub"]
+    file_size: u64,
+}
+
+impl LogHeader {
+    /// The arcdps build that recorded the log, as `yyyymmdd` string.
+    pub fn arcdps_build(&self) -> &str {
+        &self.arcdps_build
+    }
+
+    /// Returns the encounter, if present.
+    ///
+    /// See [`Log::encounter`][super::Log::encounter].
+    pub fn encounter(&self) -> Option<Encounter> {
+        Encounter::from_header_id(self.encounter_id)
+    }
+
+    /// Check whether this is a generic (e.g. WvW) log.
+    ///
+    /// See [`Log::is_generic`][super::Log::is_generic].
+    pub fn is_generic(&self) -> bool {
+        self.encounter_id == 1
+    }
+
+    /// Returns the game mode that the log was made in.
+    ///
+    /// See [`Log::game_mode`][super::Log::game_mode].
+    pub fn game_mode(&self) -> Option<GameMode> {
+        if self.is_generic() {
+            Some(GameMode::WvW)
+        } else {
+            self.encounter().map(Encounter::game_mode)
+        }
+    }
+
+    /// Returns the players that are present in the log.
+    ///
+    /// Players with unknown professions or elite specializations are included, using
+    /// [`Profession::Unknown`][super::Profession::Unknown] and
+    /// [`EliteSpec::Unknown`][super::EliteSpec::Unknown].
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    fn from_partial(partial: &raw::parser::PartialEvtc, file_size: u64) -> Result<Self, EvtcError> {
+        let mut players = Vec::new();
+        for raw_agent in &partial.agents {
+            if let Some(player) = AgentKind::from_raw_lenient(raw_agent)?.as_player() {
+                players.push(player.clone());
+            }
+        }
+        Ok(LogHeader {
+            arcdps_build: partial.header.arcdps_build.clone(),
+            revision: partial.header.revision,
+            encounter_id: partial.header.combat_id,
+            players,
+            file_size,
+        })
+    }
+}
+
+/// Read the [`LogHeader`][LogHeader] of the given file.
+///
+/// The compression of the file is detected automatically (see
+/// [`Compression::Auto`][Compression::Auto]). The events are neither parsed nor decompressed, so
+/// this is much faster than [`process_file`][super::process_file].
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let header = evtclib::scan_file("logfile.zevtc")?;
+/// println!("{:?} with {} players", header.encounter(), header.players().len());
+/// # Ok(()) }
+/// ```
+pub fn scan_file<P: AsRef<Path>>(path: P) -> Result<LogHeader, EvtcError> {
+    let file = File::open(path).map_err(Into::<raw::ParseError>::into)?;
+    let file_size = file
+        .metadata()
+        .map_err(Into::<raw::ParseError>::into)?
+        .len();
+    let partial = parse_partial(BufReader::new(file), Compression::Auto)?;
+    LogHeader::from_partial(&partial, file_size)
+}
+
+fn parse_partial<R: Read + Seek>(
+    mut input: R,
+    compression: Compression,
+) -> Result<raw::parser::PartialEvtc, EvtcError> {
+    let partial = match compression {
+        Compression::None => raw::parser::parse_partial_file(input)?,
+        Compression::Zip => raw::parse_partial_zip(input)?,
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => raw::parse_partial_gzip(input)?,
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => raw::parse_partial_zstd(input)?,
+        #[cfg(feature = "xz")]
+        Compression::Xz => raw::parse_partial_xz(input)?,
+        Compression::Auto => {
+            let detected = Compression::detect(&mut input)?;
+            return parse_partial(input, detected);
+        }
+        // Only reachable if some of the compression features are disabled.
+        #[allow(unreachable_patterns)]
+        other => return Err(EvtcError::UnsupportedCompression(other)),
+    };
+    Ok(partial)
+}
+
+/// A log file that was found by [`find_logs`][find_logs].
+///
+/// arcdps sorts the logs into folders, by default as `<boss>/<character>/<timestamp>.zevtc`. If
+/// the log is in such a folder structure, the names of the folders are available through
+/// [`boss_folder`][LogFile::boss_folder] and [`character_folder`][LogFile::character_folder].
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct LogFile {
+    path: PathBuf,
+    boss_folder: Option<String>,
+    character_folder: Option<String>,
+}
+
+impl LogFile {
+    fn new(root: &Path, path: PathBuf) -> LogFile {
+        let folders = path
+            .strip_prefix(root)
+            .ok()
+            .and_then(Path::parent)
+            .map(|parent| {
+                parent
+                    .iter()
+                    .map(|c| c.to_string_lossy().into_owned())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        LogFile {
+            path,
+            boss_folder: folders.first().cloned(),
+            character_folder: folders.get(1).cloned(),
+        }
+    }
+
+    /// The path of the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The name of the boss folder (the first folder below the root), if any.
+    ///
+    /// Note that this is the name that arcdps chose for the folder, which is not necessarily the
+    /// name of an [`Encounter`][Encounter]. Use [`scan`][LogFile::scan] to get the actual encounter.
+    pub fn boss_folder(&self) -> Option<&str> {
+        self.boss_folder.as_deref()
+    }
+
+    /// The name of the character folder (the second folder below the root), if any.
+    pub fn character_folder(&self) -> Option<&str> {
+        self.character_folder.as_deref()
+    }
+
+    /// The name of the file without the extension, which is the timestamp for logs saved by
+    /// arcdps.
+    pub fn name(&self) -> &str {
+        let name = self
+            .path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        let lowercase = name.to_lowercase();
+        // .zevtc also ends with .evtc, so we need to strip the longest matching suffix.
+        raw::archive::LOG_SUFFIXES
+            .iter()
+            .filter(|s| lowercase.ends_with(*s))
+            .max_by_key(|s| s.len())
+            .map(|s| &name[..name.len() - s.len()])
+            .unwrap_or(name)
+    }
+
+    /// Read the [`LogHeader`][LogHeader] of this file.
+    ///
+    /// This is a shorthand for [`scan_file`][scan_file].
+    pub fn scan(&self) -> Result<LogHeader, EvtcError> {
+        scan_file(&self.path)
+    }
+}
+
+/// An iterator over the log files in a directory tree.
+///
+/// Use [`find_logs`][find_logs] to create it.
+#[derive(Debug, Clone)]
+pub struct LogFiles {
+    root: PathBuf,
+    directories: Vec<PathBuf>,
+    files: VecDeque<PathBuf>,
+}
+
+impl Iterator for LogFiles {
+    type Item = Result<LogFile, EvtcError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(path) = self.files.pop_front() {
+                return Some(Ok(LogFile::new(&self.root, path)));
+            }
+            let directory = self.directories.pop()?;
+            if let Err(e) = self.visit(&directory) {
+                return Some(Err(raw::ParseError::from(e).into()));
+            }
+        }
+    }
+}
+
+impl LogFiles {
+    fn visit(&mut self, directory: &Path) -> std::io::Result<()> {
+        let mut entries = fs::read_dir(directory)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        // The directories are taken from the end of the stack, so we need to push them in reverse
+        // to visit them in order.
+        for entry in entries.iter().rev() {
+            if entry.file_type()?.is_dir() {
+                self.directories.push(entry.path());
+            }
+        }
+        for entry in entries {
+            let lowercase = entry.file_name().to_string_lossy().to_lowercase();
+            if entry.file_type()?.is_file()
+                && raw::archive::LOG_SUFFIXES
+                    .iter()
+                    .any(|s| lowercase.ends_with(s))
+            {
+                self.files.push_back(entry.path());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Find all log files in the given directory and its subdirectories.
+///
+/// Files with the extensions `.evtc`, `.zevtc` and `.evtc.zip` are considered to be logs. The
+/// files of a directory are returned (sorted by name) before the files of its subdirectories.
+/// Errors while reading a directory are returned, but do not stop the iteration.
+///
+/// Combined with [`LogFile::scan`][LogFile::scan], this can be used to quickly index a log folder
+/// (such as the `arcdps.cbtlogs` folder):
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// for file in evtclib::find_logs("arcdps.cbtlogs") {
+///     let file = file?;
+///     let header = file.scan()?;
+///     println!("{}: {:?}", file.path().display(), header.encounter());
+/// }
+/// # Ok(()) }
+/// ```
+pub fn find_logs<P: AsRef<Path>>(root: P) -> LogFiles {
+    let root = root.as_ref().to_owned();
+    LogFiles {
+        directories: vec![root.clone()],
+        root,
+        files: VecDeque::new(),
+    }
+}
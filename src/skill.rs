@@ -0,0 +1,94 @@
+//! Private module to contain the skill table entries.
+
+use std::ffi::CStr;
+
+use getset::CopyGetters;
+use num_traits::FromPrimitive;
+
+use super::raw;
+
+/// A skill from the skill table of a log.
+///
+/// arcdps writes the names of all skills that appear in a log to the skill table, so this can be
+/// used to turn the skill ids of events (such as
+/// [`EventKind::SkillUse`][super::EventKind::SkillUse] or
+/// [`EventKind::Physical`][super::EventKind::Physical]) into human readable names.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, CopyGetters)]
+pub struct Skill {
+    /// The id of the skill.
+    #[get_copy = "pub"]
+    id: u32,
+
+    name: String,
+
+    /// The custom skill that this skill represents, if any.
+    ///
+    /// Custom skills are skills that arcdps uses to represent actions that are not real skills
+    /// (such as dodging), or that are important but unnamed (such as resurrecting).
+    #[get_copy = "pub"]
+    custom: Option<raw::CbtCustomSkill>,
+}
+
+impl Skill {
+    /// The name of the skill.
+    ///
+    /// Invalid UTF-8 sequences in the name are replaced by the replacement character.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Check whether this skill is one of arcdps' custom skills.
+    pub fn is_custom(&self) -> bool {
+        self.custom.is_some()
+    }
+}
+
+impl From<&raw::Skill> for Skill {
+    fn from(raw_skill: &raw::Skill) -> Self {
+        let id = raw_skill.id as u32;
+        // Names that fill the whole buffer are not terminated.
+        let name = raw::cstr_up_to_nul(&raw_skill.name)
+            .map(CStr::to_bytes)
+            .unwrap_or(&raw_skill.name);
+        Skill {
+            id,
+            name: String::from_utf8_lossy(name).into_owned(),
+            custom: raw::CbtCustomSkill::from_u32(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_skill(id: i32, name: &[u8]) -> raw::Skill {
+        let mut skill = raw::Skill {
+            id,
+            ..Default::default()
+        };
+        skill.name[..name.len()].copy_from_slice(name);
+        skill
+    }
+
+    #[test]
+    fn test_from_raw() {
+        let skill = Skill::from(&raw_skill(5492, b"Fire Attunement"));
+        assert_eq!(skill.id(), 5492);
+        assert_eq!(skill.name(), "Fire Attunement");
+        assert!(!skill.is_custom());
+    }
+
+    #[test]
+    fn test_from_raw_custom() {
+        let skill = Skill::from(&raw_skill(65001, b"Dodge"));
+        assert_eq!(skill.custom(), Some(raw::CbtCustomSkill::Dodge));
+    }
+
+    #[test]
+    fn test_from_raw_unterminated() {
+        let skill = Skill::from(&raw_skill(1, &[b'a'; 64]));
+        assert_eq!(skill.name(), "a".repeat(64));
+    }
+}
@@ -0,0 +1,133 @@
+//! Tests for archives with multiple logs.
+
+use std::fs::{self, File};
+use std::io::{Cursor, Write};
+
+use evtclib::raw::{self, Archive, Limit, ParseError, ParseLimits};
+use evtclib::{EvtcError, ProcessingOptions};
+use zip::{write::FileOptions, ZipWriter};
+
+mod common;
+use common::unzipped;
+
+fn bundle() -> Cursor<Vec<u8>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let entries: &[(&str, Vec<u8>)] = &[
+        ("README.txt", b"weekly logs".to_vec()),
+        (
+            "vg/20200421.zevtc",
+            fs::read("tests/logs/vg-20200421.zevtc").unwrap(),
+        ),
+        (
+            "cairn.evtc",
+            unzipped("tests/logs/old-cairn-20180321.evtc.zip"),
+        ),
+        ("broken.evtc", b"EVTC20200101".to_vec()),
+    ];
+    for (name, data) in entries {
+        writer.start_file(*name, FileOptions::default()).unwrap();
+        writer.write_all(data).unwrap();
+    }
+    let mut output = writer.finish().unwrap();
+    output.set_position(0);
+    output
+}
+
+#[test]
+fn test_list_entries() {
+    let archive = Archive::new(bundle()).unwrap();
+    assert_eq!(archive.len(), 3);
+    assert_eq!(
+        archive.names().collect::<Vec<_>>(),
+        &["vg/20200421.zevtc", "cairn.evtc", "broken.evtc"]
+    );
+    assert_eq!(archive.index_of("cairn.evtc"), Some(1));
+    assert_eq!(archive.index_of("README.txt"), None);
+}
+
+#[test]
+fn test_parse_nested() {
+    let mut archive = Archive::new(bundle()).unwrap();
+    let evtc = archive.parse_by_name("vg/20200421.zevtc").unwrap();
+    let expected = raw::parse_zip(File::open("tests/logs/vg-20200421.zevtc").unwrap()).unwrap();
+    assert_eq!(evtc, expected);
+}
+
+#[test]
+fn test_parse_plain() {
+    let mut archive = Archive::new(bundle()).unwrap();
+    let evtc = archive.parse(1).unwrap();
+    let expected =
+        raw::parse_zip(File::open("tests/logs/old-cairn-20180321.evtc.zip").unwrap()).unwrap();
+    assert_eq!(evtc, expected);
+
+    let partial = archive.parse_partial(1).unwrap();
+    assert_eq!(partial.agents, expected.agents);
+}
+
+#[test]
+fn test_error_names_entry() {
+    let mut archive = Archive::new(bundle()).unwrap();
+    match archive.parse(2) {
+        Err(ParseError::ArchiveEntry { name, .. }) => assert_eq!(name, "broken.evtc"),
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    match archive.parse_by_name("missing.evtc") {
+        Err(ParseError::ArchiveEntry { name, .. }) => assert_eq!(name, "missing.evtc"),
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_process_entry() {
+    let mut archive = Archive::new(bundle()).unwrap();
+    let options = ProcessingOptions::new();
+    let log = evtclib::process_archive_entry(&mut archive, 0, &options).unwrap();
+    assert_eq!(log.encounter(), Some(evtclib::Encounter::ValeGuardian));
+
+    let error = evtclib::process_archive_entry(&mut archive, 2, &options).unwrap_err();
+    assert!(matches!(
+        error,
+        EvtcError::ParseError(ParseError::ArchiveEntry { .. })
+    ));
+    assert!(error.to_string().contains("broken.evtc"));
+}
+
+fn assert_size_exceeded<T>(result: Result<T, ParseError>) {
+    match result {
+        Err(ParseError::ArchiveEntry { source, .. }) => assert!(matches!(
+            *source,
+            ParseError::LimitExceeded {
+                limit: Limit::DecompressedSize,
+                ..
+            }
+        )),
+        Err(other) => panic!("unexpected error: {:?}", other),
+        Ok(_) => panic!("the limit was not enforced"),
+    }
+}
+
+#[test]
+fn test_limits() {
+    let limits = ParseLimits {
+        max_decompressed_size: Some(1024),
+        ..Default::default()
+    };
+    let mut archive = Archive::with_limits(bundle(), limits).unwrap();
+    // The nested archive is too large to be read into memory.
+    assert_size_exceeded(archive.parse(0));
+    assert_size_exceeded(archive.parse_partial(0));
+    assert_size_exceeded(archive.parse(1));
+
+    // Explicit limits take precedence over the ones of the archive.
+    assert!(archive
+        .parse_with_limits(1, &ParseLimits::default())
+        .is_ok());
+    let mut archive = Archive::new(bundle()).unwrap();
+    assert_size_exceeded(archive.parse_with_limits(0, &limits));
+    let options = ProcessingOptions::new().parse_limits(limits);
+    assert!(matches!(
+        evtclib::process_archive_entry(&mut archive, 0, &options),
+        Err(EvtcError::ParseError(ParseError::ArchiveEntry { .. }))
+    ));
+}
@@ -0,0 +1,75 @@
+//! Tests for the asynchronous parsing functions.
+#![cfg(feature = "async")]
+
+use evtclib::{raw, Compression, EvtcError};
+
+mod common;
+use common::{assert_same_log, unzipped};
+
+#[tokio::test]
+async fn test_parse_file_async() {
+    for path in &[
+        "tests/logs/vg-20200421.zevtc",
+        "tests/logs/old-cairn-20180321.evtc.zip",
+    ] {
+        let data = unzipped(path);
+        let expected = raw::parse_file(&data[..]).unwrap();
+        let parsed = raw::parse_file_async(&data[..]).await.unwrap();
+        assert_eq!(parsed, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_parse_partial_file_async() {
+    let data = unzipped("tests/logs/vg-20200421.zevtc");
+    let expected = raw::parser::parse_partial_file(&data[..]).unwrap();
+    // Only the header, agents and skills have to be present.
+    let section_end = data.len() - 64 * 20;
+    let parsed = raw::parse_partial_file_async(&data[..section_end])
+        .await
+        .unwrap();
+    assert_eq!(parsed, expected);
+}
+
+#[tokio::test]
+async fn test_reject_malformed_header() {
+    let result = raw::parse_partial_file_async(&b"not a log, but long enough"[..]).await;
+    assert!(matches!(result, Err(raw::ParseError::MalformedHeader)));
+
+    let result = raw::parse_partial_file_async(&b"EVTC"[..]).await;
+    assert!(matches!(result, Err(raw::ParseError::Io(_))));
+}
+
+#[tokio::test]
+async fn test_parse_zip_async() {
+    let data = std::fs::read("tests/logs/vg-20200421.zevtc").unwrap();
+    let expected = raw::parse_zip(std::io::Cursor::new(&data)).unwrap();
+    let parsed = raw::parse_zip_async(&data[..]).await.unwrap();
+    assert_eq!(parsed, expected);
+}
+
+#[tokio::test]
+async fn test_process_stream_async() {
+    let path = "tests/logs/vg-20200421.zevtc";
+    let expected = evtclib::process_file(path, Compression::Zip).unwrap();
+
+    let file = tokio::io::BufReader::new(tokio::fs::File::open(path).await.unwrap());
+    let log = evtclib::process_stream_async(file, Compression::Zip)
+        .await
+        .unwrap();
+    assert_same_log(&log, &expected);
+
+    let data = unzipped(path);
+    let log = evtclib::process_stream_async(&data[..], Compression::None)
+        .await
+        .unwrap();
+    assert_same_log(&log, &expected);
+
+    let log = evtclib::process_stream_async(&data[..], Compression::Auto)
+        .await
+        .unwrap();
+    assert_same_log(&log, &expected);
+
+    let result = evtclib::process_stream_async(&b"garbage"[..], Compression::Auto).await;
+    assert!(matches!(result, Err(EvtcError::UnknownFormat)));
+}
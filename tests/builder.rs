@@ -0,0 +1,100 @@
+//! Tests for the incremental building of logs.
+
+use evtclib::{raw, EventCategory, Log, LogBuilder, ProcessingOptions};
+
+#[macro_use]
+mod common;
+use common::{assert_same_log, parse};
+
+fn build(evtc: &raw::Evtc, options: &ProcessingOptions) -> Log {
+    let mut builder =
+        LogBuilder::with_options(&evtc.header, &evtc.agents, &evtc.skills, options).unwrap();
+    for event in &evtc.events {
+        builder.add_event(event.clone());
+    }
+    builder.add_unknown_statechanges(evtc.unknown_statechanges);
+    builder.build()
+}
+
+fn check_builder(path: &str) {
+    let evtc = parse(path);
+    let expected = evtclib::process(&evtc).unwrap();
+    let built = build(&evtc, &ProcessingOptions::default());
+    assert_same_log(&built, &expected);
+}
+
+log_tests!(check_builder {
+    test_builder_rev0: "tests/logs/old-cairn-20180321.evtc.zip",
+    test_builder_rev1: "tests/logs/vg-20200421.zevtc",
+    test_builder_masters: "tests/logs/mama-20200427.zevtc",
+    test_builder_wvw: "tests/logs/wvw-20211112.zevtc",
+    test_builder_dropped: "tests/logs/ankka-20220303.zevtc",
+});
+
+#[test]
+fn test_builder_options() {
+    let evtc = parse("tests/logs/vg-20200421.zevtc");
+    let options = ProcessingOptions::new()
+        .keep_only(&[EventCategory::State])
+        .retain_raw_events(true)
+        .resolve_agents(false);
+    let expected = evtclib::process_with_options(&evtc, &options).unwrap();
+    let built = build(&evtc, &options);
+    assert_same_log(&built, &expected);
+    assert_eq!(built.raw_events(), expected.raw_events());
+}
+
+#[test]
+fn test_builder_unknown_statechanges() {
+    let mut evtc = parse("tests/logs/vg-20200421.zevtc");
+    evtc.unknown_statechanges = 3;
+    let expected = evtclib::process(&evtc).unwrap();
+    let built = build(&evtc, &ProcessingOptions::default());
+    assert_eq!(built.diagnostics().unknown_statechanges(), 3);
+    assert_same_log(&built, &expected);
+}
+
+#[test]
+fn test_builder_snapshot() {
+    let evtc = parse("tests/logs/vg-20200421.zevtc");
+    let mut builder = LogBuilder::new(&evtc.header, &evtc.agents, &evtc.skills).unwrap();
+    let empty = builder.snapshot();
+    assert!(empty.events().is_empty());
+    assert_eq!(empty.agents().len(), builder.agents().len());
+
+    let half = evtc.events.len() / 2;
+    for event in &evtc.events[..half] {
+        builder.add_event(event.clone());
+    }
+    let snapshot = builder.snapshot();
+    assert_eq!(snapshot.events(), builder.events());
+    let partial = raw::Evtc {
+        events: evtc.events[..half].to_vec(),
+        ..evtc.clone()
+    };
+    assert_eq!(
+        snapshot.events(),
+        evtclib::process(&partial).unwrap().events()
+    );
+
+    for event in &evtc.events[half..] {
+        builder.add_event(event.clone());
+    }
+    assert_same_log(&builder.build(), &evtclib::process(&evtc).unwrap());
+}
+
+#[test]
+fn test_builder_add_agent() {
+    let evtc = parse("tests/logs/vg-20200421.zevtc");
+    let (first, rest) = evtc.agents.split_first().unwrap();
+    let mut builder = LogBuilder::new(&evtc.header, rest, &evtc.skills).unwrap();
+    assert!(builder.agents().iter().all(|a| a.addr() != first.addr));
+
+    builder.add_agent(first).unwrap();
+    builder.add_agent(first).unwrap();
+    assert_eq!(builder.agents().len(), evtc.agents.len());
+    assert!(builder
+        .agents()
+        .windows(2)
+        .all(|w| w[0].addr() < w[1].addr()));
+}
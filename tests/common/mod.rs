@@ -0,0 +1,54 @@
+//! Helpers that are shared between the integration tests.
+//!
+//! Every test crate only uses some of them, so the unused ones should not cause warnings.
+#![allow(dead_code, unused_macros)]
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use evtclib::{raw, Log};
+
+/// Define a test for each of the given logs, which calls the check function with its path.
+macro_rules! log_tests {
+    ($check:ident { $($name:ident: $log:expr,)* }) => {
+        $(
+            #[test]
+            fn $name() {
+                $check($log);
+            }
+        )*
+    };
+}
+
+/// Return the uncompressed contents of the zipped log at the given path.
+pub fn unzipped(path: &str) -> Vec<u8> {
+    let file = BufReader::new(File::open(path).unwrap());
+    let mut archive = zip::ZipArchive::new(file).unwrap();
+    let mut data = Vec::new();
+    archive.by_index(0).unwrap().read_to_end(&mut data).unwrap();
+    data
+}
+
+/// Parse the zipped log at the given path.
+pub fn parse(path: &str) -> raw::Evtc {
+    raw::parse_zip(BufReader::new(File::open(path).unwrap())).unwrap()
+}
+
+/// Assert that both logs contain the same data.
+pub fn assert_same_log(left: &Log, right: &Log) {
+    assert_eq!(left.agents(), right.agents());
+    assert_eq!(left.skills(), right.skills());
+    assert_eq!(left.events(), right.events());
+    assert_eq!(left.dropped_events(), right.dropped_events());
+    assert_eq!(left.metadata(), right.metadata());
+    assert_eq!(left.buff_database(), right.buff_database());
+    assert_eq!(left.skill_database(), right.skill_database());
+    assert_eq!(left.encounter_id(), right.encounter_id());
+    assert_eq!(left.diagnostics(), right.diagnostics());
+    for agent in left.agents() {
+        assert_eq!(
+            left.instance_periods(agent.addr()),
+            right.instance_periods(agent.addr())
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Tests for the compression detection.
+
+use std::fs::File;
+use std::io::{Cursor, Seek};
+
+use evtclib::{Compression, EvtcError};
+
+mod common;
+use common::unzipped;
+
+#[test]
+fn test_detect_zip() {
+    let mut file = File::open("tests/logs/vg-20200421.zevtc").unwrap();
+    assert_eq!(Compression::detect(&mut file).unwrap(), Compression::Zip);
+    assert_eq!(file.stream_position().unwrap(), 0);
+}
+
+#[test]
+fn test_detect_none() {
+    let mut input = Cursor::new(unzipped("tests/logs/vg-20200421.zevtc"));
+    assert_eq!(Compression::detect(&mut input).unwrap(), Compression::None);
+    assert_eq!(input.position(), 0);
+}
+
+#[test]
+fn test_detect_unknown() {
+    let mut input = Cursor::new(b"not a log".to_vec());
+    assert!(matches!(
+        Compression::detect(&mut input),
+        Err(EvtcError::UnknownFormat)
+    ));
+
+    let mut input = Cursor::new(Vec::new());
+    assert!(matches!(
+        Compression::detect(&mut input),
+        Err(EvtcError::UnknownFormat)
+    ));
+}
+
+#[test]
+fn test_process_auto() {
+    let zipped = evtclib::process_file("tests/logs/vg-20200421.zevtc", Compression::Auto).unwrap();
+    let data = unzipped("tests/logs/vg-20200421.zevtc");
+    let plain = evtclib::process_stream(Cursor::new(data), Compression::Auto).unwrap();
+    assert_eq!(zipped.events(), plain.events());
+    assert_eq!(zipped.agents(), plain.agents());
+}
+
+macro_rules! compression_test {
+    ($name:ident, $feature:literal, $compression:expr, $write:path, $parse:path) => {
+        #[cfg(feature = $feature)]
+        #[test]
+        fn $name() {
+            let data = unzipped("tests/logs/vg-20200421.zevtc");
+            let evtc = evtclib::raw::parse_file(&data[..]).unwrap();
+
+            let mut compressed = Vec::new();
+            $write(&evtc, &mut compressed).unwrap();
+            assert_eq!($parse(&compressed[..]).unwrap(), evtc);
+
+            let mut input = Cursor::new(compressed);
+            assert_eq!(Compression::detect(&mut input).unwrap(), $compression);
+            let log = evtclib::process_stream(input, Compression::Auto).unwrap();
+            assert_eq!(log.events(), evtclib::process(&evtc).unwrap().events());
+        }
+    };
+}
+
+compression_test!(
+    test_gzip,
+    "gzip",
+    Compression::Gzip,
+    evtclib::raw::write_gzip,
+    evtclib::raw::parse_gzip
+);
+compression_test!(
+    test_zstd,
+    "zstd",
+    Compression::Zstd,
+    evtclib::raw::write_zstd,
+    evtclib::raw::parse_zstd
+);
+compression_test!(
+    test_xz,
+    "xz",
+    Compression::Xz,
+    evtclib::raw::write_xz,
+    evtclib::raw::parse_xz
+);
+
+#[cfg(not(feature = "zstd"))]
+#[test]
+fn test_unsupported_compression() {
+    let mut input = Cursor::new(vec![0x28, 0xb5, 0x2f, 0xfd, 0, 0]);
+    assert_eq!(Compression::detect(&mut input).unwrap(), Compression::Zstd);
+    assert!(matches!(
+        evtclib::process_stream(input, Compression::Auto),
+        Err(EvtcError::UnsupportedCompression(Compression::Zstd))
+    ));
+}
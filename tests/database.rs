@@ -0,0 +1,83 @@
+//! Tests for the skill table and the buff and skill databases.
+
+use evtclib::database::StackingType;
+use evtclib::raw::BuffCategory;
+use evtclib::{Compression, EventKind};
+
+// Buff ids as used by the game.
+const MIGHT: u32 = 740;
+const BLEEDING: u32 = 736;
+const PROTECTION: u32 = 717;
+
+#[test]
+fn test_buff_database() {
+    let log = evtclib::process_file("tests/logs/adina-20200427.zevtc", Compression::Zip).unwrap();
+    let buffs = log.buff_database();
+
+    assert!(buffs.is_boon(MIGHT));
+    assert!(buffs.is_boon(PROTECTION));
+    assert!(buffs.is_condition(BLEEDING));
+    assert!(!buffs.is_condition(MIGHT));
+
+    let might = buffs.buff_info(MIGHT).unwrap();
+    assert_eq!(might.category(), Some(BuffCategory::Boon));
+    assert_eq!(might.stacking_type(), StackingType::StackingConditionalLoss);
+    assert_eq!(might.max_stacks(), 25);
+    assert!(!buffs.formulas(MIGHT).is_empty());
+}
+
+#[test]
+fn test_skill_database() {
+    let log = evtclib::process_file("tests/logs/adina-20200427.zevtc", Compression::Zip).unwrap();
+    let skills = log.skill_database();
+
+    assert!(!skills.is_empty());
+    let (skill_id, _) = skills
+        .iter()
+        .find(|(_, skill)| !skill.timings().is_empty())
+        .unwrap();
+    assert!(skills.skill_info(skill_id).is_some());
+}
+
+#[test]
+fn test_unknown_ids() {
+    let log = evtclib::process_file("tests/logs/adina-20200427.zevtc", Compression::Zip).unwrap();
+
+    assert!(log.buff_database().buff_info(u32::MAX).is_none());
+    assert!(log.buff_database().formulas(u32::MAX).is_empty());
+    assert!(!log.buff_database().is_boon(u32::MAX));
+    assert!(log.skill_database().timings(u32::MAX).is_empty());
+}
+
+#[test]
+fn test_skill_table() {
+    let log = evtclib::process_file("tests/logs/adina-20200427.zevtc", Compression::Zip).unwrap();
+
+    assert_eq!(log.skill_name(PROTECTION), Some("Protection"));
+    assert_eq!(log.skill_name(u32::MAX), None);
+
+    let dodge = log.skill_by_id(65001).unwrap();
+    assert_eq!(dodge.name(), "Dodge");
+    assert_eq!(dodge.custom(), Some(evtclib::raw::CbtCustomSkill::Dodge));
+
+    assert!(log.skills().windows(2).all(|w| w[0].id() < w[1].id()));
+}
+
+#[test]
+fn test_database_events_not_in_log() {
+    let log = evtclib::process_file("tests/logs/ankka-20220303.zevtc", Compression::Zip).unwrap();
+
+    assert!(!log.buff_database().is_empty());
+    assert!(!log.skill_database().is_empty());
+    assert!(!log.events().iter().any(|e| matches!(
+        e.kind(),
+        EventKind::BuffInfo { .. }
+            | EventKind::BuffFormula { .. }
+            | EventKind::SkillInfo { .. }
+            | EventKind::SkillTiming { .. }
+    )));
+    // The fight took a few minutes, the time fields of the database events would be far off.
+    let first = log.events().iter().map(|e| e.time()).min().unwrap();
+    let last = log.events().iter().map(|e| e.time()).max().unwrap();
+    assert!(last - first < 60 * 60 * 1000);
+}
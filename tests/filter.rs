@@ -0,0 +1,62 @@
+//! Tests for the filtered parsing of events.
+//!
+//! The filtered parsers should give the same results as parsing everything and filtering
+//! afterwards.
+
+use evtclib::raw::{self, CbtStateChange, EventClass, EventFilter};
+
+#[macro_use]
+mod common;
+use common::unzipped;
+
+fn filters() -> Vec<EventFilter> {
+    vec![
+        EventFilter::all(),
+        EventFilter::none(),
+        EventFilter::none()
+            .include_statechange(CbtStateChange::Reward)
+            .include_statechange(CbtStateChange::ChangeDead)
+            .include_statechange(CbtStateChange::MaxHealthUpdate),
+        EventFilter::none().include(EventClass::Activation),
+        EventFilter::none().include(EventClass::BuffRemove),
+        EventFilter::none().include(EventClass::Buff),
+        EventFilter::all().exclude(EventClass::Physical),
+        EventFilter::all().exclude_statechange(CbtStateChange::Position),
+    ]
+}
+
+fn check_filters(path: &str) {
+    let data = unzipped(path);
+    let complete = raw::parse_bytes(&data).unwrap();
+
+    for filter in filters() {
+        let expected = complete
+            .events
+            .iter()
+            .filter(|e| filter.matches(e))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let evtc = raw::parser::parse_file_filtered(&data[..], &filter).unwrap();
+        assert_eq!(evtc.events, expected, "{:?}", filter);
+        assert_eq!(evtc.agents, complete.agents);
+        assert_eq!(evtc.skills, complete.skills);
+
+        let evtc = raw::parser::parse_bytes_filtered(&data, &filter).unwrap();
+        assert_eq!(evtc.events, expected, "{:?}", filter);
+    }
+}
+
+log_tests!(check_filters {
+    test_filter_rev0: "tests/logs/old-cairn-20180321.evtc.zip",
+    test_filter_rev1: "tests/logs/vg-20200421.zevtc",
+    test_filter_wvw: "tests/logs/wvw-20211112.zevtc",
+});
+
+#[test]
+fn test_filter_reader_unknown_revision() {
+    assert!(matches!(
+        raw::EventReader::with_filter(&b""[..], 42, EventFilter::all()),
+        Err(raw::ParseError::UnknownRevision(42))
+    ));
+}
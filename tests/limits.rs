@@ -0,0 +1,219 @@
+//! Tests for the parse limits and the robustness against malformed files.
+
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+
+use evtclib::raw::{self, parser, EventFilter, Limit, ParseError, ParseLimits};
+use evtclib::{Compression, EvtcError, ProcessingOptions};
+
+mod common;
+use common::unzipped;
+
+const LOG: &str = "tests/logs/vg-20200421.zevtc";
+
+/// A header that claims to have the given amount of agents, followed by nothing.
+fn crafted_header(agent_count: u32) -> Vec<u8> {
+    let mut data = b"EVTC20200421\x01\x00\x00\x00".to_vec();
+    data.extend_from_slice(&agent_count.to_le_bytes());
+    data
+}
+
+fn assert_limit(result: Result<raw::Evtc, ParseError>, expected: Limit) {
+    match result {
+        Err(ParseError::LimitExceeded { limit, .. }) => assert_eq!(limit, expected),
+        other => panic!("expected {} to be exceeded, got {:?}", expected, other),
+    }
+}
+
+#[test]
+fn test_huge_agent_count() {
+    // Should fail because the file is too short, not because of the allocation.
+    let data = crafted_header(u32::MAX);
+    assert!(matches!(raw::parse_file(&data[..]), Err(ParseError::Io(_))));
+
+    let limits = ParseLimits {
+        max_agents: Some(1000),
+        ..Default::default()
+    };
+    assert_limit(
+        raw::parse_file_with_limits(&data[..], &limits),
+        Limit::Agents,
+    );
+    assert_limit(raw::parse_bytes_with_limits(&data, &limits), Limit::Agents);
+    assert_limit(
+        parser::parse_file_filtered_with_limits(&data[..], &EventFilter::all(), &limits),
+        Limit::Agents,
+    );
+}
+
+#[test]
+fn test_huge_skill_count() {
+    let mut data = crafted_header(0);
+    data.extend_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(raw::parse_file(&data[..]), Err(ParseError::Io(_))));
+
+    let limits = ParseLimits {
+        max_skills: Some(1000),
+        ..Default::default()
+    };
+    assert_limit(
+        raw::parse_file_with_limits(&data[..], &limits),
+        Limit::Skills,
+    );
+}
+
+#[test]
+fn test_limits_are_inclusive() {
+    let data = unzipped(LOG);
+    let evtc = raw::parse_file(&data[..]).unwrap();
+    let limits = ParseLimits {
+        max_agents: Some(evtc.header.agent_count),
+        max_skills: Some(evtc.skill_count),
+        max_events: Some(evtc.events.len() as u64),
+        max_decompressed_size: Some(data.len() as u64),
+    };
+    assert_eq!(
+        raw::parse_file_with_limits(&data[..], &limits).unwrap(),
+        evtc
+    );
+    let zipped = BufReader::new(File::open(LOG).unwrap());
+    assert_eq!(raw::parse_zip_with_limits(zipped, &limits).unwrap(), evtc);
+    assert_eq!(raw::parse_bytes_with_limits(&data, &limits).unwrap(), evtc);
+    assert_eq!(
+        parser::parse_bytes_filtered_with_limits(&data, &EventFilter::all(), &limits).unwrap(),
+        evtc
+    );
+}
+
+#[test]
+fn test_event_limit() {
+    let data = unzipped(LOG);
+    let limits = ParseLimits {
+        max_events: Some(100),
+        ..Default::default()
+    };
+    assert_limit(
+        raw::parse_file_with_limits(&data[..], &limits),
+        Limit::Events,
+    );
+    assert_limit(raw::parse_bytes_with_limits(&data, &limits), Limit::Events);
+    assert_limit(
+        parser::parse_file_filtered_with_limits(&data[..], &EventFilter::all(), &limits),
+        Limit::Events,
+    );
+    assert_limit(
+        parser::parse_bytes_filtered_with_limits(&data, &EventFilter::all(), &limits),
+        Limit::Events,
+    );
+    #[cfg(feature = "rayon")]
+    assert_limit(
+        raw::parse_bytes_parallel_with_limits(&data, &limits),
+        Limit::Events,
+    );
+
+    // Events that do not match the filter do not count towards the limit.
+    assert!(parser::parse_bytes_filtered_with_limits(&data, &EventFilter::none(), &limits).is_ok());
+}
+
+#[test]
+fn test_decompressed_size_limit() {
+    let data = unzipped(LOG);
+    let limits = ParseLimits {
+        max_decompressed_size: Some(data.len() as u64 - 1),
+        ..Default::default()
+    };
+    let zipped = BufReader::new(File::open(LOG).unwrap());
+    assert_limit(
+        raw::parse_zip_with_limits(zipped, &limits),
+        Limit::DecompressedSize,
+    );
+
+    // The header alone is already too big.
+    let limits = ParseLimits {
+        max_decompressed_size: Some(10),
+        ..Default::default()
+    };
+    let zipped = BufReader::new(File::open(LOG).unwrap());
+    assert_limit(
+        raw::parse_zip_with_limits(zipped, &limits),
+        Limit::DecompressedSize,
+    );
+}
+
+#[test]
+fn test_processing_options_limits() {
+    let options = ProcessingOptions::new().parse_limits(ParseLimits {
+        max_events: Some(100),
+        ..Default::default()
+    });
+    let result = evtclib::process_file_with_options(LOG, Compression::Zip, &options);
+    assert!(matches!(
+        result,
+        Err(EvtcError::ParseError(ParseError::LimitExceeded {
+            limit: Limit::Events,
+            maximum: 100
+        }))
+    ));
+}
+
+/// A small xorshift generator, so that the mutations are reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// Corrupt a few bytes of a valid log and make sure that nothing panics.
+///
+/// This is a cheap version of the fuzz targets in `fuzz/`, which can be run without any additional
+/// tooling.
+#[test]
+fn test_mutated_logs() {
+    let data = unzipped(LOG);
+    // Only the start of the file, so that the test stays fast.
+    let data = &data[..data.len().min(64 * 1024)];
+    let limits = ParseLimits {
+        max_agents: Some(1000),
+        max_skills: Some(10_000),
+        max_events: Some(100_000),
+        max_decompressed_size: Some(16 * 1024 * 1024),
+    };
+    let options = ProcessingOptions::new().parse_limits(limits);
+    let mut rng = Rng(0x5eed_1234_abcd_ef01);
+
+    for _ in 0..500 {
+        let mut mutated = data.to_vec();
+        for _ in 0..=rng.below(8) {
+            let index = rng.below(mutated.len());
+            mutated[index] = rng.next() as u8;
+        }
+        mutated.truncate(rng.below(mutated.len()) + 1);
+
+        let _ = raw::parse_file(&mutated[..]);
+        if let Ok(evtc) = raw::parse_file_with_limits(&mutated[..], &limits) {
+            let _ = evtclib::process(&evtc);
+        }
+        let _ = evtclib::process_stream_with_options(
+            Cursor::new(&mutated),
+            Compression::Auto,
+            &options,
+        );
+    }
+
+    let zipped = std::fs::read(LOG).unwrap();
+    for _ in 0..100 {
+        let mut mutated = zipped.clone();
+        let index = rng.below(mutated.len());
+        mutated[index] = rng.next() as u8;
+        let _ = raw::parse_zip_with_limits(Cursor::new(&mutated), &limits);
+    }
+}
@@ -0,0 +1,236 @@
+//! Tests for the resolution of master agents and instance ids.
+
+use std::fs::File;
+use std::io::BufReader;
+
+use evtclib::raw::{self, parser::Header, CbtEvent};
+use evtclib::{Agent, Log, LogBuilder, ProcessingOptions};
+
+fn agent(addr: u64) -> raw::Agent {
+    let mut name = [0; 64];
+    name[..6].copy_from_slice(b"Minion");
+    raw::Agent {
+        addr,
+        prof: 1,
+        is_elite: u32::MAX,
+        toughness: 0,
+        concentration: 0,
+        healing: 0,
+        condition: 0,
+        name,
+    }
+}
+
+fn event(time: u64, src_agent: u64, src_instid: u16, src_master_instid: u16) -> CbtEvent {
+    CbtEvent {
+        time,
+        src_agent,
+        src_instid,
+        src_master_instid,
+        value: 1,
+        ..Default::default()
+    }
+}
+
+fn process(agents: &[u64], events: Vec<CbtEvent>) -> Log {
+    let evtc = raw::Evtc {
+        header: Header {
+            arcdps_build: "20211112".into(),
+            combat_id: 1,
+            agent_count: agents.len() as u32,
+            revision: 1,
+        },
+        skill_count: 0,
+        agents: agents.iter().copied().map(agent).collect(),
+        skills: Vec::new(),
+        events,
+        unknown_statechanges: 0,
+    };
+    evtclib::process(&evtc).unwrap()
+}
+
+fn masters(agents: &[u64], events: Vec<CbtEvent>) -> Vec<(u64, Option<u64>)> {
+    process(agents, events)
+        .agents()
+        .iter()
+        .map(|a| (a.addr(), a.master_agent()))
+        .collect()
+}
+
+/// Feed the events to a [`LogBuilder`] and return the master after every event.
+fn live_masters(agents: &[u64], events: Vec<CbtEvent>, minion: u64) -> Vec<Option<u64>> {
+    let header = Header {
+        arcdps_build: "20211112".into(),
+        combat_id: 1,
+        agent_count: agents.len() as u32,
+        revision: 1,
+    };
+    let raw_agents = agents.iter().copied().map(agent).collect::<Vec<_>>();
+    let mut builder = LogBuilder::new(&header, &raw_agents, &[]).unwrap();
+    events
+        .into_iter()
+        .map(|event| {
+            builder.add_event(event);
+            let minion = builder.agents().iter().find(|a| a.addr() == minion);
+            minion.unwrap().master_agent()
+        })
+        .collect()
+}
+
+#[test]
+fn test_reused_instance_id() {
+    // 1 and 2 both have the instance id 5, but at different times.
+    let result = masters(
+        &[1, 2, 10, 20],
+        vec![
+            event(10, 1, 5, 0),
+            event(15, 10, 50, 5),
+            event(20, 1, 5, 0),
+            event(30, 2, 5, 0),
+            event(35, 20, 51, 5),
+            event(40, 2, 5, 0),
+        ],
+    );
+    assert_eq!(
+        result,
+        vec![(1, None), (2, None), (10, Some(1)), (20, Some(2))]
+    );
+}
+
+#[test]
+fn test_overlapping_instance_id() {
+    // 1 is still aware when 2 gets the same instance id, so the minion belongs to the newer one.
+    let result = masters(
+        &[1, 2, 10],
+        vec![
+            event(10, 1, 5, 0),
+            event(30, 2, 5, 0),
+            event(35, 10, 50, 5),
+            event(40, 2, 5, 0),
+            event(50, 1, 5, 0),
+        ],
+    );
+    assert_eq!(result, vec![(1, None), (2, None), (10, Some(2))]);
+}
+
+#[test]
+fn test_master_outside_of_aware_time() {
+    // The master has to be aware at the time of the event.
+    let result = masters(
+        &[1, 10],
+        vec![event(10, 1, 5, 0), event(20, 1, 5, 0), event(25, 10, 50, 5)],
+    );
+    assert_eq!(result, vec![(1, None), (10, None)]);
+}
+
+#[test]
+fn test_master_at_period_boundaries() {
+    // The first and last event of a period belong to it, so even a single event is enough.
+    for (master_time, minion_time) in [(10, 10), (20, 20), (15, 15)] {
+        let result = masters(
+            &[1, 10],
+            vec![
+                event(10, 2, 7, 0),
+                event(master_time, 1, 5, 0),
+                event(minion_time, 10, 50, 5),
+                event(20, 2, 7, 0),
+            ],
+        );
+        assert_eq!(result, vec![(1, None), (10, Some(1))]);
+    }
+}
+
+#[test]
+fn test_agent_by_instance_id_at() {
+    let log = process(
+        &[1, 2],
+        vec![
+            event(10, 1, 5, 0),
+            event(20, 1, 5, 0),
+            event(30, 2, 5, 0),
+            event(40, 2, 5, 0),
+        ],
+    );
+    let addr = |time| log.agent_by_instance_id_at(5, time).map(Agent::addr);
+    assert_eq!(addr(5), None);
+    assert_eq!(addr(10), Some(1));
+    assert_eq!(addr(15), Some(1));
+    assert_eq!(addr(20), Some(1));
+    assert_eq!(addr(25), None);
+    assert_eq!(addr(30), Some(2));
+    assert_eq!(addr(40), Some(2));
+    assert_eq!(addr(45), None);
+    assert_eq!(log.agent_by_instance_id_at(6, 15), None);
+}
+
+#[test]
+fn test_instance_periods() {
+    // The agent changes its instance id and then gets the old one back.
+    let log = process(
+        &[1],
+        vec![
+            event(10, 1, 5, 0),
+            event(15, 1, 5, 0),
+            event(20, 1, 6, 0),
+            event(30, 1, 5, 0),
+        ],
+    );
+    let periods = log
+        .instance_periods(1)
+        .iter()
+        .map(|p| (p.instance_id(), p.first_aware(), p.last_aware()))
+        .collect::<Vec<_>>();
+    assert_eq!(periods, vec![(5, 10, 15), (6, 20, 20), (5, 30, 30)]);
+    assert_eq!(log.agent_by_instance_id_at(6, 20).map(Agent::addr), Some(1));
+    assert_eq!(log.agent_by_instance_id_at(6, 25), None);
+    assert!(log.instance_periods(2).is_empty());
+}
+
+#[test]
+fn test_instance_ids_of_events() {
+    let file = BufReader::new(File::open("tests/logs/mama-20200427.zevtc").unwrap());
+    let evtc = raw::parse_zip(file).unwrap();
+    for options in [
+        ProcessingOptions::new(),
+        #[cfg(feature = "rayon")]
+        ProcessingOptions::new().parallel(true),
+    ] {
+        let log = evtclib::process_with_options(&evtc, &options).unwrap();
+        for event in &evtc.events {
+            if event.is_statechange != raw::CbtStateChange::None
+                || log.agent_by_addr(event.src_agent).is_none()
+            {
+                continue;
+            }
+            let agent = log
+                .agent_by_instance_id_at(event.src_instid, event.time)
+                .unwrap();
+            assert_eq!(agent.addr(), event.src_agent, "{:?}", event);
+            if event.src_master_instid != 0 {
+                let master = log.agent_by_instance_id_at(event.src_master_instid, event.time);
+                assert_eq!(master.map(Agent::addr), agent.master_agent(), "{:?}", event);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_builder_current_master() {
+    let result = live_masters(
+        &[1, 2, 10],
+        vec![
+            event(10, 1, 5, 0),
+            event(15, 10, 50, 5),
+            // 1 gives up the instance id, so the minion keeps its master until 2 gets it.
+            event(20, 1, 6, 0),
+            event(25, 10, 50, 5),
+            event(30, 2, 5, 0),
+            event(35, 10, 50, 5),
+        ],
+        10,
+    );
+    assert_eq!(
+        result,
+        vec![None, Some(1), Some(1), Some(1), Some(1), Some(2)]
+    );
+}
@@ -0,0 +1,364 @@
+//! Tests for the merging of logs from multiple points of view.
+//!
+//! The points of view are simulated by splitting a log into two overlapping parts, which get
+//! their own agent addresses and clock.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use evtclib::raw::{self, parser::Header, CbtEvent, CbtStateChange};
+use evtclib::{
+    Agent, AgentKind, Event, EventCategory, EventKind, Log, MergeError, ProcessingOptions,
+};
+
+mod common;
+use common::parse;
+
+const SHIFT: u64 = 123_456;
+const ADDR_OFFSET: u64 = 0x1_0000_0000;
+
+/// The species id of the adds in the synthetic logs.
+const ADD_SPECIES: u32 = 4242;
+/// The server timestamp of the log start in the synthetic logs.
+const SERVER_START: i32 = 1_600_000_000;
+
+/// Create a log that only contains the given range of events (and the metadata), with shifted
+/// times and agent addresses.
+fn point_of_view(evtc: &raw::Evtc, range: Range<usize>, time_shift: u64, addr_offset: u64) -> Log {
+    let addrs = evtc
+        .agents
+        .iter()
+        .map(|a| (a.addr, a.addr + addr_offset))
+        .collect::<HashMap<_, _>>();
+    let map = |addr: u64| addrs.get(&addr).copied().unwrap_or(addr);
+
+    let mut evtc = evtc.clone();
+    for agent in &mut evtc.agents {
+        agent.addr += addr_offset;
+    }
+    evtc.events = evtc
+        .events
+        .iter()
+        .enumerate()
+        .filter(|(i, e)| {
+            range.contains(i)
+                || matches!(
+                    e.is_statechange,
+                    CbtStateChange::LogStart | CbtStateChange::LogEnd
+                )
+        })
+        .map(|(_, e)| {
+            let mut event = e.clone();
+            event.time += time_shift;
+            event.src_agent = map(event.src_agent);
+            if matches!(
+                event.is_statechange,
+                CbtStateChange::None
+                    | CbtStateChange::AttackTarget
+                    | CbtStateChange::BuffInitial
+                    | CbtStateChange::Effect
+                    | CbtStateChange::Effect2
+            ) {
+                event.dst_agent = map(event.dst_agent);
+            }
+            event
+        })
+        .collect();
+    evtclib::process(&evtc).unwrap()
+}
+
+/// Replace the agent addresses in the debug output of the event by the names of the agents.
+///
+/// Agents of the same kind cannot be told apart if they do not interact with players, so they
+/// might get each other's addresses when merging.
+fn describe(log: &Log, event: &Event) -> String {
+    let text = format!("{:?}", event.kind());
+    let mut result = String::new();
+    let mut rest = text.as_str();
+    while let Some(index) = ["addr: ", "Agent("]
+        .iter()
+        .filter_map(|pattern| rest.find(pattern))
+        .min()
+    {
+        let (head, tail) = rest.split_at(index + 6);
+        let end = tail
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let addr = tail[..end].parse::<u64>().unwrap();
+        let name = match log.agent_by_addr(addr).map(Agent::kind) {
+            Some(AgentKind::Player(player)) => player.account_name().to_string(),
+            Some(AgentKind::Character(character)) => character.name().to_string(),
+            Some(AgentKind::Gadget(gadget)) => gadget.name().to_string(),
+            None => addr.to_string(),
+        };
+        result.push_str(head);
+        result.push_str(&name);
+        rest = &tail[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+fn raw_agent(addr: u64, prof: u32, is_elite: u32, name: &[u8]) -> raw::Agent {
+    let mut raw_name = [0; 64];
+    raw_name[..name.len()].copy_from_slice(name);
+    raw::Agent {
+        addr,
+        prof,
+        is_elite,
+        toughness: 0,
+        concentration: 0,
+        healing: 0,
+        condition: 0,
+        name: raw_name,
+    }
+}
+
+/// Create a synthetic log with a player (at `player`) and the adds at the given addresses, all of
+/// the same species.
+///
+/// The log starts at the given time, and every hit is a `(time, add)` pair of an add hitting the
+/// player with the same skill and damage.
+fn synthetic_log(
+    player: u64,
+    adds: &[u64],
+    start: u64,
+    hits: &[(u64, u64)],
+    options: &ProcessingOptions,
+) -> Log {
+    let mut agents = vec![raw_agent(player, 1, 0, b"Player\0:Account.1234\0\0")];
+    agents.extend(
+        adds.iter()
+            .map(|&addr| raw_agent(addr, ADD_SPECIES, u32::MAX, b"Add")),
+    );
+    let mut events = vec![CbtEvent {
+        time: start,
+        value: SERVER_START,
+        is_statechange: CbtStateChange::LogStart,
+        ..Default::default()
+    }];
+    events.extend(hits.iter().map(|&(time, add)| CbtEvent {
+        time,
+        src_agent: add,
+        dst_agent: player,
+        value: 100,
+        skillid: 1234,
+        iff: raw::IFF::Foe,
+        ..Default::default()
+    }));
+    let evtc = raw::Evtc {
+        header: Header {
+            arcdps_build: "20211112".into(),
+            combat_id: 1,
+            agent_count: agents.len() as u32,
+            revision: 1,
+        },
+        skill_count: 0,
+        agents,
+        skills: Vec::new(),
+        events,
+        unknown_statechanges: 0,
+    };
+    evtclib::process_with_options(&evtc, options).unwrap()
+}
+
+/// Return the sources of the physical hits in the log.
+fn hit_sources(log: &Log) -> Vec<u64> {
+    log.events()
+        .iter()
+        .filter_map(|e| match *e.kind() {
+            EventKind::Physical {
+                source_agent_addr, ..
+            } => Some(source_agent_addr),
+            _ => None,
+        })
+        .collect()
+}
+
+fn sorted_events(log: &Log) -> Vec<(u64, String)> {
+    let mut events = log
+        .events()
+        .iter()
+        .filter(|e| e.kind().category() != EventCategory::Metadata)
+        .map(|e| (e.time(), describe(log, e)))
+        .collect::<Vec<_>>();
+    events.sort();
+    events
+}
+
+fn check_merge(path: &str) {
+    check_merge_evtc(parse(path));
+}
+
+fn check_merge_evtc(evtc: raw::Evtc) {
+    let expected = evtclib::process(&evtc).unwrap();
+    let count = evtc.events.len();
+    let first = point_of_view(&evtc, 0..count * 7 / 10, 0, 0);
+    let second = point_of_view(&evtc, count * 3 / 10..count, SHIFT, ADDR_OFFSET);
+    assert!(first.is_same_fight(&second));
+
+    let merged = evtclib::merge_logs(&[first, second]).unwrap();
+    assert_eq!(merged.agents().len(), expected.agents().len());
+    for player in expected.players() {
+        assert_eq!(merged.agent_by_addr(player.addr()), Some(player.erase()));
+    }
+    assert_eq!(sorted_events(&merged), sorted_events(&expected));
+    assert!(merged
+        .events()
+        .windows(2)
+        .all(|w| w[0].time() <= w[1].time()));
+}
+
+#[test]
+fn test_merge_vg() {
+    check_merge("tests/logs/vg-20200421.zevtc");
+}
+
+#[test]
+fn test_merge_mama() {
+    check_merge("tests/logs/mama-20200427.zevtc");
+}
+
+#[test]
+fn test_merge_wvw() {
+    check_merge("tests/logs/wvw-20211112.zevtc");
+}
+
+#[test]
+fn test_merge_without_timestamps() {
+    let mut evtc = parse("tests/logs/vg-20200421.zevtc");
+    evtc.events.retain(|e| {
+        !matches!(
+            e.is_statechange,
+            CbtStateChange::LogStart | CbtStateChange::LogEnd
+        )
+    });
+    check_merge_evtc(evtc);
+}
+
+#[test]
+fn test_merge_single() {
+    let log = evtclib::process(&parse("tests/logs/vg-20200421.zevtc")).unwrap();
+    let merged = evtclib::merge_logs(std::slice::from_ref(&log)).unwrap();
+    assert_eq!(merged.agents(), log.agents());
+    assert_eq!(merged.events(), log.events());
+}
+
+#[test]
+fn test_merge_same_log() {
+    let log = evtclib::process(&parse("tests/logs/vg-20200421.zevtc")).unwrap();
+    let merged = evtclib::merge_logs(&[log.clone(), log.clone()]).unwrap();
+    assert_eq!(merged.agents(), log.agents());
+    // Nothing was added, so the events are left as they are.
+    assert_eq!(merged.events(), log.events());
+    assert_eq!(merged.span(), log.span());
+    assert_eq!(merged.buff_database(), log.buff_database());
+    assert_eq!(merged.skill_database(), log.skill_database());
+    assert_eq!(
+        merged.diagnostics().total_events(),
+        2 * log.diagnostics().total_events()
+    );
+    assert_eq!(
+        merged.dropped_events().len(),
+        2 * log.dropped_events().len()
+    );
+}
+
+#[test]
+fn test_merge_point_of_view() {
+    let evtc = parse("tests/logs/vg-20200421.zevtc");
+    let first = evtclib::process(&evtc).unwrap();
+    let pov = first.metadata().pov_agent_addr().unwrap();
+    let other = first.players().find(|p| p.addr() != pov).unwrap().addr();
+    let mut second = evtc.clone();
+    for event in &mut second.events {
+        if event.is_statechange == CbtStateChange::PointOfView {
+            event.src_agent = other;
+        }
+    }
+    let second = evtclib::process(&second).unwrap();
+
+    let merged = evtclib::merge_logs(&[first.clone(), second]).unwrap();
+    let povs = merged
+        .events()
+        .iter()
+        .filter_map(|e| match *e.kind() {
+            EventKind::PointOfView { agent_addr } => Some(agent_addr),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(povs, vec![pov, other]);
+    assert_eq!(merged.metadata(), first.metadata());
+}
+
+#[test]
+fn test_different_fights() {
+    let vg = evtclib::process(&parse("tests/logs/vg-20200421.zevtc")).unwrap();
+    let gorseval = evtclib::process(&parse("tests/logs/gorseval-20200421.zevtc")).unwrap();
+    assert!(!vg.is_same_fight(&gorseval));
+    assert!(matches!(
+        evtclib::merge_logs(&[vg, gorseval]),
+        Err(MergeError::DifferentFight(1))
+    ));
+    assert!(matches!(evtclib::merge_logs(&[]), Err(MergeError::NoLogs)));
+}
+
+#[test]
+fn test_merged_time_order() {
+    let evtc = parse("tests/logs/vg-20200421.zevtc");
+    let count = evtc.events.len();
+    let first = point_of_view(&evtc, 0..count * 6 / 10, SHIFT, 0);
+    let second = point_of_view(&evtc, count * 4 / 10..count, 0, ADDR_OFFSET);
+    let merged = evtclib::merge_logs(&[first.clone(), second]).unwrap();
+    // The times of the first log are kept.
+    let boss = merged.boss();
+    let expected = evtclib::process(&evtc).unwrap();
+    assert_eq!(boss.first_aware(), expected.boss().first_aware() + SHIFT);
+    assert_eq!(boss.last_aware(), expected.boss().last_aware() + SHIFT);
+    assert!(merged.events().iter().map(Event::time).all(|t| t >= SHIFT));
+    // The events of the first log keep their order.
+    let mut remaining = first.events().iter().peekable();
+    for event in merged.events() {
+        remaining.next_if(|e| *e == event);
+    }
+    assert!(remaining.next().is_none());
+}
+
+#[test]
+fn test_merge_same_species_adds() {
+    let options = ProcessingOptions::default();
+    // The first log only sees one of the adds, the second one sees both, hitting at the same time.
+    // The clock of the second log is 500 ms behind.
+    let first = synthetic_log(1, &[2], 1000, &[(2000, 2)], &options);
+    let second = synthetic_log(11, &[12, 13], 500, &[(1500, 13), (1500, 12)], &options);
+    let merged = evtclib::merge_logs(&[first, second]).unwrap();
+
+    assert_eq!(merged.agents().len(), 3);
+    let mut sources = hit_sources(&merged);
+    sources.sort_unstable();
+    sources.dedup();
+    assert_eq!(sources.len(), 2, "both adds have to keep their hit");
+    assert!(merged
+        .events()
+        .iter()
+        .filter(|e| matches!(e.kind(), EventKind::Physical { .. }))
+        .all(|e| e.time() == 2000));
+}
+
+#[test]
+fn test_merge_without_metadata_events() {
+    // Without the LogStart events, the repeated hits could not be aligned.
+    let options = ProcessingOptions::default().exclude(EventCategory::Metadata);
+    let hits = [(2000, 2), (3000, 2)];
+    let first = synthetic_log(1, &[2], 1000, &hits, &options);
+    let hits = [(1500, 12), (2500, 12)];
+    let second = synthetic_log(11, &[12], 500, &hits, &options);
+    assert!(first
+        .events()
+        .iter()
+        .all(|e| !matches!(e.kind(), EventKind::LogStart { .. })));
+
+    let merged = evtclib::merge_logs(&[first.clone(), second]).unwrap();
+    assert_eq!(merged.agents(), first.agents());
+    assert_eq!(merged.events(), first.events());
+}
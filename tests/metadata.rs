@@ -0,0 +1,135 @@
+//! Tests for the log metadata and the processing diagnostics.
+
+use std::convert::TryFrom;
+
+use evtclib::event::FromRawEventError;
+use evtclib::raw::{self, CbtStateChange, Language};
+use evtclib::{Compression, Event, ProcessingOptions};
+
+mod common;
+use common::parse;
+
+#[test]
+fn test_metadata() {
+    let log = evtclib::process_file("tests/logs/vg-20200421.zevtc", Compression::Zip).unwrap();
+    let metadata = log.metadata();
+
+    assert_eq!(metadata.arcdps_build(), "20200414");
+    assert_eq!(metadata.revision(), 1);
+    assert_eq!(metadata.gw2_build(), Some(103340));
+    assert_eq!(metadata.language(), Some(Language::Eng));
+    assert_eq!(metadata.map_id(), Some(1062));
+    assert_eq!(metadata.shard_id(), Some(2007));
+    assert_eq!(metadata.server_start_timestamp(), Some(1587502358));
+    assert_eq!(metadata.local_start_timestamp(), Some(1587502359));
+    assert_eq!(metadata.server_end_timestamp(), Some(1587502585));
+    assert_eq!(metadata.local_end_timestamp(), Some(1587502586));
+
+    let pov = log
+        .agent_by_addr(metadata.pov_agent_addr().unwrap())
+        .unwrap();
+    assert!(pov.as_player().is_some());
+}
+
+#[test]
+fn test_metadata_shortcuts() {
+    let log = evtclib::process_file("tests/logs/vg-20200421.zevtc", Compression::Zip).unwrap();
+
+    assert_eq!(log.build_id(), log.metadata().gw2_build());
+    assert_eq!(
+        log.local_start_timestamp(),
+        log.metadata().local_start_timestamp()
+    );
+    assert_eq!(
+        log.local_end_timestamp(),
+        log.metadata().local_end_timestamp()
+    );
+}
+
+#[test]
+fn test_dropped_events() {
+    let options = ProcessingOptions::new().retain_raw_events(true);
+    let log = evtclib::process_file_with_options(
+        "tests/logs/ankka-20220303.zevtc",
+        Compression::Zip,
+        &options,
+    )
+    .unwrap();
+    let diagnostics = log.diagnostics();
+
+    assert_eq!(diagnostics.dropped_events(), log.dropped_events().len());
+    // The buff and skill information is only collected into the databases.
+    let database_events = log.raw_events().unwrap().iter().filter(|e| {
+        matches!(
+            e.is_statechange,
+            CbtStateChange::BuffInfo
+                | CbtStateChange::BuffFormula
+                | CbtStateChange::SkillInfo
+                | CbtStateChange::SkillTiming
+        )
+    });
+    assert_eq!(
+        diagnostics.converted_events(),
+        log.events().len() + database_events.count()
+    );
+    assert_eq!(
+        diagnostics.dropped_by_reason()
+            [&FromRawEventError::UnknownStateChange(CbtStateChange::Extension)],
+        log.dropped_events()
+            .iter()
+            .filter(|e| e.raw().is_statechange == CbtStateChange::Extension)
+            .count()
+    );
+    assert!(log
+        .dropped_events()
+        .iter()
+        .all(|e| Event::try_from(e.raw()).as_ref().err() == Some(e.error())));
+}
+
+#[test]
+fn test_lossless_log() {
+    let log = evtclib::process_file("tests/logs/wvw-20211112.zevtc", Compression::Zip).unwrap();
+    assert!(log.diagnostics().is_lossless());
+    assert!(log.dropped_events().is_empty());
+}
+
+#[test]
+fn test_unknown_statechanges() {
+    let evtc = parse("tests/logs/wvw-20211112.zevtc");
+    assert_eq!(evtc.unknown_statechanges, 0);
+    let mut data = Vec::new();
+    raw::write_file(&evtc, &mut data).unwrap();
+    // Give the last event a statechange that evtclib does not know.
+    // Events have 64 bytes, and the statechange is at offset 56.
+    let statechange = data.len() - 64 + 56;
+    data[statechange] = 250;
+
+    let parsed = raw::parse_file(&data[..]).unwrap();
+    assert_eq!(parsed.unknown_statechanges, 1);
+    assert_eq!(parsed.events.len(), evtc.events.len() - 1);
+    assert_eq!(raw::parse_bytes(&data).unwrap(), parsed);
+
+    let log = evtclib::process(&parsed).unwrap();
+    let diagnostics = log.diagnostics();
+    assert_eq!(diagnostics.unknown_statechanges(), 1);
+    assert_eq!(diagnostics.total_events(), parsed.events.len());
+    assert!(!diagnostics.is_lossless());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_old_log() {
+    let log = evtclib::process(&parse("tests/logs/vg-20200421.zevtc")).unwrap();
+    let mut value = serde_json::to_value(&log).unwrap();
+    // Only keep the fields that older versions of evtclib serialized.
+    value
+        .as_object_mut()
+        .unwrap()
+        .retain(|key, _| ["agents", "events", "boss_id"].contains(&key.as_str()));
+    let old: evtclib::Log = serde_json::from_value(value).unwrap();
+    assert_eq!(old.agents(), log.agents());
+    assert_eq!(old.events(), log.events());
+    assert_eq!(old.encounter_id(), log.encounter_id());
+    assert!(old.dropped_events().is_empty());
+    assert!(old.skills().is_empty());
+}
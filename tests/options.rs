@@ -0,0 +1,101 @@
+//! Tests for the processing options.
+
+use evtclib::{Compression, EliteSpec, EventCategory, EvtcError, ProcessingOptions, Profession};
+
+mod common;
+use common::parse;
+
+#[test]
+fn test_default_options() {
+    let evtc = parse("tests/logs/vg-20200421.zevtc");
+    let log = evtclib::process(&evtc).unwrap();
+    let with_options = evtclib::process_with_options(&evtc, &ProcessingOptions::new()).unwrap();
+    assert_eq!(log.events(), with_options.events());
+    assert_eq!(log.agents(), with_options.agents());
+    assert!(log.raw_events().is_none());
+}
+
+#[test]
+fn test_keep_only() {
+    let options =
+        ProcessingOptions::new().keep_only(&[EventCategory::State, EventCategory::Reward]);
+    let log = evtclib::process_file_with_options(
+        "tests/logs/vg-20200421.zevtc",
+        Compression::Zip,
+        &options,
+    )
+    .unwrap();
+
+    assert!(!log.events().is_empty());
+    assert!(log.events().iter().all(|e| matches!(
+        e.kind().category(),
+        EventCategory::State | EventCategory::Reward
+    )));
+    assert!(log.was_rewarded());
+    // The metadata should be collected regardless of the kept events.
+    assert!(log.build_id().is_some());
+    assert!(!log.buff_database().is_empty());
+}
+
+#[test]
+fn test_exclude() {
+    let options = ProcessingOptions::new().exclude(EventCategory::Movement);
+    assert!(!options.keeps(EventCategory::Movement));
+    assert!(options.keeps(EventCategory::Damage));
+
+    let options = options.include(EventCategory::Movement);
+    assert!(options.keeps(EventCategory::Movement));
+}
+
+#[test]
+fn test_retain_raw_events() {
+    let evtc = parse("tests/logs/vg-20200421.zevtc");
+    let options = ProcessingOptions::new().retain_raw_events(true);
+    let log = evtclib::process_with_options(&evtc, &options).unwrap();
+    assert_eq!(log.raw_events(), Some(&evtc.events[..]));
+}
+
+#[test]
+fn test_resolve_agents() {
+    let evtc = parse("tests/logs/vg-20200421.zevtc");
+    let options = ProcessingOptions::new().resolve_agents(false);
+    let log = evtclib::process_with_options(&evtc, &options).unwrap();
+    assert!(log
+        .agents()
+        .iter()
+        .all(|a| a.first_aware() == 0 && a.master_agent().is_none()));
+}
+
+#[test]
+fn test_unknown_professions() {
+    let mut evtc = parse("tests/logs/vg-20200421.zevtc");
+    let player = evtc.agents.iter_mut().find(|a| a.is_player()).unwrap();
+    player.prof = 1000;
+    let addr = player.addr;
+
+    let result = evtclib::process(&evtc);
+    assert!(matches!(result, Err(EvtcError::InvalidProfession(1000))));
+
+    let options = ProcessingOptions::new().unknown_professions_fatal(false);
+    let log = evtclib::process_with_options(&evtc, &options).unwrap();
+    let player = log.agent_by_addr(addr).unwrap().as_player().unwrap();
+    assert_eq!(player.profession(), Profession::Unknown(1000));
+    assert_eq!(log.agents().len(), evtc.agents.len());
+}
+
+#[test]
+fn test_unknown_elite_specs() {
+    let mut evtc = parse("tests/logs/vg-20200421.zevtc");
+    let player = evtc.agents.iter_mut().find(|a| a.is_player()).unwrap();
+    player.is_elite = 1000;
+    let addr = player.addr;
+
+    let result = evtclib::process(&evtc);
+    assert!(matches!(result, Err(EvtcError::InvalidEliteSpec(1000))));
+
+    let options = ProcessingOptions::new().unknown_professions_fatal(false);
+    let log = evtclib::process_with_options(&evtc, &options).unwrap();
+    let player = log.agent_by_addr(addr).unwrap().as_player().unwrap();
+    assert_eq!(player.elite(), Some(EliteSpec::Unknown(1000)));
+    assert!(!player.profession().is_unknown());
+}
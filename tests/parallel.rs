@@ -0,0 +1,39 @@
+//! Tests for the parallel parsing and processing.
+//!
+//! The parallel versions should give the exact same results as the sequential ones.
+#![cfg(feature = "rayon")]
+
+use evtclib::{raw, ProcessingOptions};
+
+#[macro_use]
+mod common;
+use common::{assert_same_log, unzipped};
+
+fn check_parallel(path: &str) {
+    let data = unzipped(path);
+    let evtc = raw::parse_bytes(&data).unwrap();
+    assert_eq!(raw::parse_bytes_parallel(&data).unwrap(), evtc);
+
+    let sequential = evtclib::process(&evtc).unwrap();
+    let parallel =
+        evtclib::process_with_options(&evtc, &ProcessingOptions::new().parallel(true)).unwrap();
+    assert_same_log(&parallel, &sequential);
+}
+
+log_tests!(check_parallel {
+    test_parallel_rev0: "tests/logs/old-cairn-20180321.evtc.zip",
+    test_parallel_rev1: "tests/logs/vg-20200421.zevtc",
+    test_parallel_masters: "tests/logs/mama-20200427.zevtc",
+    test_parallel_wvw: "tests/logs/wvw-20211112.zevtc",
+    test_parallel_dropped: "tests/logs/ankka-20220303.zevtc",
+});
+
+#[test]
+fn test_parallel_unknown_revision() {
+    let mut data = unzipped("tests/logs/vg-20200421.zevtc");
+    data[12] = 42;
+    assert!(matches!(
+        raw::parse_bytes_parallel(&data),
+        Err(raw::ParseError::UnknownRevision(42))
+    ));
+}
@@ -1,7 +1,7 @@
 //! Tests that test parsing a complete log file and comparing some basic information.
 
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Read};
 
 use evtclib::{EliteSpec::*, Encounter, GameMode::*, Profession::*};
 
@@ -857,3 +857,23 @@ test! {
         (2, ":flumbum.4068", "Xynaliba", Mesmer, Some(Chronomancer)),
     ],
 }
+
+fn check_parse_bytes(log: &str) {
+    let mut archive = zip::ZipArchive::new(BufReader::new(File::open(log).unwrap())).unwrap();
+    let mut data = Vec::new();
+    archive.by_index(0).unwrap().read_to_end(&mut data).unwrap();
+
+    let from_bytes = evtclib::raw::parse_bytes(&data).expect("parsing bytes failed");
+    let from_file = evtclib::raw::parse_file(&data[..]).expect("parsing file failed");
+    assert_eq!(from_bytes, from_file);
+}
+
+#[test]
+fn parse_bytes_rev0() {
+    check_parse_bytes("tests/logs/old-cairn-20180321.evtc.zip");
+}
+
+#[test]
+fn parse_bytes_rev1() {
+    check_parse_bytes("tests/logs/qadim-20200427.zevtc");
+}
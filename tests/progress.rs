@@ -0,0 +1,193 @@
+//! Tests for the progress reporting and cancellation.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+
+use evtclib::raw::{
+    self, CancellationToken, ParseError, ParseLimits, Progress, ProgressMonitor, Stage,
+};
+use evtclib::{Compression, EvtcError, ProcessingOptions};
+
+mod common;
+use common::unzipped;
+
+const LOG: &str = "tests/logs/vg-20200421.zevtc";
+
+/// Create a monitor that records all reported progress.
+fn recording_monitor() -> (ProgressMonitor, Arc<Mutex<Vec<Progress>>>) {
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&reports);
+    let monitor = ProgressMonitor::new().on_progress(move |p| recorded.lock().unwrap().push(p));
+    (monitor, reports)
+}
+
+#[test]
+fn test_zip_progress() {
+    let (monitor, reports) = recording_monitor();
+    let input = BufReader::new(File::open(LOG).unwrap());
+    let evtc = raw::parse_zip_monitored(input, &ParseLimits::default(), &monitor).unwrap();
+    assert_eq!(evtc, raw::parse_file(&unzipped(LOG)[..]).unwrap());
+
+    let reports = reports.lock().unwrap();
+    let file_size = std::fs::metadata(LOG).unwrap().len();
+    assert!(reports.len() > 2);
+    assert!(reports.iter().all(|p| p.stage == Stage::Parsing));
+    assert!(reports.iter().all(|p| p.total_bytes == Some(file_size)));
+    assert!(reports
+        .windows(2)
+        .all(|w| w[0].events_decoded <= w[1].events_decoded));
+
+    let last = reports.last().unwrap();
+    assert_eq!(last.events_decoded, evtc.events.len() as u64);
+    assert_eq!(last.fraction(), Some(1.0));
+}
+
+#[test]
+fn test_uncompressed_progress() {
+    let data = unzipped(LOG);
+    let (monitor, reports) = recording_monitor();
+    let monitor = monitor.total_bytes(data.len() as u64);
+    let evtc = raw::parse_file_monitored(&data[..], &ParseLimits::default(), &monitor).unwrap();
+    assert_eq!(evtc, raw::parse_file(&data[..]).unwrap());
+
+    let reports = reports.lock().unwrap();
+    assert!(reports
+        .windows(2)
+        .all(|w| w[0].bytes_read <= w[1].bytes_read));
+    let last = reports.last().unwrap();
+    assert_eq!(last.bytes_read, data.len() as u64);
+    assert_eq!(last.events_decoded, evtc.events.len() as u64);
+}
+
+#[test]
+fn test_processing_progress() {
+    let (monitor, reports) = recording_monitor();
+    let options = ProcessingOptions::new().monitor(monitor);
+    let log = evtclib::process_file_with_options(LOG, Compression::Zip, &options).unwrap();
+
+    let reports = reports.lock().unwrap();
+    let parsing = reports.iter().filter(|p| p.stage == Stage::Parsing).count();
+    assert!(parsing > 0);
+    // The agents are resolved after the parsing, and the processing comes last.
+    let resolving = reports[parsing..]
+        .iter()
+        .take_while(|p| p.stage == Stage::Resolving)
+        .count();
+    assert!(resolving > 0);
+    let resolved = &reports[parsing + resolving - 1];
+    assert_eq!(resolved.events_processed, 2 * resolved.events_decoded);
+    assert_eq!(resolved.fraction(), Some(1.0));
+    assert!(reports[parsing + resolving..]
+        .iter()
+        .all(|p| p.stage == Stage::Processing));
+    let last = reports.last().unwrap();
+    assert_eq!(last.stage, Stage::Processing);
+    assert_eq!(last.events_processed, last.events_decoded);
+    assert!(last.events_processed >= log.events().len() as u64);
+    assert_eq!(last.fraction(), Some(1.0));
+}
+
+#[test]
+fn test_cancelled_before_start() {
+    let token = CancellationToken::new();
+    token.cancel();
+    let monitor = ProgressMonitor::new().cancellation_token(token);
+
+    let input = BufReader::new(File::open(LOG).unwrap());
+    assert!(matches!(
+        raw::parse_zip_monitored(input, &ParseLimits::default(), &monitor),
+        Err(ParseError::Cancelled)
+    ));
+
+    let evtc = raw::parse_file(&unzipped(LOG)[..]).unwrap();
+    let options = ProcessingOptions::new().monitor(monitor);
+    assert!(matches!(
+        evtclib::process_with_options(&evtc, &options),
+        Err(EvtcError::ParseError(ParseError::Cancelled))
+    ));
+}
+
+#[test]
+fn test_cancelled_while_parsing() {
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    let reports = Arc::new(Mutex::new(0));
+    let counter = Arc::clone(&reports);
+    let monitor = ProgressMonitor::new()
+        .cancellation_token(token)
+        .on_progress(move |p| {
+            *counter.lock().unwrap() += 1;
+            if p.events_decoded > 0 {
+                cancel.cancel();
+            }
+        });
+
+    let options = ProcessingOptions::new().monitor(monitor);
+    assert!(matches!(
+        evtclib::process_file_with_options(LOG, Compression::Zip, &options),
+        Err(EvtcError::ParseError(ParseError::Cancelled))
+    ));
+    // Once after the header, once after the first batch of events.
+    assert_eq!(*reports.lock().unwrap(), 2);
+}
+
+fn check_cancelled_while_resolving(options: ProcessingOptions) {
+    let evtc = raw::parse_file(&unzipped(LOG)[..]).unwrap();
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    let stages = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&stages);
+    let monitor = ProgressMonitor::new()
+        .cancellation_token(token)
+        .on_progress(move |p| {
+            recorded.lock().unwrap().push(p.stage);
+            if p.events_processed > 0 {
+                cancel.cancel();
+            }
+        });
+
+    let options = options.monitor(monitor);
+    assert!(matches!(
+        evtclib::process_with_options(&evtc, &options),
+        Err(EvtcError::ParseError(ParseError::Cancelled))
+    ));
+    let stages = stages.lock().unwrap();
+    assert!(stages.len() >= 2);
+    assert!(stages.iter().all(|&s| s == Stage::Resolving));
+}
+
+#[test]
+fn test_cancelled_while_resolving() {
+    check_cancelled_while_resolving(ProcessingOptions::new());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_cancelled_while_resolving_parallel() {
+    check_cancelled_while_resolving(ProcessingOptions::new().parallel(true));
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn test_gzip_progress() {
+    let data = unzipped(LOG);
+    let evtc = raw::parse_file(&data[..]).unwrap();
+    let mut compressed = Vec::new();
+    raw::write_gzip(&evtc, &mut compressed).unwrap();
+
+    let (monitor, reports) = recording_monitor();
+    let options = ProcessingOptions::new().monitor(monitor);
+    evtclib::process_stream_with_options(
+        std::io::Cursor::new(&compressed),
+        Compression::Auto,
+        &options,
+    )
+    .unwrap();
+
+    let reports = reports.lock().unwrap();
+    let last_parsing = reports.iter().rfind(|p| p.stage == Stage::Parsing).unwrap();
+    assert_eq!(last_parsing.bytes_read, compressed.len() as u64);
+    assert_eq!(last_parsing.total_bytes, Some(compressed.len() as u64));
+    assert_eq!(last_parsing.events_decoded, evtc.events.len() as u64);
+}
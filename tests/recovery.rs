@@ -0,0 +1,71 @@
+//! Tests for the recovery of incomplete logs.
+
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+
+use evtclib::raw;
+
+mod common;
+use common::unzipped;
+
+const LOG: &str = "tests/logs/vg-20200421.zevtc";
+
+#[test]
+fn test_complete_log() {
+    let data = unzipped(LOG);
+    let expected = raw::parse_file(&data[..]).unwrap();
+
+    let (evtc, report) = raw::parse_file_recovering(&data[..]).unwrap();
+    assert_eq!(evtc, expected);
+    assert!(report.is_complete(), "{:?}", report);
+    assert_eq!(report.events_end, data.len() as u64);
+
+    let zipped = BufReader::new(File::open(LOG).unwrap());
+    let (evtc, report) = raw::parse_zip_recovering(zipped).unwrap();
+    assert_eq!(evtc, expected);
+    assert!(report.is_complete(), "{:?}", report);
+}
+
+#[test]
+fn test_truncated_log() {
+    let data = unzipped(LOG);
+    let complete = raw::parse_file(&data[..]).unwrap();
+    // Cut the log in the middle of an event, 100 events before the end.
+    let cut = data.len() - 100 * 64 - 30;
+    let (evtc, report) = raw::parse_file_recovering(&data[..cut]).unwrap();
+
+    assert!(!report.is_complete());
+    assert_eq!(report.trailing_bytes, 34);
+    assert_eq!(report.events_end, cut as u64 - 34);
+    assert!(report.missing_log_end);
+    assert!(!report.checksum_failed);
+    assert!(report.error.is_none());
+    assert_eq!(
+        evtc.events[..],
+        complete.events[..complete.events.len() - 101]
+    );
+}
+
+#[test]
+fn test_truncated_header() {
+    let data = unzipped(LOG);
+    assert!(raw::parse_file_recovering(&data[..100]).is_err());
+}
+
+#[test]
+fn test_checksum_mismatch() {
+    let mut zipped = std::fs::read(LOG).unwrap();
+    // Change the checksum in the central directory, which is the one that is checked.
+    let central_directory = zipped.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+    zipped[central_directory + 16] ^= 0xFF;
+
+    assert!(raw::parse_zip(Cursor::new(&zipped)).is_err());
+
+    let (evtc, report) = raw::parse_zip_recovering(Cursor::new(&zipped)).unwrap();
+    assert!(!report.is_complete());
+    assert!(report.checksum_failed);
+    assert_eq!(report.trailing_bytes, 0);
+    assert!(!report.missing_log_end);
+    assert!(report.error.is_none());
+    assert_eq!(evtc, raw::parse_file(&unzipped(LOG)[..]).unwrap());
+}
@@ -0,0 +1,121 @@
+//! Tests for the header-only scanning of logs.
+
+use std::fs::{self};
+use std::path::PathBuf;
+
+use evtclib::{Compression, Encounter, GameMode};
+
+mod common;
+use common::unzipped;
+
+const LOG: &str = "tests/logs/vg-20200421.zevtc";
+
+/// A temporary directory that is removed when the test is done.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("evtclib-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_scan_file() {
+    let header = evtclib::scan_file(LOG).unwrap();
+    let log = evtclib::process_file(LOG, Compression::Zip).unwrap();
+
+    assert_eq!(header.arcdps_build(), "20200414");
+    assert_eq!(header.revision(), 1);
+    assert_eq!(header.encounter_id(), log.encounter_id());
+    assert_eq!(header.encounter(), Some(Encounter::ValeGuardian));
+    assert_eq!(header.game_mode(), Some(GameMode::Raid));
+    assert!(!header.is_generic());
+    assert_eq!(header.file_size(), fs::metadata(LOG).unwrap().len());
+
+    // The header keeps the order of the file, while the log sorts the agents by their address.
+    let mut players = log.players().map(|p| p.player()).collect::<Vec<_>>();
+    players.sort_by_key(|p| p.account_name());
+    let mut scanned = header.players().iter().collect::<Vec<_>>();
+    scanned.sort_by_key(|p| p.account_name());
+    assert_eq!(scanned, players);
+    assert_eq!(header.players().len(), 10);
+}
+
+#[test]
+fn test_scan_uncompressed() {
+    let dir = TempDir::new("scan");
+    let path = dir.0.join("vg.evtc");
+    fs::write(&path, unzipped(LOG)).unwrap();
+
+    let header = evtclib::scan_file(&path).unwrap();
+    let zipped = evtclib::scan_file(LOG).unwrap();
+    assert_eq!(header.players(), zipped.players());
+    assert_eq!(header.encounter(), zipped.encounter());
+    assert_eq!(header.file_size(), fs::metadata(&path).unwrap().len());
+}
+
+#[test]
+fn test_scan_invalid() {
+    let dir = TempDir::new("scan-invalid");
+    let path = dir.0.join("broken.zevtc");
+    fs::write(&path, b"not a log").unwrap();
+    assert!(evtclib::scan_file(&path).is_err());
+    assert!(evtclib::scan_file(dir.0.join("missing.zevtc")).is_err());
+}
+
+#[test]
+fn test_find_logs() {
+    let dir = TempDir::new("find-logs");
+    let character = dir.0.join("Vale Guardian").join("Some Character");
+    fs::create_dir_all(&character).unwrap();
+    fs::copy(LOG, character.join("20200421-183243.zevtc")).unwrap();
+    fs::copy(LOG, character.join("20200421-190000.zevtc")).unwrap();
+    fs::write(character.join("notes.txt"), b"not a log").unwrap();
+    fs::create_dir_all(dir.0.join("Empty")).unwrap();
+    fs::write(dir.0.join("loose.evtc"), unzipped(LOG)).unwrap();
+
+    let files = evtclib::find_logs(&dir.0)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let summary = files
+        .iter()
+        .map(|f| (f.boss_folder(), f.character_folder(), f.name()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            (None, None, "loose"),
+            (
+                Some("Vale Guardian"),
+                Some("Some Character"),
+                "20200421-183243"
+            ),
+            (
+                Some("Vale Guardian"),
+                Some("Some Character"),
+                "20200421-190000"
+            ),
+        ]
+    );
+
+    for file in &files {
+        let header = file.scan().unwrap();
+        assert_eq!(header.encounter(), Some(Encounter::ValeGuardian));
+    }
+}
+
+#[test]
+fn test_find_logs_missing_directory() {
+    let mut files = evtclib::find_logs("tests/does-not-exist");
+    assert!(files.next().unwrap().is_err());
+    assert!(files.next().is_none());
+}
@@ -0,0 +1,54 @@
+//! Tests for the lazy event parsing.
+//!
+//! The streaming functions should yield exactly the same events as the functions that parse the
+//! complete log at once.
+
+use evtclib::raw;
+
+mod common;
+use common::unzipped;
+
+#[test]
+fn test_event_reader() {
+    let data = unzipped("tests/logs/vg-20200421.zevtc");
+    let evtc = raw::parse_file(&data[..]).unwrap();
+
+    let mut input = &data[..];
+    let partial = raw::parser::parse_partial_file(&mut input).unwrap();
+    let events = raw::EventReader::new(input, partial.header.revision)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(events, evtc.events);
+}
+
+#[test]
+fn test_event_reader_unknown_revision() {
+    let result = raw::EventReader::new(&[][..], 42);
+    assert!(matches!(result, Err(raw::ParseError::UnknownRevision(42))));
+}
+
+#[test]
+fn test_stream_events() {
+    let data = unzipped("tests/logs/old-cairn-20180321.evtc.zip");
+    let log = evtclib::process(&raw::parse_file(&data[..]).unwrap()).unwrap();
+
+    let (partial, events) = evtclib::stream_events(&data[..]).unwrap();
+    assert_eq!(partial.agents.len(), log.agents().len());
+    let events = events.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(events, log.events());
+}
+
+#[test]
+fn test_stream_events_dropped() {
+    let data = unzipped("tests/logs/ankka-20220303.zevtc");
+    let log = evtclib::process(&raw::parse_file(&data[..]).unwrap()).unwrap();
+    assert!(!log.dropped_events().is_empty());
+
+    let (_, mut events) = evtclib::stream_events(&data[..]).unwrap();
+    for event in events.by_ref() {
+        event.unwrap();
+    }
+    assert_eq!(events.dropped_events(), log.dropped_events());
+    assert_eq!(events.diagnostics(), log.diagnostics());
+}
@@ -0,0 +1,154 @@
+//! Tests for the conversion of event times.
+
+use evtclib::raw::CbtStateChange;
+use evtclib::{EventCategory, EventKind, Log, TimeOrigin};
+
+mod common;
+use common::parse;
+
+fn process(path: &str) -> Log {
+    evtclib::process(&parse(path)).unwrap()
+}
+
+#[test]
+fn test_utc_timestamps() {
+    let log = process("tests/logs/vg-20200421.zevtc");
+    let metadata = log.metadata();
+    let start = log
+        .utc_timestamp_millis(metadata.log_start_time().unwrap())
+        .unwrap();
+    let end = log
+        .utc_timestamp_millis(metadata.log_end_time().unwrap())
+        .unwrap();
+    assert_eq!(
+        start / 1000,
+        i64::from(metadata.server_start_timestamp().unwrap())
+    );
+    assert_eq!(
+        end / 1000,
+        i64::from(metadata.server_end_timestamp().unwrap())
+    );
+    assert_eq!(
+        end - start,
+        (metadata.log_end_time().unwrap() - metadata.log_start_time().unwrap()) as i64
+    );
+}
+
+#[test]
+fn test_relative_time() {
+    let log = process("tests/logs/vg-20200421.zevtc");
+    let log_start = log
+        .events()
+        .iter()
+        .find(|e| matches!(e.kind(), EventKind::LogStart { .. }))
+        .unwrap();
+    assert_eq!(log.relative_time(log_start, TimeOrigin::LogStart), Some(0));
+
+    let first_hit = log
+        .events()
+        .iter()
+        .find(|e| match *e.kind() {
+            EventKind::Physical {
+                destination_agent_addr,
+                ..
+            } => log.is_boss(destination_agent_addr),
+            _ => false,
+        })
+        .unwrap();
+    assert!(
+        log.relative_time(first_hit, TimeOrigin::FightStart)
+            .unwrap()
+            >= 0
+    );
+    let fight_start = log.origin_time(TimeOrigin::FightStart);
+    assert!(log
+        .events()
+        .iter()
+        .any(|e| e.time() == fight_start && e.kind().category() == EventCategory::Damage));
+    assert!(fight_start > log.origin_time(TimeOrigin::LogStart));
+    assert!(
+        log.relative_time(log_start, TimeOrigin::FightStart)
+            .unwrap()
+            < 0
+    );
+}
+
+#[test]
+fn test_relative_time_generic() {
+    let log = process("tests/logs/wvw-20211112.zevtc");
+    assert_eq!(
+        log.origin_time(TimeOrigin::FightStart),
+        log.origin_time(TimeOrigin::LogStart)
+    );
+}
+
+#[test]
+fn test_without_timestamps() {
+    let mut evtc = parse("tests/logs/vg-20200421.zevtc");
+    evtc.events.retain(|e| {
+        !matches!(
+            e.is_statechange,
+            CbtStateChange::LogStart | CbtStateChange::LogEnd
+        )
+    });
+    let log = evtclib::process(&evtc).unwrap();
+    assert_eq!(log.utc_timestamp_millis(0), None);
+    assert_eq!(
+        log.origin_time(TimeOrigin::LogStart),
+        log.events()[0].time()
+    );
+}
+
+#[test]
+fn test_huge_times() {
+    let mut evtc = parse("tests/logs/vg-20200421.zevtc");
+    // A corrupted LogStart time must not break the conversion with the LogEnd event.
+    for event in &mut evtc.events {
+        if event.is_statechange == CbtStateChange::LogStart {
+            event.time = u64::MAX;
+        }
+    }
+    let log = evtclib::process(&evtc).unwrap();
+    let end_time = log.metadata().log_end_time().unwrap();
+    assert_eq!(
+        log.utc_timestamp_millis(end_time).unwrap() / 1000,
+        i64::from(log.metadata().server_end_timestamp().unwrap())
+    );
+    assert_eq!(log.utc_timestamp_millis(u64::MAX), None);
+
+    let log_start = log
+        .events()
+        .iter()
+        .find(|e| matches!(e.kind(), EventKind::LogStart { .. }))
+        .unwrap();
+    assert_eq!(log.relative_time(log_start, TimeOrigin::FightStart), None);
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_to_utc() {
+    let log = process("tests/logs/vg-20200421.zevtc");
+    let time = log.origin_time(TimeOrigin::FightStart);
+    let date = log.to_utc(time).unwrap();
+    assert_eq!(
+        Some(date.timestamp_millis()),
+        log.utc_timestamp_millis(time)
+    );
+    assert_eq!(date.format("%Y-%m-%d").to_string(), "2020-04-21");
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_to_offset_date_time() {
+    let log = process("tests/logs/vg-20200421.zevtc");
+    let time = log.origin_time(TimeOrigin::FightStart);
+    let date = log.to_offset_date_time(time).unwrap();
+    assert_eq!(
+        Some((date.unix_timestamp_nanos() / 1_000_000) as i64),
+        log.utc_timestamp_millis(time)
+    );
+    assert_eq!(
+        (date.year(), u8::from(date.month()), date.day()),
+        (2020, 4, 21)
+    );
+}
@@ -0,0 +1,153 @@
+//! Tests for writing evtc files.
+//!
+//! Writing a log and parsing it back should give back the exact same log.
+
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+
+use evtclib::raw;
+
+#[macro_use]
+mod common;
+
+fn check_roundtrip(path: &str) {
+    let file = BufReader::new(File::open(path).unwrap());
+    let evtc = raw::parse_zip(file).unwrap();
+
+    let mut output = Vec::new();
+    raw::write_file(&evtc, &mut output).unwrap();
+    let parsed = raw::parse_file(&output[..]).unwrap();
+    assert_eq!(parsed, evtc);
+
+    let mut zipped = Cursor::new(Vec::new());
+    raw::write_zip(&evtc, &mut zipped).unwrap();
+    zipped.set_position(0);
+    let parsed = raw::parse_zip(zipped).unwrap();
+    assert_eq!(parsed, evtc);
+}
+
+log_tests!(check_roundtrip {
+    test_roundtrip_rev0: "tests/logs/old-cairn-20180321.evtc.zip",
+    test_roundtrip_rev1: "tests/logs/vg-20200421.zevtc",
+    test_roundtrip_wvw: "tests/logs/wvw-20211112.zevtc",
+});
+
+#[test]
+fn test_output_is_stable() {
+    let file = BufReader::new(File::open("tests/logs/vg-20200421.zevtc").unwrap());
+    let evtc = raw::parse_zip(file).unwrap();
+
+    let mut first = Vec::new();
+    raw::write_file(&evtc, &mut first).unwrap();
+    let mut second = Vec::new();
+    raw::write_file(&raw::parse_file(&first[..]).unwrap(), &mut second).unwrap();
+    assert!(
+        first == second,
+        "writing the log twice gave different results"
+    );
+}
+
+#[test]
+fn test_unknown_revision() {
+    let mut evtc = raw::Evtc::default();
+    evtc.header.arcdps_build = "20200101".into();
+    evtc.header.revision = 42;
+    let result = raw::write_file(&evtc, Vec::new());
+    assert!(matches!(result, Err(raw::WriteError::UnknownRevision(42))));
+}
+
+#[test]
+fn test_count_mismatch() {
+    let file = BufReader::new(File::open("tests/logs/vg-20200421.zevtc").unwrap());
+    let evtc = raw::parse_zip(file).unwrap();
+
+    let mut changed = evtc.clone();
+    changed.agents.pop();
+    let result = raw::write_file(&changed, Vec::new());
+    assert!(matches!(result, Err(raw::WriteError::CountMismatch)));
+
+    let mut changed = evtc.clone();
+    changed.skill_count += 1;
+    let result = raw::write_file(&changed, Vec::new());
+    assert!(matches!(result, Err(raw::WriteError::CountMismatch)));
+
+    let mut changed = evtc;
+    changed.agents.pop();
+    changed.header.agent_count -= 1;
+    let mut output = Vec::new();
+    raw::write_file(&changed, &mut output).unwrap();
+    assert_eq!(raw::parse_file(&output[..]).unwrap(), changed);
+}
+
+#[test]
+fn test_unknown_statechanges() {
+    let file = BufReader::new(File::open("tests/logs/vg-20200421.zevtc").unwrap());
+    let mut evtc = raw::parse_zip(file).unwrap();
+    evtc.unknown_statechanges = 2;
+    let result = raw::write_file(&evtc, Vec::new());
+    assert!(matches!(
+        result,
+        Err(raw::WriteError::UnknownStateChanges(2))
+    ));
+
+    evtc.unknown_statechanges = 0;
+    let mut output = Vec::new();
+    raw::write_file(&evtc, &mut output).unwrap();
+    assert_eq!(raw::parse_file(&output[..]).unwrap(), evtc);
+}
+
+#[test]
+fn test_rev0_value_out_of_range() {
+    let mut evtc = raw::Evtc::default();
+    evtc.header.arcdps_build = "20200101".into();
+    evtc.events.push(raw::CbtEvent {
+        skillid: 0x1_0000,
+        ..Default::default()
+    });
+    let result = raw::write_file(&evtc, Vec::new());
+    assert!(matches!(result, Err(raw::WriteError::InvalidData)));
+}
+
+#[test]
+fn test_statechange_data() {
+    // An Effect2 event stores its duration in the bytes of iff, buff, result and is_activation,
+    // which are not valid values for the typed fields.
+    let mut bytes = [0u8; 64];
+    bytes[48..52].copy_from_slice(&0xdead_beefu32.to_le_bytes());
+    bytes[56] = raw::CbtStateChange::Effect2 as u8;
+    bytes[57] = 1;
+    let event = raw::parser::parse_event_rev1_bytes(&bytes).unwrap();
+    assert_eq!(
+        event.statechange_data,
+        raw::StateChangeData::Effect2 {
+            duration: 0xdead_beef,
+            tracking_id: 0,
+            orientation_x: 0,
+        }
+    );
+    assert_eq!(event.iff, raw::IFF::None);
+    assert!(event.is_flanking);
+
+    let mut output = Vec::new();
+    raw::writer::write_event_rev1(&event, &mut output).unwrap();
+    assert_eq!(&output[..], &bytes[..]);
+}
+
+#[test]
+fn test_unknown_flags() {
+    let mut bytes = [0u8; 64];
+    bytes[48] = 100;
+    bytes[50] = 101;
+    bytes[51] = 102;
+    bytes[52] = 103;
+    let event = raw::parser::parse_event_rev1_bytes(&bytes).unwrap();
+    assert_eq!(event.iff, raw::IFF::Other(100));
+    assert_eq!(event.result, raw::CbtResult::Other(101));
+    assert_eq!(event.is_activation, raw::CbtActivation::Other(102));
+    assert_eq!(event.is_buffremove, raw::CbtBuffRemove::Other(103));
+    assert_eq!(event.statechange_data, raw::StateChangeData::None);
+
+    let mut output = Vec::new();
+    raw::writer::write_event_rev1(&event, &mut output).unwrap();
+    assert_eq!(&output[..], &bytes[..]);
+}